@@ -29,6 +29,12 @@ pub mod vram {
     pub const BASE: u32 = 0x18000000;
     /// VRAM size (6 MB)
     pub const SIZE: usize = 6 * 1024 * 1024;
+    /// Size of each of the two VRAM banks (3 MB)
+    pub const BANK_SIZE: usize = SIZE / 2;
+    /// VRAM bank A base address
+    pub const BANK_A_BASE: u32 = BASE;
+    /// VRAM bank B base address
+    pub const BANK_B_BASE: u32 = BASE + BANK_SIZE as u32;
 }
 
 /// ARM9-specific memory regions
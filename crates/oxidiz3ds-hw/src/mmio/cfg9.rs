@@ -0,0 +1,44 @@
+//! # References
+//! - <https://www.3dbrew.org/wiki/CONFIG9_Registers>
+
+/// CFG9 register block base address (ARM9 only)
+pub const BASE: u32 = 0x10000000;
+
+/// CFG9 register block end address (exclusive)
+pub const END: u32 = 0x10001000;
+
+/// CFG9 register offsets (relative to `BASE`)
+pub mod registers {
+    /// ARM9 access protection (8-bit, bits can only be set)
+    pub const SYSPROT9: u32 = 0x000;
+
+    /// ARM11 access protection (8-bit, bits can only be set)
+    pub const SYSPROT11: u32 = 0x001;
+
+    /// ARM11 reset control (8-bit)
+    pub const RST11: u32 = 0x002;
+
+    /// Debug control (16-bit)
+    pub const DEBUGCTL: u32 = 0x004;
+
+    /// Gamecard controller select (16-bit)
+    pub const CARD_CTL: u32 = 0x00C;
+
+    /// Gamecard slot power (8-bit)
+    pub const CARD_POWER: u32 = 0x010;
+
+    /// SD/MMC controller configuration (16-bit)
+    pub const SDMMCCTL: u32 = 0x020;
+
+    /// New 3DS ARM9 extended memory control (32-bit)
+    pub const EXTMEMCNT9: u32 = 0x200;
+
+    /// Console type (read-only, 32-bit): zero on Old 3DS
+    pub const MPCORECFG: u32 = 0xFFC;
+}
+
+/// `EXTMEMCNT9` bit flags
+pub mod extmemcnt9 {
+    /// Map the extra 512KB of ARM9 memory at 0x08100000 (New 3DS only)
+    pub const ENABLE: u32 = 1 << 0;
+}
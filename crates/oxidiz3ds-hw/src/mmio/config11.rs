@@ -0,0 +1,68 @@
+//! # References
+//! - <https://www.3dbrew.org/wiki/CONFIG11_Registers>
+
+/// CONFIG11 register block base address
+pub const BASE: u32 = 0x10140000;
+
+/// CONFIG11 register block end address (exclusive)
+pub const END: u32 = 0x10142000;
+
+/// CONFIG11 register offsets (relative to `BASE`)
+pub mod registers {
+    /// Shared WRAM code block mapping (8 x 8-bit, one per 32KB block)
+    pub const SHAREDWRAM_32K_CODE: u32 = 0x000;
+
+    /// Shared WRAM data block mapping (8 x 8-bit, one per 32KB block)
+    pub const SHAREDWRAM_32K_DATA: u32 = 0x008;
+
+    /// FIQ mask (8-bit)
+    pub const FIQ_MASK: u32 = 0x104;
+
+    /// GPU/peripheral power and clock control (32-bit)
+    pub const GPUPROT: u32 = 0x140;
+
+    /// MPCore clock control (16-bit)
+    pub const MPCORE_CLKCNT: u32 = 0x1300;
+
+    /// MPCore control (16-bit)
+    pub const MPCORE_CNT: u32 = 0x1304;
+
+    /// MPCore per-core boot control, one byte per core (cores 0-3)
+    pub const MPCORE_BOOTCNT: u32 = 0x1310;
+}
+
+/// `MPCORE_BOOTCNT` bit flags
+pub mod bootcnt {
+    /// Written to release the core from reset
+    pub const RELEASE: u8 = 1 << 0;
+
+    /// Read-only: set once the core has left reset
+    pub const RUNNING: u8 = 1 << 4;
+}
+
+/// `SHAREDWRAM_32K_CODE`/`SHAREDWRAM_32K_DATA` layout
+pub mod sharedwram {
+    /// Number of 32KB blocks in each of the code and data halves
+    pub const BLOCKS: u32 = 8;
+
+    /// Size of one block in bytes
+    pub const BLOCK_SIZE: u32 = 32 * 1024;
+
+    /// Base address of the code half; the data half follows it
+    pub const CODE_BASE: u32 = 0x1FF00000;
+
+    /// Base address of the data half
+    pub const DATA_BASE: u32 = CODE_BASE + BLOCKS * BLOCK_SIZE;
+
+    /// Master: clear for the ARM9/ARM11, set for the DSP
+    pub const MASTER_DSP: u8 = 1 << 0;
+
+    /// Slot (0-7) the block appears at within its half
+    pub const SLOT_SHIFT: u8 = 2;
+
+    /// Mask of the slot field after shifting
+    pub const SLOT_MASK: u8 = 0x7;
+
+    /// Block is mapped
+    pub const ENABLE: u8 = 1 << 7;
+}
@@ -0,0 +1,71 @@
+//! # References
+//! - <https://www.3dbrew.org/wiki/DSP_Registers>
+
+/// DSP register block base address (ARM11 only)
+pub const BASE: u32 = 0x10203000;
+
+/// DSP register block end address (exclusive)
+pub const END: u32 = 0x10204000;
+
+/// DSP register offsets (relative to `BASE`), all 16-bit
+pub mod registers {
+    /// Data transfer FIFO
+    pub const PDATA: u32 = 0x000;
+
+    /// Transfer address in DSP memory
+    pub const PADR: u32 = 0x008;
+
+    /// Configuration
+    pub const PCFG: u32 = 0x00C;
+
+    /// Status (read-only)
+    pub const PSTS: u32 = 0x010;
+
+    /// ARM11-to-DSP semaphore
+    pub const PSEM: u32 = 0x014;
+
+    /// DSP-to-ARM11 semaphore interrupt mask
+    pub const PMASK: u32 = 0x018;
+
+    /// Write 1 to clear bits in `SEM`
+    pub const PCLEAR: u32 = 0x01C;
+
+    /// DSP-to-ARM11 semaphore
+    pub const SEM: u32 = 0x020;
+
+    /// Command registers (ARM11 to DSP), 8 bytes apart
+    pub const CMD0: u32 = 0x024;
+
+    /// Reply registers (DSP to ARM11), 8 bytes apart
+    pub const REP0: u32 = 0x028;
+
+    /// Number of command/reply register pairs
+    pub const NUM_CHANNELS: u32 = 3;
+
+    /// Distance between consecutive CMD (or REP) registers
+    pub const CHANNEL_STRIDE: u32 = 0x008;
+}
+
+/// `PCFG` bit flags
+pub mod pcfg {
+    /// Hold the DSP core in reset while set
+    pub const RESET: u16 = 1 << 0;
+}
+
+/// `PSTS` bit flags
+pub mod psts {
+    /// DSP peripheral is in reset
+    pub const PERIPHERAL_RESET: u16 = 1 << 2;
+
+    /// Write FIFO is empty
+    pub const WRITE_FIFO_EMPTY: u16 = 1 << 8;
+
+    /// An unmasked `SEM` bit is set
+    pub const SEM_IRQ: u16 = 1 << 9;
+
+    /// `REPn` holds a reply the ARM11 hasn't read yet (bit 10 + n)
+    pub const REP_NEW_SHIFT: u16 = 10;
+
+    /// `CMDn` holds a command the DSP hasn't read yet (bit 13 + n)
+    pub const CMD_UNREAD_SHIFT: u16 = 13;
+}
@@ -0,0 +1,75 @@
+//! # References
+//! - <https://www.3dbrew.org/wiki/ARM11_Interrupts>
+//! - <https://developer.arm.com/documentation/ddi0360/f/mpcore-private-memory-region>
+
+/// MPCore private memory region base address (ARM11 only)
+///
+/// Contains the SCU, the GIC CPU interface, the private timers, and the GIC
+/// distributor.
+pub const BASE: u32 = 0x17E00000;
+
+/// MPCore private memory region end address (exclusive)
+pub const END: u32 = 0x17E02000;
+
+/// Number of interrupt IDs handled by the distributor
+pub const NUM_INTERRUPTS: usize = 128;
+
+/// Interrupt ID returned by the acknowledge register when nothing is pending
+pub const SPURIOUS_INTERRUPT_ID: u32 = 1023;
+
+/// GIC register offsets (relative to `BASE`)
+pub mod registers {
+    /// CPU interface control register
+    pub const CPU_CONTROL: u32 = 0x100;
+
+    /// CPU interface priority mask register
+    pub const CPU_PRIORITY_MASK: u32 = 0x104;
+
+    /// CPU interface binary point register
+    pub const CPU_BINARY_POINT: u32 = 0x108;
+
+    /// Interrupt acknowledge register (read-only)
+    pub const CPU_ACKNOWLEDGE: u32 = 0x10C;
+
+    /// End of interrupt register (write-only)
+    pub const CPU_END_OF_INTERRUPT: u32 = 0x110;
+
+    /// Running priority register (read-only)
+    pub const CPU_RUNNING_PRIORITY: u32 = 0x114;
+
+    /// Highest pending interrupt register (read-only)
+    pub const CPU_HIGHEST_PENDING: u32 = 0x118;
+
+    /// Distributor control register
+    pub const DIST_CONTROL: u32 = 0x1000;
+
+    /// Distributor controller type register (read-only)
+    pub const DIST_TYPE: u32 = 0x1004;
+
+    /// Set-enable registers (one bit per interrupt)
+    pub const DIST_SET_ENABLE: u32 = 0x1100;
+
+    /// Clear-enable registers (one bit per interrupt)
+    pub const DIST_CLEAR_ENABLE: u32 = 0x1180;
+
+    /// Set-pending registers (one bit per interrupt)
+    pub const DIST_SET_PENDING: u32 = 0x1200;
+
+    /// Clear-pending registers (one bit per interrupt)
+    pub const DIST_CLEAR_PENDING: u32 = 0x1280;
+
+    /// Active bit registers (read-only, one bit per interrupt)
+    pub const DIST_ACTIVE: u32 = 0x1300;
+
+    /// Priority registers (one byte per interrupt)
+    pub const DIST_PRIORITY: u32 = 0x1400;
+
+    /// CPU target registers (one byte per interrupt)
+    pub const DIST_TARGET: u32 = 0x1800;
+
+    /// Interrupt configuration registers (two bits per interrupt)
+    pub const DIST_CONFIG: u32 = 0x1C00;
+
+    /// Software interrupt register (write-only)
+    pub const DIST_SOFTWARE_INTERRUPT: u32 = 0x1F00;
+}
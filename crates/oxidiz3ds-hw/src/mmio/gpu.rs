@@ -9,7 +9,16 @@ pub const BASE: u32 = 0x10400000;
 pub const END: u32 = 0x10500000;
 
 /// GPU register offsets (relative to `BASE`)
+///
+/// The framebuffer registers belong to the two PDC (display controller) blocks:
+/// PDC0 at `0x400` drives the top screen and PDC1 at `0x500` the bottom screen.
 pub mod registers {
+    /// Top screen total number of scanlines per frame (PDC0 VTotal)
+    pub const PDC0_VTOTAL: u32 = 0x424;
+
+    /// Top screen current scanline (PDC0 VCount, read-only)
+    pub const PDC0_VCOUNT: u32 = 0x454;
+
     /// Top screen left framebuffer address register
     ///
     /// Reference: <https://www.3dbrew.org/wiki/GPU/External_Registers#Framebuffers>
@@ -21,19 +30,64 @@ pub mod registers {
     /// Top screen framebuffer stride (bytes per row) register
     pub const FRAMEBUFFER_TOP_STRIDE: u32 = 0x490;
 
+    /// Top screen display control register (PDC0 control, see [`super::pdc_control`])
+    pub const PDC0_CONTROL: u32 = 0x474;
+
+    /// Top screen framebuffer select register (bit 0: 0 = framebuffer A, 1 = B)
+    pub const FRAMEBUFFER_TOP_SELECT: u32 = 0x478;
+
     /// Top screen right framebuffer address register (for 3D mode)
+    ///
+    /// This is also the framebuffer B address chosen by `FRAMEBUFFER_TOP_SELECT`.
     pub const FRAMEBUFFER_TOP_RIGHT: u32 = 0x494;
 
+    /// Bottom screen total number of scanlines per frame (PDC1 VTotal)
+    pub const PDC1_VTOTAL: u32 = 0x524;
+
+    /// Bottom screen current scanline (PDC1 VCount, read-only)
+    pub const PDC1_VCOUNT: u32 = 0x554;
+
     /// Bottom screen framebuffer address register
     pub const FRAMEBUFFER_BOTTOM_LEFT: u32 = 0x568;
 
     /// Bottom screen framebuffer pixel format register
     pub const FRAMEBUFFER_BOTTOM_FORMAT: u32 = 0x570;
 
+    /// Bottom screen display control register (PDC1 control, see [`super::pdc_control`])
+    pub const PDC1_CONTROL: u32 = 0x574;
+
+    /// Bottom screen framebuffer select register (bit 0: 0 = framebuffer A, 1 = B)
+    pub const FRAMEBUFFER_BOTTOM_SELECT: u32 = 0x578;
+
     /// Bottom screen framebuffer stride register
     pub const FRAMEBUFFER_BOTTOM_STRIDE: u32 = 0x590;
+
+    /// Bottom screen framebuffer B address register
+    pub const FRAMEBUFFER_BOTTOM_B: u32 = 0x594;
+}
+
+/// Bits of the PDC control registers (`PDC0_CONTROL`, `PDC1_CONTROL`)
+///
+/// Firmware typically writes `0x10501`: scanout enabled, with only the VBlank
+/// interrupt unmasked.
+///
+/// Reference: <https://www.3dbrew.org/wiki/GPU/External_Registers#LCD_Source_Framebuffer_Setup>
+pub mod pdc_control {
+    /// Scan the framebuffer out to the screen
+    pub const ENABLE: u32 = 1 << 0;
+    /// Mask the HBlank interrupt
+    pub const DISABLE_HBLANK_IRQ: u32 = 1 << 8;
+    /// Mask the VBlank interrupt
+    pub const DISABLE_VBLANK_IRQ: u32 = 1 << 9;
+    /// Mask the error interrupt
+    pub const DISABLE_ERROR_IRQ: u32 = 1 << 10;
+    /// Enable output (set by firmware together with `ENABLE`)
+    pub const ENABLE_OUTPUT: u32 = 1 << 16;
 }
 
+/// Default VTotal (scanlines per frame, including VBlank) programmed by the boot ROM
+pub const DEFAULT_VTOTAL: u32 = 413;
+
 /// Pixel format values for framebuffer format registers
 ///
 /// These correspond to bits 0-2 of the format register.
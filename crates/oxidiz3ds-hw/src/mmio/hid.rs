@@ -0,0 +1,36 @@
+//! # References
+//! - <https://www.3dbrew.org/wiki/HID_Registers>
+
+/// HID register block base address
+pub const BASE: u32 = 0x10146000;
+
+/// HID register block end address (exclusive)
+pub const END: u32 = 0x10147000;
+
+/// HID register offsets (relative to `BASE`)
+pub mod registers {
+    /// Button state (16-bit, read-only, a cleared bit means pressed)
+    pub const PAD: u32 = 0x000;
+
+    /// Button interrupt control (16-bit)
+    pub const PADCNT: u32 = 0x002;
+}
+
+/// `PAD` button bits
+pub mod buttons {
+    pub const A: u16 = 1 << 0;
+    pub const B: u16 = 1 << 1;
+    pub const SELECT: u16 = 1 << 2;
+    pub const START: u16 = 1 << 3;
+    pub const RIGHT: u16 = 1 << 4;
+    pub const LEFT: u16 = 1 << 5;
+    pub const UP: u16 = 1 << 6;
+    pub const DOWN: u16 = 1 << 7;
+    pub const R: u16 = 1 << 8;
+    pub const L: u16 = 1 << 9;
+    pub const X: u16 = 1 << 10;
+    pub const Y: u16 = 1 << 11;
+
+    /// All buttons reported by `PAD` (bits 12-15 always read as zero)
+    pub const ALL: u16 = 0x0FFF;
+}
@@ -0,0 +1,82 @@
+//! # References
+//! - <https://www.3dbrew.org/wiki/I2C_Registers>
+//! - <https://www.3dbrew.org/wiki/I2C_Registers#Device_list>
+//! - <https://www.3dbrew.org/wiki/MCU_Services>
+
+/// I2C bus register block base addresses, indexed by bus ID
+pub const BUS_BASES: [u32; 3] = [0x10161000, 0x10144000, 0x10148000];
+
+/// Size of the MMIO region mapped for each bus
+pub const BUS_SIZE: u32 = 0x1000;
+
+/// I2C register offsets (relative to a bus base)
+pub mod registers {
+    /// Data register (8-bit)
+    pub const DATA: u32 = 0x00;
+
+    /// Control register (8-bit)
+    pub const CNT: u32 = 0x01;
+
+    /// Extended control register (16-bit)
+    pub const CNTEX: u32 = 0x02;
+
+    /// Clock configuration register (16-bit)
+    pub const SCL: u32 = 0x04;
+}
+
+/// Control register bit flags
+pub mod cnt {
+    /// Stop condition after this byte
+    pub const STOP: u8 = 1 << 0;
+    /// Start condition before this byte (byte is a device address)
+    pub const START: u8 = 1 << 1;
+    /// Pause
+    pub const PAUSE: u8 = 1 << 2;
+    /// Acknowledge received (write) / send acknowledge (read)
+    pub const ACK: u8 = 1 << 4;
+    /// Transfer direction (0 = write, 1 = read)
+    pub const READ: u8 = 1 << 5;
+    /// Interrupt enable
+    pub const IRQ_ENABLE: u8 = 1 << 6;
+    /// Start transfer / busy
+    pub const ENABLE: u8 = 1 << 7;
+}
+
+/// I2C device addresses (7-bit address shifted left by one, as written to DATA)
+pub mod devices {
+    /// MCU (power management, RTC, buttons) on bus 1
+    pub const MCU_BUS: usize = 1;
+    pub const MCU_ADDR: u8 = 0x4A;
+}
+
+/// MCU register indices
+pub mod mcu {
+    /// Firmware version (major)
+    pub const VERSION_HIGH: u8 = 0x00;
+    /// Firmware version (minor)
+    pub const VERSION_LOW: u8 = 0x01;
+    /// Battery level in percent
+    pub const BATTERY_PERCENT: u8 = 0x0B;
+    /// Power/shell status flags
+    pub const POWER_STATUS: u8 = 0x0F;
+    /// Interrupt status (4 bytes, cleared on read)
+    pub const INTERRUPT_STATUS: u8 = 0x10;
+    /// Interrupt mask (4 bytes)
+    pub const INTERRUPT_MASK: u8 = 0x18;
+    /// Power control (poweroff/reboot requests)
+    pub const POWER_CONTROL: u8 = 0x20;
+    /// RTC time (7 BCD bytes: second, minute, hour, weekday, day, month, year)
+    pub const RTC_TIME: u8 = 0x30;
+
+    /// Interrupt status bit: power button pressed
+    pub const IRQ_POWER_PRESS: u32 = 1 << 0;
+    /// Interrupt status bit: power button held
+    pub const IRQ_POWER_HELD: u32 = 1 << 1;
+    /// Interrupt status bit: HOME button pressed
+    pub const IRQ_HOME_PRESS: u32 = 1 << 2;
+    /// Interrupt status bit: HOME button released
+    pub const IRQ_HOME_RELEASE: u32 = 1 << 3;
+
+    /// Power status bit: shell is open
+    pub const POWER_STATUS_SHELL_OPEN: u8 = 1 << 1;
+}
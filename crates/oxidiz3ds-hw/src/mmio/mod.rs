@@ -1,2 +1,8 @@
+pub mod cfg9;
+pub mod config11;
+pub mod dsp;
+pub mod gic;
 pub mod gpu;
+pub mod hid;
+pub mod i2c;
 pub mod sdmmc;
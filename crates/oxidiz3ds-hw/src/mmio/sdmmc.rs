@@ -25,7 +25,7 @@ pub mod registers {
     /// Stop/abort command register
     pub const STOP: u32 = 0x008;
 
-    /// Block count register
+    /// Block count register (16-bit FIFO transfers)
     pub const BLKCOUNT: u32 = 0x00a;
 
     /// Response data registers (8 registers × 2 bytes each)
@@ -38,69 +38,125 @@ pub mod registers {
     pub const RESP6: u32 = 0x018;
     pub const RESP7: u32 = 0x01a;
 
-    /// Status register 0 (includes card detection flags)
+    /// Status register 0 (command/data end, card detection flags)
     pub const STATUS0: u32 = 0x01c;
 
-    /// Status register 1
+    /// Status register 1 (errors, FIFO readiness, card state)
     pub const STATUS1: u32 = 0x01e;
 
-    /// Interrupt status register
-    pub const IRQ_STAT: u32 = 0x020;
+    /// Interrupt mask for `STATUS0`
+    pub const IRQ_MASK0: u32 = 0x020;
 
-    /// Interrupt mask register
-    pub const IRQ_MASK: u32 = 0x024;
+    /// Interrupt mask for `STATUS1`
+    pub const IRQ_MASK1: u32 = 0x022;
 
-    /// Clock control register
-    pub const CLK_CTL: u32 = 0x028;
+    /// Card clock control register
+    pub const CLKCTL: u32 = 0x024;
 
-    /// Block length register
-    pub const BLKLEN: u32 = 0x02a;
+    /// Block length register (16-bit FIFO transfers)
+    pub const BLKLEN: u32 = 0x026;
 
-    /// Option register
-    pub const OPTION: u32 = 0x02c;
+    /// Card option register (bus width, timeouts)
+    pub const OPT: u32 = 0x028;
 
-    /// FIFO control register
-    pub const FIFO_CTL: u32 = 0x034;
+    /// Error detail register 0 (CRC and end bit errors)
+    pub const ERROR_DETAIL_STATUS0: u32 = 0x02c;
 
-    /// Data FIFO register (16-bit access)
-    pub const DATA_FIFO: u32 = 0x030;
+    /// Error detail register 1 (timeouts)
+    pub const ERROR_DETAIL_STATUS1: u32 = 0x02e;
 
-    /// Data control register
-    pub const DATA_CTL: u32 = 0x038;
+    /// 16-bit data FIFO
+    pub const FIFO: u32 = 0x030;
+
+    /// SDIO card interrupt control register (not a FIFO control register: the FIFO
+    /// is cleared through bit 10 of [`DATA32_IRQ`])
+    pub const SDIO_CARD_IRQ_CTL: u32 = 0x034;
+
+    /// Data control register (selects the 32-bit FIFO)
+    pub const DATA_CTL: u32 = 0x0d8;
 
     /// Software reset register
-    pub const SOFT_RST: u32 = 0x100;
+    pub const RESET: u32 = 0x0e0;
+
+    /// 32-bit FIFO control and status register (readiness, FIFO clear, IRQ enables)
+    ///
+    /// 3dbrew's REG_SDDATACTL32; writing bit 10 (0x400) clears the FIFO.
+    pub const DATA32_IRQ: u32 = 0x100;
+
+    /// Block length register (32-bit FIFO transfers)
+    pub const DATA32_BLK_LEN: u32 = 0x104;
+
+    /// Block count register (32-bit FIFO transfers)
+    pub const DATA32_BLK_COUNT: u32 = 0x108;
+
+    /// 32-bit data FIFO
+    pub const DATA32_FIFO: u32 = 0x10c;
+
+    // Names from the old, incorrect register map, kept for one release. They now
+    // resolve to the register they were meant to name, not to their old offsets.
+
+    /// Old name for [`STATUS0`], which holds the interrupt status
+    #[deprecated(note = "use `registers::STATUS0`")]
+    pub const IRQ_STAT: u32 = STATUS0;
+
+    /// Old name for [`IRQ_MASK0`]
+    #[deprecated(note = "use `registers::IRQ_MASK0`")]
+    pub const IRQ_MASK: u32 = IRQ_MASK0;
+
+    /// Old name for [`CLKCTL`]
+    #[deprecated(note = "use `registers::CLKCTL`")]
+    pub const CLK_CTL: u32 = CLKCTL;
+
+    /// Old name for [`OPT`]
+    #[deprecated(note = "use `registers::OPT`")]
+    pub const OPTION: u32 = OPT;
+
+    /// Old name for [`FIFO`]
+    #[deprecated(note = "use `registers::FIFO`")]
+    pub const DATA_FIFO: u32 = FIFO;
+
+    /// Old name for 0x034, which is [`SDIO_CARD_IRQ_CTL`] rather than a FIFO control
+    /// register
+    #[deprecated(note = "use `registers::SDIO_CARD_IRQ_CTL`")]
+    pub const FIFO_CTL: u32 = SDIO_CARD_IRQ_CTL;
+
+    /// Old name for [`RESET`]
+    #[deprecated(note = "use `registers::RESET`")]
+    pub const SOFT_RST: u32 = RESET;
 
-    /// SD clock control register
-    pub const SD_CLK_CTL: u32 = 0x104;
+    /// Old name for the card clock control register, [`CLKCTL`]
+    #[deprecated(note = "use `registers::CLKCTL`")]
+    pub const SD_CLK_CTL: u32 = CLKCTL;
 }
 
 /// SDMMC command bit flags
 pub mod cmd_flags {
-    /// Response type mask
-    pub const RESP_MASK: u16 = 0x0060;
+    /// Response type mask (REG_CMD bits 8-10)
+    pub const RESP_MASK: u16 = 0x0700;
+    /// Response type chosen by the controller from the command index
+    pub const RESP_AUTO: u16 = 0x0000;
     /// No response
-    pub const RESP_NONE: u16 = 0x0000;
+    pub const RESP_NONE: u16 = 0x0300;
     /// R1 response (48-bit)
-    pub const RESP_R1: u16 = 0x0020;
+    pub const RESP_R1: u16 = 0x0400;
     /// R1b response (48-bit with busy)
-    pub const RESP_R1B: u16 = 0x0060;
+    pub const RESP_R1B: u16 = 0x0500;
     /// R2 response (136-bit)
-    pub const RESP_R2: u16 = 0x0040;
+    pub const RESP_R2: u16 = 0x0600;
     /// R3 response (48-bit without CRC)
-    pub const RESP_R3: u16 = 0x0020;
+    pub const RESP_R3: u16 = 0x0700;
 }
 
-/// SDMMC status register bit flags
+/// SDMMC status register (`STATUS0`) bit flags
 pub mod status {
     /// Command response received
-    pub const CMD_RESP_END: u32 = 0x0001;
+    pub const CMD_RESP_END: u16 = 0x0001;
     /// Data transfer end
-    pub const DATA_END: u32 = 0x0004;
+    pub const DATA_END: u16 = 0x0004;
     /// Card removed
-    pub const CARD_REMOVED: u32 = 0x0008;
-    /// Card inserted
-    pub const CARD_INSERTED: u32 = 0x0010;
-    /// Write protect enabled
-    pub const WRITE_PROTECT: u32 = 0x0020;
+    pub const CARD_REMOVED: u16 = 0x0008;
+    /// Card inserted (card detect signal, set while a card is present)
+    pub const CARD_INSERTED: u16 = 0x0020;
+    /// Write protect switch, set while the card is writable
+    pub const WRITE_PROTECT: u16 = 0x0080;
 }
@@ -1,13 +1,199 @@
-use crate::EmulatorConfig;
-use clap::Parser;
-use std::path::PathBuf;
+use crate::cpu_types::{ArmRegister, Core, CpuModel, Endianness};
+use crate::display::{FbByteOrder, FbRotation};
+use crate::mmio::{Button, MmioReplay};
+use crate::pctrace::PcTraceConfig;
+use crate::savestate::SaveState;
+use crate::scheduler::MIN_FREQ_HZ;
+use crate::screenshot::DumpFormat;
+use crate::{EmulatorConfig, container, firm};
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+
+/// Command line for the threemu binaries
+///
+/// Without a subcommand the emulator runs using the top-level arguments, exactly as
+/// with `run`.
+#[derive(Parser, Debug, Clone)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: Args,
+}
+
+/// Exit code the binaries use for bad arguments and for inputs that can't be loaded
+/// or parsed
+pub const EXIT_USAGE_ERROR: i32 = 2;
+
+/// Exit code of a headless run that met its stop conditions
+pub const EXIT_PASS: i32 = 0;
+
+/// Exit code of a headless run that ended (timeout, interruption, limit) without
+/// meeting its stop conditions, or that diverged from a verified MMIO write log
+pub const EXIT_FAIL: i32 = 1;
+
+/// Exit code of a headless run that ended on an emulation error
+pub const EXIT_EMULATION_ERROR: i32 = 2;
+
+/// Exit code of a headless run that ended because every core hung (see
+/// `--hang-detect-quanta`)
+pub const EXIT_HANG: i32 = 3;
+
+impl Cli {
+    /// The selected command, treating top-level arguments as `run`
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Run(self.run))
+    }
+
+    /// Arguments for running the emulator, after carrying out any other subcommand
+    ///
+    /// `info` and `diff` print their output here and then exit the process: with 0
+    /// on success, or [`EXIT_USAGE_ERROR`] after printing the error to stderr.
+    pub fn into_run_args(self) -> Args {
+        let result = match self.into_command() {
+            Command::Run(args) => return args,
+            Command::Info(info) => info.run(),
+            Command::Diff(diff) => diff.run(),
+        };
+        match result {
+            Ok(report) => {
+                print!("{}", report);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(EXIT_USAGE_ERROR);
+            }
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+#[expect(clippy::large_enum_variant)]
+pub enum Command {
+    /// Run a FIRM (the default when no subcommand is given)
+    Run(Args),
+    /// Print FIRM header and section metadata without running it
+    Info(InfoArgs),
+    /// Compare two save states and report changed registers and memory pages
+    Diff(DiffArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// Earlier save state
+    pub state_a: PathBuf,
+
+    /// Later save state
+    pub state_b: PathBuf,
+}
+
+impl DiffArgs {
+    /// Load both save states and describe their differences
+    pub fn run(&self) -> Result<String, String> {
+        let load = |path: &Path| {
+            SaveState::load(path)
+                .map_err(|e| format!("Failed to load save state {:?}: {}", path, e))
+        };
+        let diff = load(&self.state_a)?.diff(&load(&self.state_b)?);
+        Ok(diff.to_string())
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct InfoArgs {
+    /// Path to FIRM file to inspect. If --entry-firm-in-sd-card is set,
+    /// this is a path inside the SD card image.
+    pub firm: PathBuf,
+
+    /// Path to SD card image (raw disk image with MBR + FAT32)
+    #[arg(long)]
+    pub sd_card: Option<PathBuf>,
+
+    /// Interpret FIRM path as a path inside the SD card image instead of local filesystem
+    #[arg(long, requires = "sd_card")]
+    pub entry_firm_in_sd_card: bool,
+}
+
+impl InfoArgs {
+    /// Load the FIRM data to inspect
+    pub fn load_firm_data(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let sd_card = self
+            .sd_card
+            .as_deref()
+            .filter(|_| self.entry_firm_in_sd_card);
+        load_firm_file(&self.firm, sd_card)
+    }
+
+    /// Load the FIRM and describe its header and section table
+    pub fn run(&self) -> Result<String, String> {
+        let data = self
+            .load_firm_data()
+            .map_err(|e| format!("Failed to load FIRM file: {}", e))?;
+        let info = firm::info(&data).map_err(|e| format!("Failed to parse FIRM: {:?}", e))?;
+        Ok(info.to_string())
+    }
+}
+
+/// Format of log output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// One line per event, with span context
+    #[default]
+    Full,
+    /// Shorter single lines
+    Compact,
+    /// Multi-line, human-oriented output
+    Pretty,
+    /// Newline-delimited JSON objects
+    Json,
+}
 
 #[derive(Parser, Debug, Clone)]
 pub struct Args {
     /// Path to FIRM file to execute. If --entry-firm-in-sd-card is set,
     /// this is a path inside the SD card image (e.g., "luma/payloads/firm.firm").
     /// Otherwise, it's a path on the local filesystem.
-    pub firm: PathBuf,
+    /// A local directory runs the valid FIRM in it with the highest boot priority.
+    #[arg(required_unless_present = "raw_binary")]
+    pub firm: Option<PathBuf>,
+
+    /// Run a raw flat binary instead of a FIRM. Requires --load-addr, --entry and --core.
+    /// Only the core given by --core runs.
+    #[arg(
+        long,
+        conflicts_with_all = ["firm", "entry_firm_in_sd_card"],
+        requires_all = ["load_addr", "entry", "core"]
+    )]
+    pub raw_binary: Option<PathBuf>,
+
+    /// Address to load the raw binary at (hex: 0x1234 or decimal: 1234)
+    #[arg(long, value_parser = parse_hex_or_dec, requires = "raw_binary")]
+    pub load_addr: Option<u64>,
+
+    /// Entry point of the raw binary (hex: 0x1234 or decimal: 1234)
+    #[arg(long, value_parser = parse_hex_or_dec, requires = "raw_binary")]
+    pub entry: Option<u64>,
+
+    /// Run only this core; the other never starts and its stop PC is ignored.
+    /// With --raw-binary, this is the core that runs the binary.
+    #[arg(long, value_enum)]
+    pub core: Option<Core>,
+
+    /// Byte order both cores start in. The 3DS runs little-endian; big-endian is
+    /// for experimentation only.
+    #[arg(long, value_enum, default_value_t)]
+    pub endianness: Endianness,
+
+    /// CPU model to emulate for ARM9 instead of the ARM946E-S
+    #[arg(long, value_enum)]
+    pub arm9_cpu_model: Option<CpuModel>,
+
+    /// CPU model to emulate for ARM11 instead of the ARM11 MPCore
+    #[arg(long, value_enum)]
+    pub arm11_cpu_model: Option<CpuModel>,
 
     /// Path to SD card image (raw disk image with MBR + FAT32)
     #[arg(long)]
@@ -18,6 +204,31 @@ pub struct Args {
     #[arg(long)]
     pub entry_firm_in_sd_card: bool,
 
+    /// Fail SD reads that start past the end of the card image with an out-of-range
+    /// error, instead of returning zeros and only flagging the error in CMD13
+    #[arg(long, requires = "sd_card")]
+    pub strict_sd_reads: bool,
+
+    /// Number of DAT0/CMD13 polls the SD card and NAND report busy for after an R1b
+    /// command or a write (0 never signals busy)
+    #[arg(long, value_name = "POLLS")]
+    pub sd_busy_polls: Option<u32>,
+
+    /// Number of ACMD41 requests the SD card and NAND report as still initializing
+    /// before powering up (0 powers up on the first request)
+    #[arg(long, value_name = "POLLS")]
+    pub sd_op_cond_busy_polls: Option<u32>,
+
+    /// Start ARM9 at this address instead of the FIRM's entrypoint (hex: 0x1234 or
+    /// decimal: 1234). Set bit 0 to start in Thumb state.
+    #[arg(long, value_parser = parse_hex_or_dec)]
+    pub arm9_entry: Option<u64>,
+
+    /// Start ARM11 at this address instead of the FIRM's entrypoint (hex: 0x1234 or
+    /// decimal: 1234). Set bit 0 to start in Thumb state.
+    #[arg(long, value_parser = parse_hex_or_dec)]
+    pub arm11_entry: Option<u64>,
+
     /// Stop when ARM9 reaches this PC (hex: 0x1234 or decimal: 1234)
     #[arg(long, value_parser = parse_hex_or_dec)]
     pub arm9_stop_pc: Option<u64>,
@@ -26,9 +237,184 @@ pub struct Args {
     #[arg(long, value_parser = parse_hex_or_dec)]
     pub arm11_stop_pc: Option<u64>,
 
+    /// Stop as soon as either core reaches its stop PC, instead of waiting until both
+    /// given stop PCs are reached
+    #[arg(long)]
+    pub stop_on_any_pc: bool,
+
+    /// Stop once a 32-bit memory word holds a value, as `<core>:<addr>:<value>`
+    /// (e.g. `arm9:0x08000100:0xC0FFEE`). The address is read as seen by that core.
+    #[arg(long, value_parser = parse_mem_condition)]
+    pub stop_on_mem: Option<(Core, u64, u32)>,
+
     /// Stop after this many instructions (total across both cores)
     #[arg(long, short = 'i')]
     pub max_instructions: Option<u64>,
+
+    /// Stop after this many frames (60 per emulated second), independent of host speed
+    #[arg(long)]
+    pub max_frames: Option<usize>,
+
+    /// Stop after this much wall-clock time, in milliseconds. The GUI shows the
+    /// time remaining in the window title.
+    #[arg(long)]
+    pub timeout_ms: Option<u64>,
+
+    /// Stop ARM9 after it has executed this many instructions, while ARM11 keeps
+    /// running. --max-instructions still caps the total.
+    #[arg(long)]
+    pub arm9_max_instructions: Option<u64>,
+
+    /// Stop ARM11 after it has executed this many instructions, while ARM9 keeps
+    /// running. --max-instructions still caps the total.
+    #[arg(long)]
+    pub arm11_max_instructions: Option<u64>,
+
+    /// Invalidate the other core's translated code when a core writes shared memory
+    /// (FCRAM, AXI WRAM, VRAM). Slower, but needed when one core loads code for the other.
+    #[arg(long)]
+    pub shared_memory_coherence: bool,
+
+    /// Refuse to run a FIRM whose sections have overlapping load addresses, instead
+    /// of warning and loading them in order
+    #[arg(long)]
+    pub reject_overlapping_sections: bool,
+
+    /// Refuse to run a FIRM whose header signature doesn't verify against this RSA
+    /// public key (PEM file, SPKI or PKCS#1), as the boot ROM does
+    #[arg(long, value_parser = firm::load_public_key)]
+    pub verify_signature: Option<firm::PublicKey>,
+
+    /// Fix the RTC to this time (seconds since the Unix epoch) for deterministic runs.
+    /// Uses the host clock when unset.
+    #[arg(long)]
+    pub rtc_time: Option<u64>,
+
+    /// Set a register before execution starts, e.g. `arm11:r0=0x1000`. Repeatable.
+    /// Registers not set here start at zero.
+    #[arg(long = "set-reg", value_parser = parse_register_assignment)]
+    pub set_reg: Vec<(Core, ArmRegister, u32)>,
+
+    /// Stop a core whose PC is unchanged across this many consecutive quanta and
+    /// report a suspected hang (e.g. a crash ending in `b .`). Disabled by default.
+    #[arg(long)]
+    pub hang_detect_quanta: Option<usize>,
+
+    /// Log generic MMIO accesses in this address range, with the accessing PC, and
+    /// print the log at exit (format: START-END, e.g. 0x10100000-0x10102000)
+    #[arg(long, value_parser = parse_range)]
+    pub mmio_log_range: Option<(u32, u32)>,
+
+    /// Stop as soon as a core executes from outside executable memory (unmapped or
+    /// MMIO space, or outside the --exec-region ranges if given), reporting the PC
+    #[arg(long)]
+    pub enforce_exec_regions: bool,
+
+    /// Only allow code to run from this address range, with --enforce-exec-regions
+    /// (format: START-END, e.g. 0x08000000-0x08100000). Can be repeated.
+    #[arg(long, value_parser = parse_range, requires = "enforce_exec_regions")]
+    pub exec_region: Vec<(u32, u32)>,
+
+    /// Record every MMIO write (core, instruction count, PC, address, size, value) to
+    /// this file when the run ends
+    #[arg(long, value_name = "FILE", conflicts_with = "verify_mmio_writes")]
+    pub record_mmio_writes: Option<PathBuf>,
+
+    /// Check the run's MMIO writes against a log made with --record-mmio-writes and
+    /// fail on the first difference, which points at non-determinism (e.g. the RTC)
+    #[arg(long, value_name = "FILE")]
+    pub verify_mmio_writes: Option<PathBuf>,
+
+    /// Report this button as held for the whole run, e.g. to test boot-time button
+    /// checks headlessly. Can be repeated.
+    #[arg(long = "hold-button", value_enum, value_name = "BUTTON")]
+    pub hold_buttons: Vec<Button>,
+
+    /// Log a line when boot reaches each recognizable milestone (FIRM parsed,
+    /// sections loaded, first SDMMC command, first framebuffer, first VBlank, ...)
+    /// and list the ones never reached when the run ends
+    #[arg(long)]
+    pub verbose_boot: bool,
+
+    /// Log the PC of every executed instruction to this file, one `core,pc` line per
+    /// instruction (e.g. `arm11,0x1FF80000`), for comparison with other emulators
+    #[arg(long)]
+    pub trace_pc: Option<PathBuf>,
+
+    /// Stop logging to the PC trace after this many entries
+    #[arg(long, requires = "trace_pc")]
+    pub trace_pc_limit: Option<usize>,
+
+    /// Only log instructions executed by this core to the PC trace
+    #[arg(long, value_enum, requires = "trace_pc")]
+    pub trace_core: Option<Core>,
+
+    /// Tally executed instructions by mnemonic and print the most executed at exit
+    /// (20 unless a count is given). Disassembling every instruction slows emulation.
+    #[arg(
+        long,
+        value_name = "TOP",
+        num_args = 0..=1,
+        default_missing_value = "20"
+    )]
+    pub opcode_coverage: Option<usize>,
+
+    /// Include decoded GPU and SDMMC register dumps in the state printed at exit
+    #[arg(long)]
+    pub dump_devices: bool,
+
+    /// ARM9 clock frequency in Hz. Defaults to 134 MHz.
+    #[arg(long)]
+    pub arm9_freq_hz: Option<usize>,
+
+    /// ARM11 clock frequency in Hz (e.g. 804000000 for a New 3DS). Defaults to 268 MHz.
+    #[arg(long)]
+    pub arm11_freq_hz: Option<usize>,
+
+    /// GUI only: when emulation stops, keep the window open showing the final frame
+    /// until it is closed, instead of exiting immediately
+    #[arg(long)]
+    pub keep_window_on_stop: bool,
+
+    /// Log output format: `full` (the default), `compact`, `pretty` (multi-line), or
+    /// `json` (one object per line, for log processors)
+    #[arg(long, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /// Channel order of framebuffer pixels in memory: `rgb` reads red from the
+    /// lowest address, `bgr` blue. Try `bgr` if red and blue appear swapped.
+    #[arg(long, value_enum, default_value_t)]
+    pub fb_byte_order: FbByteOrder,
+
+    /// Rotation of framebuffers in memory relative to the display, in degrees
+    /// counter-clockwise. Hardware stores them rotated 90°; use `0` for content laid
+    /// out row by row, or to see the raw layout.
+    #[arg(long, value_enum, default_value_t)]
+    pub fb_rotation: FbRotation,
+
+    /// Write a save state to this file when the run stops, for any reason (including
+    /// an emulation error), to inspect or compare with `diff` later. Save states
+    /// can't be restored into an emulator. Headless CLI only.
+    #[arg(long)]
+    pub dump_state_on_stop: Option<PathBuf>,
+
+    /// Write the screens to this directory when the run stops
+    #[arg(long)]
+    pub dump_screens: Option<PathBuf>,
+
+    /// Format of the screen dumps: `rgb` converts to a PPM image, `raw` writes the
+    /// framebuffer bytes as scanned out plus a JSON description of their layout
+    #[arg(long, value_enum, default_value_t, requires = "dump_screens")]
+    pub dump_format: DumpFormat,
+
+    /// Run as a benchmark and print an instructions-per-second summary.
+    /// Requires --max-instructions to be specified.
+    #[arg(long)]
+    pub bench: bool,
+
+    /// Additionally print the benchmark summary as a single JSON line
+    #[arg(long, requires = "bench")]
+    pub bench_json: bool,
 }
 
 impl Args {
@@ -37,17 +423,114 @@ impl Args {
         if self.entry_firm_in_sd_card && self.sd_card.is_none() {
             return Err("--entry-firm-in-sd-card requires --sd-card to be specified".to_string());
         }
+        for (flag, value) in [
+            ("--load-addr", self.load_addr),
+            ("--entry", self.entry),
+            ("--arm9-entry", self.arm9_entry),
+            ("--arm11-entry", self.arm11_entry),
+        ] {
+            if value.is_some_and(|v| v > u32::MAX as u64) {
+                return Err(format!("{} must fit in 32 bits", flag));
+            }
+        }
+        for (flag, value) in [
+            ("--arm9-freq-hz", self.arm9_freq_hz),
+            ("--arm11-freq-hz", self.arm11_freq_hz),
+        ] {
+            if value.is_some_and(|v| v < MIN_FREQ_HZ) {
+                return Err(format!("{} must be at least {}", flag, MIN_FREQ_HZ));
+            }
+        }
+        if self.bench && self.max_instructions.is_none() {
+            return Err("--bench requires --max-instructions to be specified".to_string());
+        }
         Ok(())
     }
 
+    /// Install the global log subscriber, filtered by `filter` plus the directives
+    /// implied by the arguments, in the format selected by `--log-format`
+    pub fn init_logging(&self, filter: tracing_subscriber::EnvFilter) {
+        let builder = tracing_subscriber::fmt().with_env_filter(self.log_filter(filter));
+        match self.log_format {
+            LogFormat::Full => builder.init(),
+            LogFormat::Compact => builder.compact().init(),
+            LogFormat::Pretty => builder.pretty().init(),
+            LogFormat::Json => builder.json().init(),
+        }
+    }
+
+    /// Add the log directives implied by the arguments to `filter`
+    ///
+    /// With `--verbose-boot`, milestones are logged whatever the base level.
+    pub fn log_filter(
+        &self,
+        filter: tracing_subscriber::EnvFilter,
+    ) -> tracing_subscriber::EnvFilter {
+        if self.verbose_boot {
+            filter.add_directive(
+                "threemu::milestones=info"
+                    .parse()
+                    .expect("milestone log directive is valid"),
+            )
+        } else {
+            filter
+        }
+    }
+
     /// Convert Args to EmulatorConfig
     pub fn to_emulator_config(&self) -> EmulatorConfig {
         EmulatorConfig {
             sd_card: self.sd_card.clone(),
+            sd_card_data: None,
+            strict_sd_reads: self.strict_sd_reads,
+            sd_busy_polls: self.sd_busy_polls,
+            sd_op_cond_busy_polls: self.sd_op_cond_busy_polls,
+            arm9_entry: self.arm9_entry.map(|v| v as u32),
+            arm11_entry: self.arm11_entry.map(|v| v as u32),
             arm9_stop_pc: self.arm9_stop_pc,
             arm11_stop_pc: self.arm11_stop_pc,
+            stop_on_any_pc: self.stop_on_any_pc,
+            stop_on_mem: self.stop_on_mem,
             max_instructions: self.max_instructions.map(|v| v as usize),
-            timeout_ms: None,
+            max_frames: self.max_frames,
+            arm9_max_instructions: self.arm9_max_instructions.map(|v| v as usize),
+            arm11_max_instructions: self.arm11_max_instructions.map(|v| v as usize),
+            timeout_ms: self.timeout_ms,
+            shared_memory_coherence: self.shared_memory_coherence,
+            initial_registers: self.set_reg.clone(),
+            hang_detect_quanta: self.hang_detect_quanta,
+            mmio_log_range: self.mmio_log_range,
+            arm9_freq_hz: self.arm9_freq_hz,
+            arm11_freq_hz: self.arm11_freq_hz,
+            cores: self.core.into(),
+            endianness: self.endianness,
+            arm9_cpu_model: self.arm9_cpu_model,
+            arm11_cpu_model: self.arm11_cpu_model,
+            rtc_epoch_secs: self.rtc_time,
+            ram_init: Default::default(),
+            pc_trace: self.trace_pc.clone().map(|path| PcTraceConfig {
+                path,
+                limit: self.trace_pc_limit,
+                cores: self.trace_core.into(),
+            }),
+            reject_overlapping_sections: self.reject_overlapping_sections,
+            verify_signature: self.verify_signature.clone(),
+            fb_byte_order: self.fb_byte_order,
+            fb_rotation: self.fb_rotation,
+            verbose_boot: self.verbose_boot,
+            dump_devices: self.dump_devices,
+            enforce_exec_regions: self.enforce_exec_regions,
+            exec_regions: self.exec_region.clone(),
+            mmio_replay: match (&self.record_mmio_writes, &self.verify_mmio_writes) {
+                (Some(path), _) => Some(MmioReplay::Record(path.clone())),
+                (None, Some(path)) => Some(MmioReplay::Verify(path.clone())),
+                (None, None) => None,
+            },
+            held_buttons: self
+                .hold_buttons
+                .iter()
+                .fold(0, |mask, button| mask | button.mask()),
+            opcode_coverage: self.opcode_coverage,
         }
     }
 }
@@ -60,21 +543,107 @@ pub fn parse_hex_or_dec(s: &str) -> Result<u64, std::num::ParseIntError> {
     }
 }
 
+/// Parse a register assignment of the form `<core>:<reg>=<value>`
+pub fn parse_register_assignment(s: &str) -> Result<(Core, ArmRegister, u32), String> {
+    let (target, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <core>:<reg>=<value>, got {:?}", s))?;
+    let (core, reg) = target
+        .split_once(':')
+        .ok_or_else(|| format!("expected <core>:<reg>=<value>, got {:?}", s))?;
+    let value = parse_hex_or_dec(value)
+        .map_err(|e| format!("invalid value {:?}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("value {:?} does not fit in 32 bits", value))?;
+    Ok((core.parse()?, reg.parse()?, value))
+}
+
+/// Parse a memory stop condition of the form `<core>:<addr>:<value>`, e.g.
+/// `arm9:0x08000100:0xC0FFEE`
+pub fn parse_mem_condition(s: &str) -> Result<(Core, u64, u32), String> {
+    let mut parts = s.splitn(3, ':');
+    let (Some(core), Some(addr), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(format!("expected <core>:<addr>:<value>, got {:?}", s));
+    };
+    let addr = parse_hex_or_dec(addr).map_err(|e| format!("invalid address {:?}: {}", addr, e))?;
+    let value = parse_hex_or_dec(value)
+        .map_err(|e| format!("invalid value {:?}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("value {:?} does not fit in 32 bits", value))?;
+    Ok((core.parse()?, addr, value))
+}
+
+/// Parse an address range of the form `START-END`, each bound in hex or decimal
+pub fn parse_range(s: &str) -> Result<(u32, u32), String> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| format!("expected START-END, got {:?}", s))?;
+    let parse = |v: &str| -> Result<u32, String> {
+        parse_hex_or_dec(v)
+            .map_err(|e| format!("invalid address {:?}: {}", v, e))?
+            .try_into()
+            .map_err(|_| format!("address {:?} does not fit in 32 bits", v))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start >= end {
+        return Err(format!("empty range {:#X}-{:#X}", start, end));
+    }
+    Ok((start, end))
+}
+
 /// Load FIRM data from either a direct file path or from inside an SD card image
+///
+/// With --raw-binary, the binary is wrapped in a synthesized FIRM image instead.
 pub fn load_firm_data(args: &Args) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    use tracing::info;
+
+    if let Some(raw_path) = &args.raw_binary {
+        let (Some(load_addr), Some(entry), Some(core)) = (args.load_addr, args.entry, args.core)
+        else {
+            return Err("--raw-binary requires --load-addr, --entry and --core".into());
+        };
+        info!(
+            "Loading raw {:?} binary from file: {:?} (load={:#X}, entry={:#X})",
+            core, raw_path, load_addr, entry
+        );
+        let binary = std::fs::read(raw_path)?;
+        return Ok(firm::build_raw_firm(
+            &binary,
+            core,
+            load_addr as u32,
+            entry as u32,
+        ));
+    }
+
+    let firm_path = args.firm.as_ref().ok_or("no FIRM path given")?;
+    let sd_card = if args.entry_firm_in_sd_card {
+        Some(
+            args.sd_card
+                .as_deref()
+                .ok_or("--entry-firm-in-sd-card requires --sd-card")?,
+        )
+    } else {
+        None
+    };
+    load_firm_file(firm_path, sd_card)
+}
+
+/// Load a FIRM file from the local filesystem, or from inside `sd_card` if given
+///
+/// If the file is a container holding a FIRM (see [`container`]), the FIRM is
+/// extracted from it.
+pub fn load_firm_file(
+    firm_path: &Path,
+    sd_card: Option<&Path>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     use std::io::Read;
     use tracing::info;
 
-    if args.entry_firm_in_sd_card {
+    if let Some(sd_card_path) = sd_card {
         // Load from SD card image using fatfs
-        let sd_card_path = args
-            .sd_card
-            .as_ref()
-            .ok_or("--entry-firm-in-sd-card requires --sd-card")?;
-
         info!(
             "Loading FIRM from SD card image: {:?} at path: {:?}",
-            sd_card_path, args.firm
+            sd_card_path, firm_path
         );
 
         use fscommon::BufStream;
@@ -85,8 +654,7 @@ pub fn load_firm_data(args: &Args) -> Result<Vec<u8>, Box<dyn std::error::Error>
         let root_dir = fs.root_dir();
 
         // Convert PathBuf to string for fatfs
-        let firm_path_str = args
-            .firm
+        let firm_path_str = firm_path
             .to_str()
             .ok_or("FIRM path contains invalid UTF-8")?;
         let mut firm_file = root_dir.open_file(firm_path_str)?;
@@ -94,11 +662,138 @@ pub fn load_firm_data(args: &Args) -> Result<Vec<u8>, Box<dyn std::error::Error>
         firm_file.read_to_end(&mut contents)?;
 
         info!("Successfully loaded {} bytes from SD card", contents.len());
-        Ok(contents)
+        Ok(container::extract_firm(contents)?)
     } else {
+        if firm_path.is_dir() {
+            return load_firm_dir(firm_path);
+        }
+
         // Load directly from filesystem
-        info!("Loading FIRM from file: {:?}", args.firm);
-        let data = std::fs::read(&args.firm)?;
-        Ok(data)
+        info!("Loading FIRM from file: {:?}", firm_path);
+        let data = std::fs::read(firm_path)?;
+        Ok(container::extract_firm(data)?)
+    }
+}
+
+/// Load the FIRM to boot from a directory of FIRM files, picked by boot priority
+/// (see [`firm::select_firm`])
+///
+/// Files are considered in name order; those that don't hold a FIRM are skipped.
+fn load_firm_dir(dir: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    use tracing::{debug, info};
+
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    let mut candidates = Vec::new();
+    let mut contents = Vec::new();
+    for path in paths {
+        let parsed = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(container::extract_firm)
+            .and_then(|data| {
+                firm::FirmHeader::parse_and_validate(&data)
+                    .map(|header| (header, data))
+                    .map_err(|e| format!("{:?}", e))
+            });
+        match parsed {
+            Ok((header, data)) => {
+                candidates.push((path, header));
+                contents.push(data);
+            }
+            Err(e) => debug!("Skipping {:?}: {}", path, e),
+        }
+    }
+
+    let index = firm::select_firm(&candidates)
+        .ok_or_else(|| format!("No valid FIRM found in {:?}", dir))?;
+    let (path, header) = &candidates[index];
+    info!(
+        "Selected FIRM {:?} (boot priority {}) from {} candidates in {:?}",
+        path,
+        header.boot_priority,
+        candidates.len(),
+        dir
+    );
+    Ok(contents.swap_remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_types::CoreSelection;
+    use crate::scheduler::SchedulerConfig;
+
+    fn run_args(args: &[&str]) -> Args {
+        let cli = Cli::try_parse_from(["threemu"].iter().chain(args)).unwrap();
+        match cli.into_command() {
+            Command::Run(args) => args,
+            command => panic!("expected run, got {:?}", command),
+        }
+    }
+
+    #[test]
+    fn cpu_frequencies_set_the_interleaving_ratio() {
+        let config = run_args(&[
+            "fw.firm",
+            "--arm9-freq-hz",
+            "134000000",
+            "--arm11-freq-hz",
+            "804000000",
+        ])
+        .to_emulator_config();
+        assert_eq!(config.arm9_freq_hz, Some(134_000_000));
+        assert_eq!(config.arm11_freq_hz, Some(804_000_000));
+
+        let scheduler = SchedulerConfig::with_frequencies(
+            config.arm9_freq_hz.unwrap(),
+            config.arm11_freq_hz.unwrap(),
+        );
+        assert_eq!(scheduler.arm11_quantum / scheduler.arm9_quantum, 6);
+    }
+
+    #[test]
+    fn core_flag_selects_the_cores_to_run() {
+        let cores = |args: &[&str]| run_args(args).to_emulator_config().cores;
+        assert_eq!(cores(&["fw.firm"]), CoreSelection::Both);
+        assert_eq!(
+            cores(&["fw.firm", "--core", "arm9"]),
+            CoreSelection::Arm9Only
+        );
+        assert_eq!(
+            cores(&["fw.firm", "--core", "arm11"]),
+            CoreSelection::Arm11Only
+        );
+    }
+
+    #[test]
+    fn strict_sd_reads_flag_reaches_the_config() {
+        assert!(!run_args(&["fw.firm"]).to_emulator_config().strict_sd_reads);
+        let config =
+            run_args(&["fw.firm", "--sd-card", "sd.img", "--strict-sd-reads"]).to_emulator_config();
+        assert!(config.strict_sd_reads);
+    }
+
+    #[test]
+    fn stop_on_mem_takes_core_address_and_value() {
+        let config =
+            run_args(&["fw.firm", "--stop-on-mem", "arm9:0x08000100:0x42"]).to_emulator_config();
+        assert_eq!(config.stop_on_mem, Some((Core::Arm9, 0x0800_0100, 0x42)));
+        assert!(parse_mem_condition("arm9:0x08000100").is_err());
+        assert!(parse_mem_condition("arm7:0x08000100:0x42").is_err());
+    }
+
+    #[test]
+    fn cpu_frequencies_below_one_instruction_per_quantum_are_rejected() {
+        for flag in ["--arm9-freq-hz", "--arm11-freq-hz"] {
+            for freq in ["0", "599"] {
+                assert!(run_args(&["fw.firm", flag, freq]).validate().is_err());
+            }
+            assert_eq!(run_args(&["fw.firm", flag, "600"]).validate(), Ok(()));
+        }
+        assert_eq!(crate::scheduler::instructions_per_quantum(MIN_FREQ_HZ), 1);
     }
 }
@@ -1,33 +1,40 @@
 use clap::Parser;
-use threemu::{Args, EmulatorCore, StopReason, load_firm_data};
+use threemu::args::{Cli, EXIT_EMULATION_ERROR, EXIT_FAIL, EXIT_HANG, EXIT_PASS, EXIT_USAGE_ERROR};
+use threemu::{
+    Args, Core, CoreSelection, EmulatorCore, StopDetail, StopReason, load_firm_data, screenshot,
+};
 use tracing::info;
 
 fn main() {
     // Parse command-line arguments
-    let args = Args::parse();
+    let args = Cli::parse().into_run_args();
 
     // Validate arguments
     if let Err(e) = args.validate() {
         eprintln!("Error: {}", e);
-        std::process::exit(2);
+        std::process::exit(EXIT_USAGE_ERROR);
     }
 
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
+    // Initialize logging (benchmarks only log warnings so logging doesn't skew the numbers)
+    let env_filter = if args.bench {
+        tracing_subscriber::EnvFilter::new("warn")
+    } else {
+        tracing_subscriber::EnvFilter::from_default_env()
+    };
+    args.init_logging(env_filter);
 
     // Load FIRM data
     let firm_data = match load_firm_data(&args) {
         Ok(data) => data,
         Err(e) => {
             eprintln!("Failed to load FIRM file: {}", e);
-            std::process::exit(2);
+            std::process::exit(EXIT_USAGE_ERROR);
         }
     };
 
     // Create emulator config from args
     let config = args.to_emulator_config();
+    let cores = config.cores;
 
     // Create emulator
     info!("=== Creating Emulator ===");
@@ -35,78 +42,192 @@ fn main() {
         Ok(emu) => emu,
         Err(e) => {
             eprintln!("Failed to create emulator: {}", e);
-            std::process::exit(2);
+            std::process::exit(EXIT_USAGE_ERROR);
         }
     };
 
     // Log entry points
-    info!("ARM9 Entry: {:#X}", emulator.arm9_pc());
-    info!("ARM11 Entry: {:#X}", emulator.arm11_pc());
+    info!("ARM9 Entry: {:#X}", emulator.pc(Core::Arm9));
+    info!("ARM11 Entry: {:#X}", emulator.pc(Core::Arm11));
 
     // Run emulator
     info!("=== Running Emulator (Headless) ===");
     let stop_reason = emulator.run();
+    emulator.flush_pc_trace();
 
     // Log final state
     info!("=== Emulation Complete ===");
     info!("Stop reason: {:?}", stop_reason);
+    let mmio_writes_ok = match emulator.print_final_state() {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    };
     info!(
         "ARM9 PC: {:#X} (stopped: {})",
-        emulator.arm9_pc(),
-        emulator.arm9_stopped()
+        emulator.pc(Core::Arm9),
+        emulator.stopped(Core::Arm9)
     );
     info!(
         "ARM11 PC: {:#X} (stopped: {})",
-        emulator.arm11_pc(),
-        emulator.arm11_stopped()
+        emulator.pc(Core::Arm11),
+        emulator.stopped(Core::Arm11)
     );
-    info!("Total instructions: {}", emulator.total_executed());
-    info!("Elapsed: {:?}", emulator.elapsed());
+
+    if let Some(path) = &args.dump_state_on_stop {
+        match emulator.save_state(path) {
+            Ok(()) => info!("Saved state to {:?}", path),
+            Err(e) => eprintln!("Failed to dump state: {}", e),
+        }
+    }
+
+    if let Some(dir) = &args.dump_screens
+        && let Err(e) = screenshot::dump_screens(&emulator, dir, args.dump_format)
+    {
+        eprintln!("Failed to dump screens: {}", e);
+    }
+
+    if args.bench {
+        print_bench_summary(&emulator, args.bench_json);
+    }
 
     // Determine exit code based on stop reason and whether expectations were met
     let exit_code = match stop_reason {
         StopReason::Error(msg) => {
             eprintln!("Emulator error: {}", msg);
-            2
+            EXIT_EMULATION_ERROR
         }
         StopReason::Timeout => {
             eprintln!("Timeout reached before stop conditions met");
-            1
+            EXIT_FAIL
+        }
+        StopReason::Interrupted => {
+            eprintln!("Emulation interrupted before stop conditions met");
+            EXIT_FAIL
+        }
+        StopReason::NonExecutable { core, pc } => {
+            eprintln!(
+                "{:?} executed outside executable memory at PC {:#X}",
+                core, pc
+            );
+            EXIT_EMULATION_ERROR
+        }
+        StopReason::MemoryValue { core, addr, value } => {
+            info!("PASS: {:?} memory at {:#X} holds {:#X}", core, addr, value);
+            EXIT_PASS
+        }
+        StopReason::Yielded | StopReason::Predicate | StopReason::Callback => {
+            // Only the budgeted, predicate and callback run loops return these
+            eprintln!("Emulation ended unexpectedly ({:?})", stop_reason);
+            EXIT_EMULATION_ERROR
+        }
+        StopReason::Hang { core, pc } => {
+            eprintln!("{:?} suspected hang at PC {:#X}", core, pc);
+            EXIT_HANG
         }
         StopReason::StopCondition => {
-            // Check if the expected stop PCs were reached
-            let arm9_ok = args
-                .arm9_stop_pc
-                .is_none_or(|expected| emulator.arm9_stopped() && emulator.arm9_pc() == expected);
-            let arm11_ok = args
-                .arm11_stop_pc
-                .is_none_or(|expected| emulator.arm11_stopped() && emulator.arm11_pc() == expected);
-
-            if arm9_ok && arm11_ok {
-                info!("PASS: All stop conditions reached");
-                0
-            } else {
-                // This means max_instructions was hit before both PCs were reached
-                if !arm9_ok {
-                    eprintln!(
-                        "ARM9 did not reach expected PC {:#X} (actual: {:#X}, stopped: {})",
-                        args.arm9_stop_pc.unwrap(),
-                        emulator.arm9_pc(),
-                        emulator.arm9_stopped()
-                    );
+            match emulator.stop_detail() {
+                Some(StopDetail::StopPcs) | None => {}
+                Some(StopDetail::MaxInstructions) => info!(
+                    "Instruction limit reached after {} instructions",
+                    emulator.total_executed()
+                ),
+                Some(StopDetail::MaxFrames) => info!(
+                    "Frame limit reached after {} frames",
+                    emulator.frames_completed()
+                ),
+                Some(StopDetail::CoresStopped) => {
+                    info!("Every core stopped before the stop PCs were reached")
                 }
-                if !arm11_ok {
-                    eprintln!(
-                        "ARM11 did not reach expected PC {:#X} (actual: {:#X}, stopped: {})",
-                        args.arm11_stop_pc.unwrap(),
-                        emulator.arm11_pc(),
-                        emulator.arm11_stopped()
-                    );
-                }
-                1
             }
+            expected_pcs_exit_code(&args, cores, &emulator)
         }
     };
 
+    // A run that diverged from the recorded MMIO writes fails even if it passed
+    let exit_code = if exit_code == EXIT_PASS && !mmio_writes_ok {
+        EXIT_FAIL
+    } else {
+        exit_code
+    };
     std::process::exit(exit_code);
 }
+
+/// Exit code for a run that ended without an error: `EXIT_PASS` if every expected
+/// stop PC was reached (any of them with --stop-on-any-pc), `EXIT_FAIL` otherwise
+fn expected_pcs_exit_code(args: &Args, cores: CoreSelection, emulator: &EmulatorCore) -> i32 {
+    // Check if the expected stop PCs were reached (ignoring disabled cores)
+    let arm9_ok = args
+        .arm9_stop_pc
+        .filter(|_| cores.runs(Core::Arm9))
+        .is_none_or(|expected| emulator.stopped(Core::Arm9) && emulator.pc(Core::Arm9) == expected);
+    let arm11_ok = args
+        .arm11_stop_pc
+        .filter(|_| cores.runs(Core::Arm11))
+        .is_none_or(|expected| {
+            emulator.stopped(Core::Arm11) && emulator.pc(Core::Arm11) == expected
+        });
+
+    // With --stop-on-any-pc, reaching one of the given stop PCs is enough
+    let reached_any = (args.arm9_stop_pc.is_some() && cores.runs(Core::Arm9) && arm9_ok)
+        || (args.arm11_stop_pc.is_some() && cores.runs(Core::Arm11) && arm11_ok);
+    if (arm9_ok && arm11_ok) || (args.stop_on_any_pc && reached_any) {
+        info!("PASS: All stop conditions reached");
+        EXIT_PASS
+    } else {
+        // A limit, hang, or timeout ended the run before the PCs were reached
+        if !arm9_ok {
+            eprintln!(
+                "ARM9 did not reach expected PC {:#X} (actual: {:#X}, stopped: {})",
+                args.arm9_stop_pc.unwrap(),
+                emulator.pc(Core::Arm9),
+                emulator.stopped(Core::Arm9)
+            );
+        }
+        if !arm11_ok {
+            eprintln!(
+                "ARM11 did not reach expected PC {:#X} (actual: {:#X}, stopped: {})",
+                args.arm11_stop_pc.unwrap(),
+                emulator.pc(Core::Arm11),
+                emulator.stopped(Core::Arm11)
+            );
+        }
+        EXIT_FAIL
+    }
+}
+
+/// Print instruction throughput for a benchmark run
+fn print_bench_summary(emulator: &EmulatorCore, json: bool) {
+    let elapsed = emulator.elapsed().as_secs_f64();
+    let mips = |instructions: usize| instructions as f64 / elapsed / 1_000_000.0;
+
+    println!("=== Benchmark ===");
+    println!("Total instructions: {}", emulator.total_executed());
+    println!("Elapsed: {:.3}s", elapsed);
+    println!(
+        "ARM9:  {} instructions ({:.2} MIPS)",
+        emulator.executed(Core::Arm9),
+        mips(emulator.executed(Core::Arm9))
+    );
+    println!(
+        "ARM11: {} instructions ({:.2} MIPS)",
+        emulator.executed(Core::Arm11),
+        mips(emulator.executed(Core::Arm11))
+    );
+    println!("Total: {:.2} MIPS", mips(emulator.total_executed()));
+
+    if json {
+        println!(
+            "{{\"total_instructions\":{},\"elapsed_secs\":{:.6},\"arm9_instructions\":{},\"arm9_mips\":{:.3},\"arm11_instructions\":{},\"arm11_mips\":{:.3},\"total_mips\":{:.3}}}",
+            emulator.total_executed(),
+            elapsed,
+            emulator.executed(Core::Arm9),
+            mips(emulator.executed(Core::Arm9)),
+            emulator.executed(Core::Arm11),
+            mips(emulator.executed(Core::Arm11)),
+            mips(emulator.total_executed())
+        );
+    }
+}
@@ -1,21 +1,24 @@
 use clap::Parser;
-use threemu::{Args, EmulatorCore, display, load_firm_data};
+use threemu::args::{Cli, EXIT_USAGE_ERROR};
+use threemu::{Core, EmulatorCore, display, load_firm_data};
 use tracing::info;
 
 fn main() {
     // Parse command-line arguments
-    let args = Args::parse();
+    let args = Cli::parse().into_run_args();
 
     // Validate arguments
     if let Err(e) = args.validate() {
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        std::process::exit(EXIT_USAGE_ERROR);
+    }
+    if args.dump_state_on_stop.is_some() {
+        eprintln!("Error: --dump-state-on-stop is only supported by the headless CLI");
+        std::process::exit(EXIT_USAGE_ERROR);
     }
 
     // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
+    args.init_logging(tracing_subscriber::EnvFilter::from_default_env());
 
     // Load FIRM data
     let firm_data =
@@ -31,8 +34,8 @@ fn main() {
 
     // Run with display
     info!("=== Starting Emulator with Display ===");
-    info!("ARM9 Entry: {:#X}", emulator.arm9_pc());
-    info!("ARM11 Entry: {:#X}", emulator.arm11_pc());
+    info!("ARM9 Entry: {:#X}", emulator.pc(Core::Arm9));
+    info!("ARM11 Entry: {:#X}", emulator.pc(Core::Arm11));
 
-    display::run(emulator).expect("Failed to run display");
+    display::run(emulator, args.keep_window_on_stop).expect("Failed to run display");
 }
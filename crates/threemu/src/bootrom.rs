@@ -11,6 +11,8 @@ pub const ARM9_REGION_START: u32 = 0xFFFF_0000;
 pub const ARM9_REGION_END: u32 = 0xFFFF_FFFF;
 pub const ARM9_REGION_LEN: u32 = (ARM9_REGION_END - ARM9_REGION_START) + 1;
 
+/// `void bfn_wait_cycles(u32 cycles)`: busy-waits for roughly the number of cycles
+/// passed in R0
 const WAIT_CYCLES_FN_ADDR_OFFSET: u32 = 0x0198;
 
 pub fn handle_instruction(
@@ -20,8 +22,11 @@ pub fn handle_instruction(
     let addr_offset = addr % 0x1_0000;
     match addr_offset {
         WAIT_CYCLES_FN_ADDR_OFFSET => {
-            trace!("handling bootrom function at WAIT_CYCLES_FN_ADDR_OFFSET");
-            // Handling WAIT_CYCLES_FN_ADDR_OFFSET as a no-op.
+            // Rather than spinning, return immediately and let the scheduler advance
+            // the core's cycle clock by the requested number of cycles
+            let cycles = uc.reg_read(RegisterARM::R0).unwrap();
+            trace!("handling bootrom function at WAIT_CYCLES_FN_ADDR_OFFSET ({cycles} cycles)");
+            uc.get_data_mut().waited_cycles += cycles;
         }
         _ => {
             warn!(
@@ -0,0 +1,91 @@
+//! Locating a FIRM inside container files.
+//!
+//! Files given as the FIRM to run may instead be a container that holds one. The
+//! container format is detected from its magic, and the embedded FIRM is extracted;
+//! anything unrecognized is assumed to be a bare FIRM. Supported containers:
+//!
+//! - NCSD images (e.g. a decrypted NAND dump): the first partition that holds an
+//!   unencrypted FIRM, normally `firm0`.
+//!
+//! # References
+//! - [NCSD](https://www.3dbrew.org/wiki/NCSD)
+
+use crate::firm::FirmHeader;
+use tracing::info;
+
+/// Offset of the NCSD magic, after the header signature
+const NCSD_MAGIC_OFFSET: usize = 0x100;
+
+/// Offset of the NCSD partition table: 8 entries of (offset, length) in media units
+const NCSD_PARTITION_TABLE_OFFSET: usize = 0x120;
+
+/// Number of NCSD partition table entries
+const NCSD_PARTITIONS: usize = 8;
+
+/// Size of an NCSD media unit in bytes
+const NCSD_MEDIA_UNIT: usize = 0x200;
+
+/// A container format that can hold a FIRM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Ncsd,
+}
+
+impl Container {
+    /// Identify the container format of `data`, if it is one
+    fn detect(data: &[u8]) -> Option<Self> {
+        if data.get(0..4) == Some(b"FIRM") {
+            return None;
+        }
+        if data.get(NCSD_MAGIC_OFFSET..NCSD_MAGIC_OFFSET + 4) == Some(b"NCSD") {
+            return Some(Container::Ncsd);
+        }
+        None
+    }
+
+    /// Byte range of the embedded FIRM within `data`
+    fn find_firm(self, data: &[u8]) -> Result<std::ops::Range<usize>, String> {
+        match self {
+            Container::Ncsd => (0..NCSD_PARTITIONS)
+                .filter_map(|i| {
+                    let entry = NCSD_PARTITION_TABLE_OFFSET + i * 8;
+                    let word = |at: usize| {
+                        data.get(at..at + 4)
+                            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+                    };
+                    let start = word(entry)? * NCSD_MEDIA_UNIT;
+                    let len = word(entry + 4)? * NCSD_MEDIA_UNIT;
+                    let partition = data.get(start..(start + len).min(data.len()))?;
+                    firm_extent(partition).map(|firm_len| start..start + firm_len)
+                })
+                .next()
+                .ok_or_else(|| "NCSD image has no partition with an unencrypted FIRM".to_string()),
+        }
+    }
+}
+
+/// Length of the FIRM at the start of `data` (header plus all sections), if there
+/// is one and all of its sections fit
+fn firm_extent(data: &[u8]) -> Option<usize> {
+    let firm = FirmHeader::parse(data).ok()?;
+    let len = firm
+        .loadable_sections()
+        .map(|(_, section)| section.file_range().end)
+        .fold(0x200, usize::max);
+    (len <= data.len()).then_some(len)
+}
+
+/// Extract the FIRM from `data` if it's a container, otherwise return it unchanged
+pub fn extract_firm(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let Some(container) = Container::detect(&data) else {
+        return Ok(data);
+    };
+    let range = container.find_firm(&data)?;
+    info!(
+        "Found FIRM in {:?} container at {:#X} ({} bytes)",
+        container,
+        range.start,
+        range.len()
+    );
+    Ok(data[range].to_vec())
+}
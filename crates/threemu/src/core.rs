@@ -3,17 +3,31 @@
 //! This module provides the main emulator interface that can be used both
 //! for headless testing and as the backend for graphical frontends.
 
-use crate::firm::FirmHeader;
+use crate::coverage::OpcodeCoverage;
+use crate::cpu_types::{
+    self, ArmRegister, Core, CoreSelection, CpuModel, Endianness, ProcessorMode,
+};
+use crate::display::{FbByteOrder, FbRotation, FramebufferWarnings};
+use crate::firm::{self, FirmError, FirmHeader};
 use crate::memory::{self, ARM9_PRIVATE_WRAM_SIZE, AXI_WRAM_SIZE, FCRAM_SIZE, VRAM_SIZE};
+use crate::milestones::{BootMilestones, Milestone};
 use crate::mmio;
-use crate::scheduler::{QuantumResult, Scheduler, SchedulerConfig};
+use crate::pctrace::{PcTrace, PcTraceConfig};
+use crate::savestate::{MemorySnapshot, SaveState};
+use crate::scheduler::{
+    self, QuantumProgress, QuantumResult, Scheduler, SchedulerConfig, StopDetail,
+};
 use crate::{bootrom, cp15};
-use std::path::PathBuf;
-use std::time::Instant;
-use tracing::info;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 use unicorn_engine::{
     RegisterARM, Unicorn,
-    unicorn_const::{Arch, Mode, Prot},
+    unicorn_const::{Arch, Prot},
 };
 
 /// Configuration for the emulator
@@ -21,25 +35,527 @@ use unicorn_engine::{
 pub struct EmulatorConfig {
     /// Optional SD card image path
     pub sd_card: Option<PathBuf>,
+    /// SD card image contents to use instead of a file (takes precedence over
+    /// `sd_card`). Writes are kept in memory for the rest of the run.
+    pub sd_card_data: Option<Vec<u8>>,
+    /// Fail SD reads that start past the end of the card image with an out-of-range
+    /// error instead of returning zeros
+    pub strict_sd_reads: bool,
+    /// Number of polls the SD card/NAND stays busy for after an R1b command or a
+    /// write (default `mmio::sdmmc::DEFAULT_BUSY_POLLS`)
+    pub sd_busy_polls: Option<u32>,
+    /// Number of ACMD41 requests the SD card/NAND reports as still initializing
+    /// (default `mmio::sdmmc::DEFAULT_OP_COND_BUSY_POLLS`)
+    pub sd_op_cond_busy_polls: Option<u32>,
+    /// Start ARM9 here instead of at the FIRM's ARM9 entrypoint. Set bit 0 to start
+    /// in Thumb state, as with a `bx` target.
+    pub arm9_entry: Option<u32>,
+    /// Start ARM11 here instead of at the FIRM's ARM11 entrypoint. Set bit 0 to start
+    /// in Thumb state, as with a `bx` target.
+    pub arm11_entry: Option<u32>,
     /// Stop when ARM9 PC reaches this address
     pub arm9_stop_pc: Option<u64>,
     /// Stop when ARM11 PC reaches this address
     pub arm11_stop_pc: Option<u64>,
+    /// Stop as soon as any core reaches its stop PC (by default, emulation continues
+    /// until every core given a stop PC has reached it)
+    pub stop_on_any_pc: bool,
+    /// Stop once the 32-bit word at this address, as seen by this core, holds this
+    /// value (for payloads that signal completion through a memory flag)
+    pub stop_on_mem: Option<(Core, u64, u32)>,
     /// Stop after this many total instructions
     pub max_instructions: Option<usize>,
+    /// Stop after this many frames
+    pub max_frames: Option<usize>,
+    /// Stop ARM9 after it has run this many instructions (ARM11 keeps running)
+    pub arm9_max_instructions: Option<usize>,
+    /// Stop ARM11 after it has run this many instructions (ARM9 keeps running)
+    pub arm11_max_instructions: Option<usize>,
     /// Optional timeout in milliseconds
     pub timeout_ms: Option<u64>,
+    /// Track writes to shared memory and invalidate the other core's translated code
+    /// for the written range after each quantum
+    pub shared_memory_coherence: bool,
+    /// ARM9 clock frequency in Hz (defaults to `scheduler::ARM9_FREQ_HZ`)
+    pub arm9_freq_hz: Option<usize>,
+    /// ARM11 clock frequency in Hz (defaults to `scheduler::ARM11_FREQ_HZ`, use
+    /// `specs::cpu::ARM11_HZ_NEW3DS` for a New 3DS)
+    pub arm11_freq_hz: Option<usize>,
+    /// Stop a core whose PC is unchanged across this many consecutive quanta and
+    /// report it as hung (disabled when unset)
+    pub hang_detect_quanta: Option<usize>,
+    /// Register values to set before execution starts (all others start at zero)
+    pub initial_registers: Vec<(Core, ArmRegister, u32)>,
+    /// Record generic MMIO accesses within this address range (start, end exclusive)
+    pub mmio_log_range: Option<(u32, u32)>,
+    /// Cores to run. A disabled core never executes and its stop PC is ignored.
+    pub cores: CoreSelection,
+    /// Byte order both cores start in (little-endian, as on hardware, by default).
+    /// Either way, a core switching its data endianness with `SETEND` is warned
+    /// about, since memory views and MMIO handlers assume the initial byte order.
+    pub endianness: Endianness,
+    /// CPU model emulated for ARM9 (`CpuModel::default_for(Core::Arm9)` when unset)
+    pub arm9_cpu_model: Option<CpuModel>,
+    /// CPU model emulated for ARM11 (`CpuModel::default_for(Core::Arm11)` when unset)
+    pub arm11_cpu_model: Option<CpuModel>,
+    /// Fixed RTC time in seconds since the Unix epoch, for deterministic runs.
+    /// The host clock is used when unset.
+    pub rtc_epoch_secs: Option<u64>,
+    /// Initial contents of FCRAM, VRAM, AXI WRAM, and ARM9 private WRAM
+    pub ram_init: RamInit,
+    /// Log the PC of every executed instruction to a file
+    pub pc_trace: Option<PcTraceConfig>,
+    /// Fail instead of warning when FIRM sections have overlapping load addresses
+    pub reject_overlapping_sections: bool,
+    /// Refuse to run a FIRM whose header signature doesn't verify against this key,
+    /// as the boot ROM does. Unchecked when unset, since most test FIRMs are unsigned.
+    pub verify_signature: Option<firm::PublicKey>,
+    /// Channel order used to read RGB8 framebuffer pixels when rendering
+    pub fb_byte_order: FbByteOrder,
+    /// Rotation of stored framebuffers relative to the display (90° on hardware)
+    pub fb_rotation: FbRotation,
+    /// Log boot milestones (FIRM parsed, first SDMMC command, first framebuffer, ...)
+    /// as they are reached
+    pub verbose_boot: bool,
+    /// Tally executed instructions by mnemonic and report this many of the most
+    /// executed at exit
+    pub opcode_coverage: Option<usize>,
+    /// Include decoded GPU and SDMMC register dumps in the final state report
+    pub dump_devices: bool,
+    /// Stop as soon as a core executes an instruction outside executable memory
+    /// (unmapped or MMIO space, or outside `exec_regions` if any are given)
+    pub enforce_exec_regions: bool,
+    /// With `enforce_exec_regions`, the only address ranges (start, end exclusive)
+    /// code may run from, e.g. the FIRM's code sections. All executable memory is
+    /// allowed when empty.
+    pub exec_regions: Vec<(u32, u32)>,
+    /// Record every MMIO write to a file, or check the writes against a previous
+    /// recording to find non-determinism
+    pub mmio_replay: Option<mmio::MmioReplay>,
+    /// Buttons reported as held by the HID `PAD` register for the whole run (bit set
+    /// = held, see [`mmio::hid`] for the layout)
+    pub held_buttons: u16,
+}
+
+impl EmulatorConfig {
+    /// Start building a configuration from the defaults
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use threemu::EmulatorConfig;
+    ///
+    /// let config = EmulatorConfig::builder()
+    ///     .sd_card("sd.img")
+    ///     .arm9_stop_pc(0x0801_0000)
+    ///     .max_instructions(10_000_000)
+    ///     .timeout(Duration::from_secs(5))
+    ///     .build();
+    ///
+    /// assert_eq!(config.arm9_stop_pc, Some(0x0801_0000));
+    /// assert_eq!(config.timeout_ms, Some(5000));
+    /// assert_eq!(config.arm11_stop_pc, None);
+    /// ```
+    pub fn builder() -> EmulatorConfigBuilder {
+        EmulatorConfigBuilder::default()
+    }
+}
+
+/// Chainable construction of an [`EmulatorConfig`], leaving unset options at their
+/// defaults
+#[derive(Debug, Clone, Default)]
+pub struct EmulatorConfigBuilder {
+    config: EmulatorConfig,
+}
+
+impl EmulatorConfigBuilder {
+    /// Use the SD card image file at `path`
+    pub fn sd_card(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.sd_card = Some(path.into());
+        self
+    }
+
+    /// Use an in-memory SD card image instead of a file
+    pub fn sd_card_data(mut self, data: Vec<u8>) -> Self {
+        self.config.sd_card_data = Some(data);
+        self
+    }
+
+    /// Fail SD reads that start past the end of the card image
+    pub fn strict_sd_reads(mut self, enabled: bool) -> Self {
+        self.config.strict_sd_reads = enabled;
+        self
+    }
+
+    /// Keep the SD card/NAND busy for `polls` polls after an R1b command or a write
+    pub fn sd_busy_polls(mut self, polls: u32) -> Self {
+        self.config.sd_busy_polls = Some(polls);
+        self
+    }
+
+    /// Report the SD card/NAND as still initializing for the first `polls` ACMD41
+    /// requests
+    pub fn sd_op_cond_busy_polls(mut self, polls: u32) -> Self {
+        self.config.sd_op_cond_busy_polls = Some(polls);
+        self
+    }
+
+    /// Start ARM9 at `addr` instead of the FIRM's entrypoint
+    pub fn arm9_entry(mut self, addr: u32) -> Self {
+        self.config.arm9_entry = Some(addr);
+        self
+    }
+
+    /// Start ARM11 at `addr` instead of the FIRM's entrypoint
+    pub fn arm11_entry(mut self, addr: u32) -> Self {
+        self.config.arm11_entry = Some(addr);
+        self
+    }
+
+    /// Stop when ARM9 reaches `pc`
+    pub fn arm9_stop_pc(mut self, pc: u64) -> Self {
+        self.config.arm9_stop_pc = Some(pc);
+        self
+    }
+
+    /// Stop when ARM11 reaches `pc`
+    pub fn arm11_stop_pc(mut self, pc: u64) -> Self {
+        self.config.arm11_stop_pc = Some(pc);
+        self
+    }
+
+    /// Stop as soon as any core reaches its stop PC
+    pub fn stop_on_any_pc(mut self, enabled: bool) -> Self {
+        self.config.stop_on_any_pc = enabled;
+        self
+    }
+
+    /// Stop once the word at `addr`, as seen by `core`, holds `value`
+    pub fn stop_on_mem(mut self, core: Core, addr: u64, value: u32) -> Self {
+        self.config.stop_on_mem = Some((core, addr, value));
+        self
+    }
+
+    /// Stop after `count` total instructions
+    pub fn max_instructions(mut self, count: usize) -> Self {
+        self.config.max_instructions = Some(count);
+        self
+    }
+
+    /// Stop after `count` frames, e.g. to take a screenshot at a fixed point
+    pub fn max_frames(mut self, count: usize) -> Self {
+        self.config.max_frames = Some(count);
+        self
+    }
+
+    /// Stop ARM9 after it has run `count` instructions
+    pub fn arm9_max_instructions(mut self, count: usize) -> Self {
+        self.config.arm9_max_instructions = Some(count);
+        self
+    }
+
+    /// Stop ARM11 after it has run `count` instructions
+    pub fn arm11_max_instructions(mut self, count: usize) -> Self {
+        self.config.arm11_max_instructions = Some(count);
+        self
+    }
+
+    /// Stop after `timeout` of wall-clock time
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    /// Invalidate the other core's translated code when shared memory is written
+    pub fn shared_memory_coherence(mut self, enabled: bool) -> Self {
+        self.config.shared_memory_coherence = enabled;
+        self
+    }
+
+    /// Run ARM9 at `freq_hz`
+    pub fn arm9_freq_hz(mut self, freq_hz: usize) -> Self {
+        self.config.arm9_freq_hz = Some(freq_hz);
+        self
+    }
+
+    /// Run ARM11 at `freq_hz`
+    pub fn arm11_freq_hz(mut self, freq_hz: usize) -> Self {
+        self.config.arm11_freq_hz = Some(freq_hz);
+        self
+    }
+
+    /// Report a core as hung once its PC is unchanged for `quanta` quanta
+    pub fn hang_detect_quanta(mut self, quanta: usize) -> Self {
+        self.config.hang_detect_quanta = Some(quanta);
+        self
+    }
+
+    /// Set `reg` of `core` to `value` before execution starts (can be repeated)
+    pub fn initial_register(mut self, core: Core, reg: ArmRegister, value: u32) -> Self {
+        self.config.initial_registers.push((core, reg, value));
+        self
+    }
+
+    /// Record generic MMIO accesses within `start..end`
+    pub fn mmio_log_range(mut self, start: u32, end: u32) -> Self {
+        self.config.mmio_log_range = Some((start, end));
+        self
+    }
+
+    /// Run only the selected cores
+    pub fn cores(mut self, cores: CoreSelection) -> Self {
+        self.config.cores = cores;
+        self
+    }
+
+    /// Fix the RTC at `secs` seconds since the Unix epoch
+    pub fn rtc_epoch_secs(mut self, secs: u64) -> Self {
+        self.config.rtc_epoch_secs = Some(secs);
+        self
+    }
+
+    /// Fill RAM this way before the FIRM is loaded
+    pub fn ram_init(mut self, ram_init: RamInit) -> Self {
+        self.config.ram_init = ram_init;
+        self
+    }
+
+    /// Log the PC of every executed instruction
+    pub fn pc_trace(mut self, pc_trace: PcTraceConfig) -> Self {
+        self.config.pc_trace = Some(pc_trace);
+        self
+    }
+
+    /// Fail instead of warning on overlapping FIRM sections
+    pub fn reject_overlapping_sections(mut self, enabled: bool) -> Self {
+        self.config.reject_overlapping_sections = enabled;
+        self
+    }
+
+    /// Check the FIRM header signature against `key` before loading
+    pub fn verify_signature(mut self, key: firm::PublicKey) -> Self {
+        self.config.verify_signature = Some(key);
+        self
+    }
+
+    /// Read RGB8 framebuffer pixels in this channel order
+    pub fn fb_byte_order(mut self, order: FbByteOrder) -> Self {
+        self.config.fb_byte_order = order;
+        self
+    }
+
+    /// Rotation framebuffers are stored in
+    pub fn fb_rotation(mut self, rotation: FbRotation) -> Self {
+        self.config.fb_rotation = rotation;
+        self
+    }
+
+    /// Log boot milestones as they are reached
+    pub fn verbose_boot(mut self, enabled: bool) -> Self {
+        self.config.verbose_boot = enabled;
+        self
+    }
+
+    /// Tally executed mnemonics and report the `top` most executed
+    pub fn opcode_coverage(mut self, top: usize) -> Self {
+        self.config.opcode_coverage = Some(top);
+        self
+    }
+
+    /// Include device register dumps in the final state report
+    pub fn dump_devices(mut self, enabled: bool) -> Self {
+        self.config.dump_devices = enabled;
+        self
+    }
+
+    /// Stop when code runs outside executable memory
+    pub fn enforce_exec_regions(mut self, enabled: bool) -> Self {
+        self.config.enforce_exec_regions = enabled;
+        self
+    }
+
+    /// Allow code to run from `start..end` with `enforce_exec_regions` (can be
+    /// repeated). An empty range allows nothing.
+    pub fn exec_region(mut self, start: u32, end: u32) -> Self {
+        self.config.exec_regions.push((start, end));
+        self
+    }
+
+    /// Record or verify MMIO writes
+    pub fn mmio_replay(mut self, mode: mmio::MmioReplay) -> Self {
+        self.config.mmio_replay = Some(mode);
+        self
+    }
+
+    /// Report `button` as held for the whole run (can be repeated)
+    pub fn hold_button(mut self, button: mmio::Button) -> Self {
+        self.config.held_buttons |= button.mask();
+        self
+    }
+
+    /// Byte order the cores start in
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.config.endianness = endianness;
+        self
+    }
+
+    /// Emulate `model` for `core` instead of the model matching the hardware
+    pub fn cpu_model(mut self, core: Core, model: CpuModel) -> Self {
+        match core {
+            Core::Arm9 => self.config.arm9_cpu_model = Some(model),
+            Core::Arm11 => self.config.arm11_cpu_model = Some(model),
+        }
+        self
+    }
+
+    /// Finish building the configuration
+    pub fn build(self) -> EmulatorConfig {
+        self.config
+    }
+}
+
+/// How RAM is filled before the FIRM is loaded
+///
+/// Hardware doesn't guarantee zeroed RAM at boot, so non-zero fills help surface
+/// firmware that reads memory it never initialized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RamInit {
+    /// All bytes zero
+    #[default]
+    Zero,
+    /// Every byte set to the given value
+    Pattern(u8),
+    /// Pseudo-random bytes from the given seed (the same seed gives the same contents)
+    Random(u64),
+}
+
+impl RamInit {
+    /// Fill each buffer in turn, continuing one random sequence across all of them
+    fn apply(self, buffers: &mut [&mut [u8]]) {
+        match self {
+            RamInit::Zero => {}
+            RamInit::Pattern(byte) => {
+                for buffer in buffers {
+                    buffer.fill(byte);
+                }
+            }
+            RamInit::Random(seed) => {
+                // SplitMix64: fast, and good enough to not look like a pattern
+                let mut state = seed;
+                let mut next = || {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    z ^ (z >> 31)
+                };
+                for buffer in buffers {
+                    for chunk in buffer.chunks_mut(8) {
+                        let bytes = next().to_le_bytes();
+                        chunk.copy_from_slice(&bytes[..chunk.len()]);
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Result of running the emulator
 #[derive(Debug, Clone, PartialEq)]
 pub enum StopReason {
-    /// Reached a stop condition (PC match, max instructions)
+    /// Reached a stop condition (PC match, max instructions); see
+    /// [`EmulatorCore::stop_detail`] for which
     StopCondition,
     /// Timeout reached
     Timeout,
     /// Emulation error occurred
     Error(String),
+    /// The instruction budget passed to `run_for` was used up without stopping
+    Yielded,
+    /// A core's PC stopped changing between quanta (e.g. a `b .` loop), and it was
+    /// the last core still running
+    Hang { core: Core, pc: u64 },
+    /// A stop was requested through [`EmulatorCore::request_stop`] or a [`StopHandle`]
+    Interrupted,
+    /// The predicate passed to `run_until` became true
+    Predicate,
+    /// The callback passed to `run_with_callback` returned `false`
+    Callback,
+    /// A core was about to execute an instruction outside executable memory (see
+    /// `EmulatorConfig::enforce_exec_regions`)
+    NonExecutable { core: Core, pc: u64 },
+    /// The memory word given by `EmulatorConfig::stop_on_mem` holds the expected
+    /// value
+    MemoryValue { core: Core, addr: u64, value: u32 },
+}
+
+/// Handle for stopping a running emulator from another thread
+///
+/// Requesting a stop ends the quantum in progress at the next basic block, and the
+/// run loop then returns [`StopReason::Interrupted`]. The stop is permanent: the
+/// emulator reports itself stopped from then on.
+///
+/// The block hooks that check for a stop mid-quantum are only installed once a
+/// handle has been handed out by [`EmulatorCore::stop_handle`], so runs that never
+/// use one don't pay for them.
+#[derive(Debug, Clone)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    /// Ask the emulator to stop as soon as possible
+    pub fn request_stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether a stop has been requested
+    pub fn stop_requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// SP, LR, and SPSR banked for one processor mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BankedRegisters {
+    pub sp: u32,
+    pub lr: u32,
+    /// `None` for User and System mode, which have no SPSR
+    pub spsr: Option<u32>,
+}
+
+/// Errors that can occur while creating an emulator
+#[derive(Debug)]
+pub enum EmulatorError {
+    /// The FIRM header could not be parsed
+    Firm(FirmError),
+    /// Mapping memory or loading sections into it failed
+    MemoryMap(String),
+    /// Any other Unicorn setup step (engine creation, hooks, registers) failed
+    Setup(String),
+}
+
+impl std::fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmulatorError::Firm(FirmError::InvalidSignature) => {
+                write!(f, "FIRM signature verification failed")
+            }
+            EmulatorError::Firm(e) => write!(f, "Failed to parse FIRM: {:?}", e),
+            EmulatorError::MemoryMap(e) => write!(f, "Memory setup failed: {}", e),
+            EmulatorError::Setup(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
+
+/// A mapped memory region as seen by one core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemRegion {
+    /// First address of the region
+    pub begin: u64,
+    /// Last address of the region (inclusive)
+    pub end: u64,
+    /// Access permissions
+    pub perms: Prot,
 }
 
 /// Core emulator for 3DS
@@ -51,22 +567,104 @@ pub struct EmulatorCore {
     // Shared memory (leaked for 'static lifetime)
     fcram: &'static mut [u8],
     vram: &'static mut [u8],
+    axi_wram: &'static mut [u8],
 
     // Configuration
     timeout_ms: Option<u64>,
     start_time: Instant,
+
+    /// PC trace written by the code hooks, when enabled
+    pc_trace: Option<Arc<Mutex<PcTrace>>>,
+
+    /// Opcode coverage tallied by the code hooks, when enabled
+    opcode_coverage: Option<Rc<RefCell<OpcodeCoverage>>>,
+
+    /// Set to interrupt emulation, checked between quanta (and by a block hook on
+    /// each core once a handle has been handed out)
+    stop_handle: StopHandle,
+
+    /// Whether the stop-request block hooks have been installed
+    stop_hooks_installed: bool,
+
+    /// Boot progress, shared with both cores' MMIO handlers
+    milestones: Arc<BootMilestones>,
+
+    /// Channel order of framebuffer pixels for rendering
+    fb_byte_order: FbByteOrder,
+    fb_rotation: FbRotation,
+
+    /// Whether the final state report includes device register dumps
+    dump_devices: bool,
+
+    /// Core and PC of the first instruction found outside executable memory, when
+    /// `enforce_exec_regions` is set
+    exec_violation: Arc<Mutex<Option<(Core, u64)>>>,
+
+    /// Memory word (core, address, value) that stops emulation once it matches
+    stop_on_mem: Option<(Core, u64, u32)>,
+
+    /// MMIO writes being recorded or verified, shared with both cores
+    mmio_writes: Option<Arc<Mutex<mmio::MmioWriteLog>>>,
+
+    /// Whether each core (indexed by `Core`) made big-endian data accesses when
+    /// emulation started, and whether it has been warned about switching since
+    initial_big_endian: [bool; 2],
+    endian_switch_warned: [bool; 2],
+
+    /// Framebuffer problems renderers have already warned about
+    fb_warnings: FramebufferWarnings,
 }
 
 impl EmulatorCore {
     /// Create a new emulator from FIRM data
-    pub fn new(firm_data: &[u8], config: EmulatorConfig) -> Result<Self, String> {
-        // Parse FIRM header
-        let firm =
-            FirmHeader::parse(firm_data).map_err(|e| format!("Failed to parse FIRM: {:?}", e))?;
+    pub fn new(firm_data: &[u8], config: EmulatorConfig) -> Result<Self, EmulatorError> {
+        let firm = FirmHeader::parse_and_validate(firm_data).map_err(EmulatorError::Firm)?;
+        Self::from_firm(firm, firm_data, config)
+    }
+
+    /// Create a new emulator from an already parsed (and possibly modified) FIRM header
+    ///
+    /// Section offsets in `firm` index into `firm_data`, which normally is the file the
+    /// header was parsed from. The section table is checked against `firm_data` (see
+    /// [`FirmHeader::validate`]) before anything is loaded.
+    pub fn from_firm(
+        firm: FirmHeader,
+        firm_data: &[u8],
+        mut config: EmulatorConfig,
+    ) -> Result<Self, EmulatorError> {
+        firm.validate(firm_data).map_err(EmulatorError::Firm)?;
+        for (core, freq_hz) in [
+            (Core::Arm9, config.arm9_freq_hz),
+            (Core::Arm11, config.arm11_freq_hz),
+        ] {
+            if let Some(freq_hz) = freq_hz
+                && freq_hz < scheduler::MIN_FREQ_HZ
+            {
+                return Err(EmulatorError::Setup(format!(
+                    "{:?} frequency {} Hz is below the minimum of {} Hz",
+                    core,
+                    freq_hz,
+                    scheduler::MIN_FREQ_HZ
+                )));
+            }
+        }
 
         info!("FIRM Magic: {}", String::from_utf8_lossy(&firm.magic));
         info!("ARM11 Entry: {:#X}", firm.arm11_entrypoint);
         info!("ARM9 Entry: {:#X}", firm.arm9_entrypoint);
+        let milestones = Arc::new(BootMilestones::new(config.verbose_boot));
+        milestones.reach(Milestone::FirmParsed, || {
+            format!(
+                "{} sections, ARM9 entry {:#X}, ARM11 entry {:#X}",
+                firm.loadable_sections().count(),
+                firm.arm9_entrypoint,
+                firm.arm11_entrypoint
+            )
+        });
+        if let Some(key) = &config.verify_signature {
+            firm.verify_signature(key).map_err(EmulatorError::Firm)?;
+            info!("FIRM signature verified");
+        }
 
         // Create shared backing memory (leaked to get 'static lifetime)
         // These are shared between ARM9 and ARM11, so we use raw pointers to allow
@@ -84,6 +682,15 @@ impl EmulatorCore {
             AXI_WRAM_SIZE / 1024,
             ARM9_PRIVATE_WRAM_SIZE / 1024
         );
+        if config.ram_init != RamInit::Zero {
+            info!("Initializing RAM: {:?}", config.ram_init);
+            config.ram_init.apply(&mut [
+                &mut *fcram,
+                &mut *vram,
+                &mut *axi_wram,
+                &mut *arm9_private_wram,
+            ]);
+        }
 
         // Get raw pointers for shared memory regions that need to be mapped to both emulators
         // SAFETY: These pointers are leaked and will remain valid for the lifetime of the program.
@@ -92,38 +699,77 @@ impl EmulatorCore {
         let vram_ptr = vram.as_mut_ptr();
         let axi_wram_ptr = axi_wram.as_mut_ptr();
 
-        // Create shared emulator state
-        let emu_state = mmio::EmulatorState::new(config.sd_card.clone());
+        // Create peripheral state shared between both cores
+        let mut sdmmc = match config.sd_card_data.take() {
+            Some(data) => mmio::SdmmcState::from_sd_card_data(data),
+            None => mmio::SdmmcState::new(config.sd_card.clone()),
+        };
+        sdmmc.strict_sd_reads = config.strict_sd_reads;
+        if let Some(polls) = config.sd_busy_polls {
+            sdmmc.busy_polls = polls;
+        }
+        if let Some(polls) = config.sd_op_cond_busy_polls {
+            sdmmc.op_cond_busy_polls = polls;
+        }
+        let sdmmc = Arc::new(Mutex::new(sdmmc));
+        let i2c = Arc::new(Mutex::new(mmio::I2cState::new(config.rtc_epoch_secs)));
+        let config11 = Arc::new(Mutex::new(mmio::Config11State::new()));
+        let hid = Arc::new(Mutex::new(mmio::HidState::new(config.held_buttons)));
+        let mmio_writes = match config.mmio_replay.clone() {
+            Some(mode) => Some(Arc::new(Mutex::new(
+                mmio::MmioWriteLog::new(mode).map_err(EmulatorError::Setup)?,
+            ))),
+            None => None,
+        };
+        let mut emu_state = mmio::EmulatorState::new(
+            Core::Arm11,
+            sdmmc.clone(),
+            i2c.clone(),
+            config11.clone(),
+            hid.clone(),
+        );
+        emu_state.mmio_log = config.mmio_log_range.map(mmio::MmioLogger::new);
+        emu_state.mmio_writes = mmio_writes.clone();
+        emu_state.milestones = milestones.clone();
 
         // Initialize ARM11 emulator
         info!("=== ARM11 Setup ===");
-        let mut arm11_emu = Unicorn::new_with_data(Arch::ARM, Mode::LITTLE_ENDIAN, emu_state)
-            .map_err(|e| format!("Failed to initialize ARM11: {:?}", e))?;
+        let arm11_span = Core::Arm11.span().entered();
+        let mut arm11_emu = Unicorn::new_with_data(Arch::ARM, config.endianness.mode(), emu_state)
+            .map_err(|e| EmulatorError::Setup(format!("Failed to initialize ARM11: {:?}", e)))?;
+        set_cpu_model(&mut arm11_emu, Core::Arm11, config.arm11_cpu_model)?;
 
         // SAFETY: We're intentionally sharing memory between emulators
         unsafe {
             let fcram_slice = std::slice::from_raw_parts_mut(fcram_ptr, FCRAM_SIZE);
             let vram_slice = std::slice::from_raw_parts_mut(vram_ptr, VRAM_SIZE);
             let axi_wram_slice = std::slice::from_raw_parts_mut(axi_wram_ptr, AXI_WRAM_SIZE);
-            memory::setup_arm11_memory(&mut arm11_emu, fcram_slice, axi_wram_slice, vram_slice);
+            memory::setup_arm11_memory(&mut arm11_emu, fcram_slice, axi_wram_slice, vram_slice)
+                .map_err(EmulatorError::MemoryMap)?;
+        }
+        memory::check_section_overlaps(&firm, config.reject_overlapping_sections)
+            .map_err(EmulatorError::MemoryMap)?;
+        let arm11_loaded = memory::load_sections(&mut arm11_emu, &firm, firm_data, Core::Arm11)
+            .map_err(EmulatorError::MemoryMap)?;
+        if config.shared_memory_coherence {
+            memory::track_shared_writes(&mut arm11_emu).map_err(|e| {
+                EmulatorError::Setup(format!("Failed to add ARM11 shared write hook: {:?}", e))
+            })?;
         }
-        memory::load_sections(&mut arm11_emu, &firm.sections, firm_data, false);
 
-        arm11_emu
-            .reg_write(RegisterARM::R0, 123)
-            .expect("failed to write to R0");
-        arm11_emu
-            .reg_write(RegisterARM::R5, 1337)
-            .expect("failed to write to R5");
+        drop(arm11_span);
 
         // Initialize ARM9 emulator
         info!("=== ARM9 Setup ===");
-        let mut arm9_emu = Unicorn::new_with_data(
-            Arch::ARM,
-            Mode::LITTLE_ENDIAN,
-            mmio::EmulatorState::new(config.sd_card.clone()),
-        )
-        .map_err(|e| format!("Failed to initialize ARM9: {:?}", e))?;
+        let arm9_span = Core::Arm9.span().entered();
+        let mut emu_state = mmio::EmulatorState::new(Core::Arm9, sdmmc, i2c, config11, hid);
+        emu_state.mmio_log = config.mmio_log_range.map(mmio::MmioLogger::new);
+        emu_state.mmio_writes = mmio_writes.clone();
+        emu_state.milestones = milestones.clone();
+        let mut arm9_emu =
+            Unicorn::new_with_data(Arch::ARM, config.endianness.mode(), emu_state)
+                .map_err(|e| EmulatorError::Setup(format!("Failed to initialize ARM9: {:?}", e)))?;
+        set_cpu_model(&mut arm9_emu, Core::Arm9, config.arm9_cpu_model)?;
 
         // SAFETY: We're intentionally sharing memory between emulators
         unsafe {
@@ -136,9 +782,14 @@ impl EmulatorCore {
                 axi_wram_slice,
                 vram_slice,
                 arm9_private_wram,
-            );
+            )
+            .map_err(EmulatorError::MemoryMap)?;
+        }
+        if config.shared_memory_coherence {
+            memory::track_shared_writes(&mut arm9_emu).map_err(|e| {
+                EmulatorError::Setup(format!("Failed to add ARM9 shared write hook: {:?}", e))
+            })?;
         }
-        memory::load_sections(&mut arm9_emu, &firm.sections, firm_data, true);
 
         // Add CP15 hook for ARM9
         arm9_emu
@@ -149,7 +800,7 @@ impl EmulatorCore {
                     cp15::handle_cp15_instruction(uc, addr, insn);
                 }
             })
-            .map_err(|e| format!("Failed to add CP15 hook: {:?}", e))?;
+            .map_err(|e| EmulatorError::Setup(format!("Failed to add CP15 hook: {:?}", e)))?;
 
         // Add bootrom hooks for ARM9
         arm9_emu
@@ -158,7 +809,7 @@ impl EmulatorCore {
                 bootrom::ARM9_REGION_LEN as u64,
                 Prot::ALL,
             )
-            .map_err(|e| format!("Failed to map bootrom: {:?}", e))?;
+            .map_err(|e| EmulatorError::MemoryMap(format!("failed to map bootrom: {:?}", e)))?;
         arm9_emu
             .add_code_hook(
                 bootrom::ARM9_REGION_START as u64,
@@ -167,20 +818,176 @@ impl EmulatorCore {
                     bootrom::handle_instruction(uc, addr.try_into().expect("addr must be 32 bit"));
                 },
             )
-            .map_err(|e| format!("Failed to add bootrom hook: {:?}", e))?;
+            .map_err(|e| EmulatorError::Setup(format!("Failed to add bootrom hook: {:?}", e)))?;
+
+        // Load sections once the full ARM9 map (including the bootrom region) exists
+        let arm9_loaded = memory::load_sections(&mut arm9_emu, &firm, firm_data, Core::Arm9)
+            .map_err(EmulatorError::MemoryMap)?;
+        drop(arm9_span);
+        milestones.reach(Milestone::SectionsLoaded, || {
+            format!(
+                "{} on ARM9, {} on ARM11",
+                arm9_loaded.len(),
+                arm11_loaded.len()
+            )
+        });
+        for (i, section) in firm.loadable_sections() {
+            if !arm9_loaded.contains(&i) && !arm11_loaded.contains(&i) {
+                warn!(
+                    "Section {}: addr={:#X}, size={:#X} is not mapped on either core, dropped",
+                    i, section.load_address, section.size
+                );
+            }
+        }
+
+        // Add PC trace hooks for the traced cores
+        let pc_trace = match &config.pc_trace {
+            Some(trace_config) => {
+                let trace = Arc::new(Mutex::new(
+                    PcTrace::create(trace_config).map_err(EmulatorError::Setup)?,
+                ));
+                for (core, emu) in [(Core::Arm9, &mut arm9_emu), (Core::Arm11, &mut arm11_emu)] {
+                    if !trace_config.cores.runs(core) {
+                        continue;
+                    }
+                    let trace = trace.clone();
+                    emu.add_code_hook(0, u64::MAX, move |_uc, addr, _size| {
+                        trace
+                            .lock()
+                            .expect("PC trace lock poisoned")
+                            .record(core, addr);
+                    })
+                    .map_err(|e| {
+                        EmulatorError::Setup(format!(
+                            "Failed to add {:?} PC trace hook: {:?}",
+                            core, e
+                        ))
+                    })?;
+                }
+                Some(trace)
+            }
+            None => None,
+        };
+
+        // Count instructions so logged MMIO writes can be placed in the run
+        if mmio_writes.is_some() {
+            for (core, emu) in [(Core::Arm9, &mut arm9_emu), (Core::Arm11, &mut arm11_emu)] {
+                emu.add_code_hook(0, u64::MAX, |uc, _addr, _size| {
+                    uc.get_data_mut().instruction_count += 1;
+                })
+                .map_err(|e| {
+                    EmulatorError::Setup(format!(
+                        "Failed to add {:?} instruction count hook: {:?}",
+                        core, e
+                    ))
+                })?;
+            }
+        }
+
+        // Add opcode coverage hooks to both cores
+        let opcode_coverage = match config.opcode_coverage {
+            Some(top) => {
+                let coverage = Rc::new(RefCell::new(
+                    OpcodeCoverage::new(top).map_err(EmulatorError::Setup)?,
+                ));
+                for (core, emu) in [(Core::Arm9, &mut arm9_emu), (Core::Arm11, &mut arm11_emu)] {
+                    let coverage = coverage.clone();
+                    emu.add_code_hook(0, u64::MAX, move |uc, addr, size| {
+                        let thumb = cpu_types::in_thumb_state(uc);
+                        let mut insn_bytes = [0u8; 4];
+                        let insn_bytes = &mut insn_bytes[..(size as usize).min(4)];
+                        if uc.mem_read(addr, insn_bytes).is_ok() {
+                            coverage.borrow_mut().record(core, thumb, addr, insn_bytes);
+                        }
+                    })
+                    .map_err(|e| {
+                        EmulatorError::Setup(format!(
+                            "Failed to add {:?} opcode coverage hook: {:?}",
+                            core, e
+                        ))
+                    })?;
+                }
+                Some(coverage)
+            }
+            None => None,
+        };
+
+        // Add hooks that stop emulation when code runs outside executable memory. The
+        // hooks only cover what is non-executable at startup; memory mapped later (e.g.
+        // a TCM moved through CP15) is checked against the map at the time.
+        let exec_violation = Arc::new(Mutex::new(None));
+        if config.enforce_exec_regions {
+            for (core, emu) in [(Core::Arm9, &mut arm9_emu), (Core::Arm11, &mut arm11_emu)] {
+                let regions = emu.mem_regions().map_err(|e| {
+                    EmulatorError::Setup(format!("Failed to list {:?} memory: {:?}", core, e))
+                })?;
+                for (begin, end) in memory::non_executable_ranges(&regions, &config.exec_regions) {
+                    let exec_violation = exec_violation.clone();
+                    let allowed = config.exec_regions.clone();
+                    emu.add_code_hook(begin, end, move |uc, addr, _size| {
+                        let executable = uc
+                            .mem_regions()
+                            .is_ok_and(|regions| memory::is_executable(&regions, &allowed, addr));
+                        if !executable {
+                            warn!("{:?} executing non-executable memory at {:#X}", core, addr);
+                            *exec_violation.lock().expect("exec violation lock poisoned") =
+                                Some((core, addr));
+                            let _ = uc.emu_stop();
+                        }
+                    })
+                    .map_err(|e| {
+                        EmulatorError::Setup(format!(
+                            "Failed to add {:?} executable region hook: {:?}",
+                            core, e
+                        ))
+                    })?;
+                }
+            }
+        }
+
+        let stop_handle = StopHandle(Arc::new(AtomicBool::new(false)));
+
+        // Apply initial register overrides
+        for &(core, reg, value) in &config.initial_registers {
+            let emu = match core {
+                Core::Arm9 => &mut arm9_emu,
+                Core::Arm11 => &mut arm11_emu,
+            };
+            emu.reg_write(RegisterARM::from(reg), value as u64)
+                .map_err(|e| {
+                    EmulatorError::Setup(format!("Failed to set {:?} {:?}: {:?}", core, reg, e))
+                })?;
+        }
+
+        let initial_big_endian = [
+            cpu_types::big_endian_data(&arm9_emu),
+            cpu_types::big_endian_data(&arm11_emu),
+        ];
 
         // Create scheduler
+        let arm9_freq_hz = config.arm9_freq_hz.unwrap_or(scheduler::ARM9_FREQ_HZ);
+        let arm11_freq_hz = config.arm11_freq_hz.unwrap_or(scheduler::ARM11_FREQ_HZ);
         let scheduler_config = SchedulerConfig {
             arm9_stop_pc: config.arm9_stop_pc,
             arm11_stop_pc: config.arm11_stop_pc,
+            stop_on_any_pc: config.stop_on_any_pc,
             max_instructions: config.max_instructions,
-            ..Default::default()
+            max_frames: config.max_frames,
+            arm9_max_instructions: config.arm9_max_instructions,
+            arm11_max_instructions: config.arm11_max_instructions,
+            hang_detect_quanta: config.hang_detect_quanta,
+            cores: config.cores,
+            ..SchedulerConfig::with_frequencies(arm9_freq_hz, arm11_freq_hz)
         };
-        let scheduler = Scheduler::new(
-            scheduler_config,
-            firm.arm9_entrypoint as u64,
-            firm.arm11_entrypoint as u64,
-        );
+        let arm9_entry = config.arm9_entry.unwrap_or(firm.arm9_entrypoint);
+        let arm11_entry = config.arm11_entry.unwrap_or(firm.arm11_entrypoint);
+        if arm9_entry != firm.arm9_entrypoint || arm11_entry != firm.arm11_entrypoint {
+            info!(
+                "Entry points overridden: ARM9 {:#X}, ARM11 {:#X}",
+                arm9_entry, arm11_entry
+            );
+        }
+        let scheduler = Scheduler::new(scheduler_config, arm9_entry as u64, arm11_entry as u64);
 
         Ok(Self {
             arm9_emu,
@@ -188,42 +995,164 @@ impl EmulatorCore {
             scheduler,
             fcram,
             vram,
+            axi_wram,
             timeout_ms: config.timeout_ms,
             start_time: Instant::now(),
+            pc_trace,
+            opcode_coverage,
+            stop_handle,
+            stop_hooks_installed: false,
+            milestones,
+            fb_byte_order: config.fb_byte_order,
+            fb_rotation: config.fb_rotation,
+            dump_devices: config.dump_devices,
+            exec_violation,
+            stop_on_mem: config.stop_on_mem,
+            mmio_writes,
+            initial_big_endian,
+            endian_switch_warned: [false; 2],
+            fb_warnings: FramebufferWarnings::default(),
         })
     }
 
     /// Run a single quantum of execution
     pub fn step(&mut self) -> QuantumResult {
-        self.scheduler
-            .run_quantum(&mut self.arm9_emu, &mut self.arm11_emu)
+        match self.step_detailed() {
+            Ok(_) => QuantumResult::Continue,
+            Err(e) => QuantumResult::Error(e),
+        }
+    }
+
+    /// Run a single quantum of execution, reporting how many instructions each core
+    /// ran and whether it stopped, e.g. for a profiler showing scheduling balance
+    pub fn step_detailed(&mut self) -> Result<QuantumProgress, String> {
+        if !self.scheduler.arm9_stopped() {
+            self.milestones.reach(Milestone::Arm9Started, || {
+                format!("PC {:#X}", self.scheduler.arm9_pc())
+            });
+        }
+        if !self.scheduler.arm11_stopped() {
+            self.milestones.reach(Milestone::Arm11Started, || {
+                format!("PC {:#X}", self.scheduler.arm11_pc())
+            });
+        }
+
+        let result = self
+            .scheduler
+            .run_quantum_detailed(&mut self.arm9_emu, &mut self.arm11_emu);
+        self.check_endianness();
+
+        if self.scheduler.frame_ended() {
+            self.milestones.reach(Milestone::FirstVBlank, || {
+                format!("after {} instructions", self.scheduler.total_executed())
+            });
+        }
+        result
+    }
+
+    /// Warn, once per core, when a core's data endianness no longer matches the one
+    /// it started with (e.g. after `SETEND BE`)
+    ///
+    /// Only the state at the end of each quantum is seen, so a switch that is undone
+    /// within the same quantum goes unnoticed.
+    fn check_endianness(&mut self) {
+        for core in [Core::Arm9, Core::Arm11] {
+            let index = core as usize;
+            let big_endian = cpu_types::big_endian_data(self.emu(core));
+            if self.endian_switch_warned[index] || big_endian == self.initial_big_endian[index] {
+                continue;
+            }
+            self.endian_switch_warned[index] = true;
+            warn!(
+                "{:?} switched to {}-endian data accesses (SETEND) by PC {:#X}; memory views and MMIO handlers assume the initial byte order, so results may be wrong",
+                core,
+                if big_endian { "big" } else { "little" },
+                self.emu(core).reg_read(RegisterARM::PC).unwrap_or(0)
+            );
+        }
     }
 
     /// Check if any stop condition is met
     pub fn should_stop(&self) -> bool {
+        self.pending_stop().is_some()
+    }
+
+    /// Reason to report for the stop condition that is met, if any
+    fn pending_stop(&self) -> Option<StopReason> {
+        if self.stop_handle.stop_requested() {
+            return Some(StopReason::Interrupted);
+        }
+        if let Some((core, pc)) = self.exec_violation() {
+            return Some(StopReason::NonExecutable { core, pc });
+        }
+        if let Some(reason) = self.stop_on_mem_match() {
+            return Some(reason);
+        }
+
         // Check scheduler stop conditions
         if self.scheduler.check_stop_conditions() {
-            return true;
+            return Some(match self.scheduler.stopping_hang() {
+                Some((core, pc)) => StopReason::Hang { core, pc },
+                None => StopReason::StopCondition,
+            });
         }
 
         // Check timeout
-        if let Some(timeout_ms) = self.timeout_ms {
-            let elapsed_ms = self.start_time.elapsed().as_millis() as u64;
-            if elapsed_ms >= timeout_ms {
-                info!("Timeout reached: {} ms", elapsed_ms);
-                return true;
-            }
+        if self.timed_out() {
+            info!(
+                "Timeout reached: {} ms",
+                self.start_time.elapsed().as_millis()
+            );
+            return Some(StopReason::Timeout);
         }
 
-        false
+        None
+    }
+
+    /// Wall-clock time left before the configured timeout stops emulation, if a
+    /// timeout is set
+    pub fn timeout_remaining(&self) -> Option<Duration> {
+        self.timeout_ms
+            .map(|ms| Duration::from_millis(ms).saturating_sub(self.start_time.elapsed()))
+    }
+
+    /// Whether the configured timeout has elapsed
+    fn timed_out(&self) -> bool {
+        self.timeout_remaining() == Some(Duration::ZERO)
     }
 
     /// Run until a stop condition is reached
     pub fn run(&mut self) -> StopReason {
+        self.run_with_callback(|_| true)
+    }
+
+    /// Run until a stop condition is reached or `callback` returns `false`
+    ///
+    /// The callback is invoked after every quantum (use [`Self::frame_ended`] to act
+    /// once per frame), e.g. to report progress or check conditions the emulator
+    /// doesn't know about. Returns `StopReason::Callback` once it asks to stop.
+    ///
+    /// ```no_run
+    /// # let firm_data = [];
+    /// use threemu::{EmulatorConfig, EmulatorCore, StopReason};
+    ///
+    /// let mut emulator = EmulatorCore::new(&firm_data, EmulatorConfig::default()).unwrap();
+    /// let mut quanta = 0;
+    /// let reason = emulator.run_with_callback(|_| {
+    ///     quanta += 1;
+    ///     quanta < 100
+    /// });
+    /// assert_eq!(reason, StopReason::Callback);
+    /// assert_eq!(quanta, 100);
+    /// ```
+    pub fn run_with_callback(
+        &mut self,
+        mut callback: impl FnMut(&EmulatorCore) -> bool,
+    ) -> StopReason {
         loop {
             // Check stop conditions first
-            if self.should_stop() {
-                return StopReason::StopCondition;
+            if let Some(reason) = self.pending_stop() {
+                return reason;
             }
 
             // Run a quantum
@@ -231,27 +1160,257 @@ impl EmulatorCore {
                 QuantumResult::Continue => {}
                 QuantumResult::Error(e) => return StopReason::Error(e),
             }
+
+            if !callback(self) {
+                return StopReason::Callback;
+            }
+        }
+    }
+
+    /// Run until `predicate` holds, a stop condition is reached, or `timeout` (wall
+    /// clock) elapses
+    ///
+    /// The predicate is checked before each quantum, so it is seen true at most one
+    /// quantum late. Returns `StopReason::Predicate` once it holds, or
+    /// `StopReason::Timeout` if the timeout elapses first.
+    pub fn run_until(
+        &mut self,
+        mut predicate: impl FnMut(&EmulatorCore) -> bool,
+        timeout: Duration,
+    ) -> StopReason {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if predicate(self) {
+                return StopReason::Predicate;
+            }
+            if let Some(reason) = self.pending_stop() {
+                return reason;
+            }
+            if Instant::now() >= deadline {
+                info!("run_until timed out after {:?}", timeout);
+                return StopReason::Timeout;
+            }
+
+            match self.step() {
+                QuantumResult::Continue => {}
+                QuantumResult::Error(e) => return StopReason::Error(e),
+            }
+        }
+    }
+
+    /// Run until the firmware sets a framebuffer address for either screen, e.g. to
+    /// take a screenshot once something is being displayed
+    ///
+    /// Returns `StopReason::Predicate` once a framebuffer is configured; see
+    /// [`Self::run_until`] for the other outcomes.
+    pub fn run_until_framebuffer(&mut self, timeout: Duration) -> StopReason {
+        self.run_until(
+            |emulator| {
+                let gpu = &emulator.arm11_emu.get_data().gpu;
+                gpu.active_top_addr() != 0 || gpu.active_bottom_addr() != 0
+            },
+            timeout,
+        )
+    }
+
+    /// Run until a stop condition is reached or roughly `budget` instructions have
+    /// executed, whichever comes first
+    ///
+    /// The budget is checked at quantum boundaries, so it is rounded up to whole
+    /// quanta. Returns `StopReason::Yielded` if emulation can continue, letting a host
+    /// event loop interleave emulation with its own work.
+    pub fn run_for(&mut self, budget: usize) -> StopReason {
+        let target = self.total_executed().saturating_add(budget);
+        while self.total_executed() < target {
+            if let Some(reason) = self.pending_stop() {
+                return reason;
+            }
+
+            match self.step() {
+                QuantumResult::Continue => {}
+                QuantumResult::Error(e) => return StopReason::Error(e),
+            }
+        }
+
+        self.pending_stop().unwrap_or(StopReason::Yielded)
+    }
+
+    /// `StopReason::MemoryValue` if the `stop_on_mem` word holds its expected value
+    ///
+    /// The word is read without side effects on MMIO, in the core's current data
+    /// endianness. Checked between quanta, so a value that is written and changed
+    /// again within one quantum is missed.
+    fn stop_on_mem_match(&self) -> Option<StopReason> {
+        let (core, addr, value) = self.stop_on_mem?;
+        let bytes: [u8; 4] = self.mem_read(core, addr, 4).ok()?.try_into().ok()?;
+        let word = if cpu_types::big_endian_data(self.emu(core)) {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        (word == value).then_some(StopReason::MemoryValue { core, addr, value })
+    }
+
+    /// Core and PC where execution left executable memory, if it has
+    fn exec_violation(&self) -> Option<(Core, u64)> {
+        *self
+            .exec_violation
+            .lock()
+            .expect("exec violation lock poisoned")
+    }
+
+    /// Stop emulation before the next quantum starts
+    ///
+    /// The run loops return `StopReason::Interrupted` from then on. This can only be
+    /// called between quanta (e.g. from a [`Self::run_with_callback`] callback), so it
+    /// never cuts a quantum short. To end the quantum in progress from another thread
+    /// (e.g. a GUI stop button or a watchdog), use a [`StopHandle`] from
+    /// [`Self::stop_handle`] instead.
+    pub fn request_stop(&self) {
+        self.stop_handle.request_stop();
+    }
+
+    /// Handle that can stop this emulator from any thread
+    ///
+    /// The first call adds block hooks to both cores that end the quantum in
+    /// progress once a stop is requested.
+    pub fn stop_handle(&mut self) -> Result<StopHandle, String> {
+        if !self.stop_hooks_installed {
+            for (core, emu) in [
+                (Core::Arm9, &mut self.arm9_emu),
+                (Core::Arm11, &mut self.arm11_emu),
+            ] {
+                let stop_handle = self.stop_handle.clone();
+                emu.add_block_hook(0, u64::MAX, move |uc, _addr, _size| {
+                    if stop_handle.stop_requested() {
+                        let _ = uc.emu_stop();
+                    }
+                })
+                .map_err(|e| format!("Failed to add {:?} stop hook: {:?}", core, e))?;
+            }
+            self.stop_hooks_installed = true;
+        }
+        Ok(self.stop_handle.clone())
+    }
+
+    /// Discard translated code in `start..end` on both cores
+    ///
+    /// Use this after modifying executable shared memory from outside the emulated
+    /// CPUs, or when automatic shared memory coherence is disabled.
+    pub fn flush_code_cache(&mut self, start: u64, end: u64) -> Result<(), String> {
+        self.arm9_emu
+            .ctl_remove_cache(start, end)
+            .map_err(|e| format!("ARM9 cache flush error: {:?}", e))?;
+        self.arm11_emu
+            .ctl_remove_cache(start, end)
+            .map_err(|e| format!("ARM11 cache flush error: {:?}", e))?;
+        Ok(())
+    }
+
+    /// Get the current PC of `core`
+    pub fn pc(&self, core: Core) -> u64 {
+        match core {
+            Core::Arm9 => self.scheduler.arm9_pc(),
+            Core::Arm11 => self.scheduler.arm11_pc(),
         }
     }
 
     /// Get the current ARM9 PC
+    #[deprecated(note = "use `pc(Core::Arm9)`")]
     pub fn arm9_pc(&self) -> u64 {
-        self.scheduler.arm9_pc()
+        self.pc(Core::Arm9)
     }
 
     /// Get the current ARM11 PC
+    #[deprecated(note = "use `pc(Core::Arm11)`")]
     pub fn arm11_pc(&self) -> u64 {
-        self.scheduler.arm11_pc()
+        self.pc(Core::Arm11)
+    }
+
+    /// Whether the most recent `step` completed a frame (the display refresh point)
+    pub fn frame_ended(&self) -> bool {
+        self.scheduler.frame_ended()
+    }
+
+    /// Number of frames emulated so far
+    pub fn frames_completed(&self) -> usize {
+        self.scheduler.frames_completed()
+    }
+
+    /// Addresses at which `core` stops
+    ///
+    /// These are the core's stop PCs: this emulator supports one per core, set with
+    /// `EmulatorConfig::arm9_stop_pc`/`arm11_stop_pc` or [`Self::add_breakpoint`], so
+    /// the list holds at most one address.
+    pub fn breakpoints(&self, core: Core) -> Vec<u64> {
+        self.scheduler.stop_pc(core).into_iter().collect()
+    }
+
+    /// Stop `core` when it reaches `addr`, returning the breakpoint this replaces
+    ///
+    /// A core has at most one breakpoint, so any other is removed (see
+    /// [`Self::remove_breakpoint`]). Takes effect from the next quantum.
+    pub fn add_breakpoint(&mut self, core: Core, addr: u64) -> Option<u64> {
+        let replaced = self.scheduler.stop_pc(core).filter(|&old| old != addr);
+        self.scheduler.set_stop_pc(core, Some(addr));
+        replaced
+    }
+
+    /// Remove the breakpoint at `addr` from `core`, returning whether there was one
+    ///
+    /// Takes effect from the next quantum. If the core is halted at that breakpoint,
+    /// it resumes from there on the next `step`/`run`.
+    pub fn remove_breakpoint(&mut self, core: Core, addr: u64) -> bool {
+        let present = self.scheduler.stop_pc(core) == Some(addr);
+        if present {
+            self.scheduler.set_stop_pc(core, None);
+        }
+        present
+    }
+
+    /// Remove every breakpoint from `core` (see [`Self::remove_breakpoint`])
+    pub fn clear_breakpoints(&mut self, core: Core) {
+        self.scheduler.set_stop_pc(core, None);
+    }
+
+    /// Check if `core` has stopped (reached a stop PC)
+    pub fn stopped(&self, core: Core) -> bool {
+        match core {
+            Core::Arm9 => self.scheduler.arm9_stopped(),
+            Core::Arm11 => self.scheduler.arm11_stopped(),
+        }
     }
 
     /// Check if ARM9 has stopped (reached a stop PC)
+    #[deprecated(note = "use `stopped(Core::Arm9)`")]
     pub fn arm9_stopped(&self) -> bool {
-        self.scheduler.arm9_stopped()
+        self.stopped(Core::Arm9)
     }
 
     /// Check if ARM11 has stopped (reached a stop PC)
+    #[deprecated(note = "use `stopped(Core::Arm11)`")]
     pub fn arm11_stopped(&self) -> bool {
-        self.scheduler.arm11_stopped()
+        self.stopped(Core::Arm11)
+    }
+
+    /// PC at which `core` was detected as hung, if hang detection triggered
+    pub fn hang_pc(&self, core: Core) -> Option<u64> {
+        match core {
+            Core::Arm9 => self.scheduler.arm9_hang_pc(),
+            Core::Arm11 => self.scheduler.arm11_hang_pc(),
+        }
+    }
+
+    /// PC at which ARM9 was detected as hung, if hang detection triggered
+    #[deprecated(note = "use `hang_pc(Core::Arm9)`")]
+    pub fn arm9_hang_pc(&self) -> Option<u64> {
+        self.hang_pc(Core::Arm9)
+    }
+
+    /// PC at which ARM11 was detected as hung, if hang detection triggered
+    #[deprecated(note = "use `hang_pc(Core::Arm11)`")]
+    pub fn arm11_hang_pc(&self) -> Option<u64> {
+        self.hang_pc(Core::Arm11)
     }
 
     /// Get total instructions executed
@@ -259,29 +1418,204 @@ impl EmulatorCore {
         self.scheduler.total_executed()
     }
 
+    /// Which scheduler stop condition is met, to tell apart the causes of
+    /// [`StopReason::StopCondition`] (stop PCs reached, instruction limit, or every
+    /// core stopped)
+    pub fn stop_detail(&self) -> Option<StopDetail> {
+        self.scheduler.stop_detail()
+    }
+
+    /// Emulated time elapsed, derived from the instructions each core has executed
+    /// (plus cycles spent in bootrom waits) and its clock frequency, independent of
+    /// host speed (see [`Self::elapsed`] for wall-clock time)
+    pub fn emulated_time(&self) -> Duration {
+        self.scheduler.emulated_time()
+    }
+
+    /// Get instructions executed by `core`
+    pub fn executed(&self, core: Core) -> usize {
+        match core {
+            Core::Arm9 => self.scheduler.arm9_executed(),
+            Core::Arm11 => self.scheduler.arm11_executed(),
+        }
+    }
+
+    /// Get instructions executed by ARM9
+    #[deprecated(note = "use `executed(Core::Arm9)`")]
+    pub fn arm9_executed(&self) -> usize {
+        self.executed(Core::Arm9)
+    }
+
+    /// Get instructions executed by ARM11
+    #[deprecated(note = "use `executed(Core::Arm11)`")]
+    pub fn arm11_executed(&self) -> usize {
+        self.executed(Core::Arm11)
+    }
+
     /// Get elapsed time since start
     pub fn elapsed(&self) -> std::time::Duration {
         self.start_time.elapsed()
     }
 
+    /// Read any Unicorn register of `core` (see [`Self::read_register`] for the
+    /// registers savestates and the CLI know about)
+    pub fn reg(&self, core: Core, reg: RegisterARM) -> u64 {
+        self.emu(core).reg_read(reg).unwrap_or(0)
+    }
+
     /// Read an ARM9 register
+    #[deprecated(note = "use `reg(Core::Arm9, reg)`")]
     pub fn arm9_reg(&self, reg: RegisterARM) -> u64 {
-        self.arm9_emu.reg_read(reg).unwrap_or(0)
+        self.reg(Core::Arm9, reg)
     }
 
     /// Read an ARM11 register
+    #[deprecated(note = "use `reg(Core::Arm11, reg)`")]
     pub fn arm11_reg(&self, reg: RegisterARM) -> u64 {
-        self.arm11_emu.reg_read(reg).unwrap_or(0)
+        self.reg(Core::Arm11, reg)
+    }
+
+    /// Read a register of `core`
+    pub fn read_register(&self, core: Core, reg: ArmRegister) -> u64 {
+        self.emu(core).reg_read(RegisterARM::from(reg)).unwrap_or(0)
+    }
+
+    /// Read the SPSR of `core`'s current processor mode
+    ///
+    /// User and System mode have no SPSR, so the value is meaningless there.
+    pub fn read_spsr(&self, core: Core) -> u64 {
+        self.emu(core).reg_read(RegisterARM::SPSR).unwrap_or(0)
+    }
+
+    /// Read the SP, LR, and SPSR that `core` banks for `mode`, which need not be
+    /// the current mode
+    ///
+    /// Unicorn only exposes the current mode's registers, so this briefly switches
+    /// the core into `mode` and back. User mode can't switch modes, so only its own
+    /// bank (shared with System mode) can be read while a core is in it.
+    pub fn banked_registers(
+        &mut self,
+        core: Core,
+        mode: ProcessorMode,
+    ) -> Result<BankedRegisters, String> {
+        let emu = match core {
+            Core::Arm9 => &mut self.arm9_emu,
+            Core::Arm11 => &mut self.arm11_emu,
+        };
+        let read = |emu: &Unicorn<'static, mmio::EmulatorState>, reg| {
+            emu.reg_read(reg)
+                .map(|value| value as u32)
+                .map_err(|e| format!("Failed to read {:?} {:?}: {:?}", core, reg, e))
+        };
+        let cpsr = read(emu, RegisterARM::CPSR)?;
+        let target = (cpsr & !cpu_types::CPSR_MODE_MASK) | mode.bits();
+        let switch = |emu: &mut Unicorn<'static, mmio::EmulatorState>, value: u32| {
+            emu.reg_write(RegisterARM::CPSR, value as u64)
+                .map_err(|e| format!("Failed to switch {:?} mode: {:?}", core, e))
+        };
+
+        switch(emu, target)?;
+        let banked = (|| {
+            if read(emu, RegisterARM::CPSR)? & cpu_types::CPSR_MODE_MASK != mode.bits() {
+                return Err(format!(
+                    "{:?} can't switch from {:?} to {:?} mode to read its registers",
+                    core,
+                    ProcessorMode::from_cpsr(cpsr),
+                    mode
+                ));
+            }
+            let spsr = if mode.has_spsr() {
+                Some(read(emu, RegisterARM::SPSR)?)
+            } else {
+                None
+            };
+            Ok(BankedRegisters {
+                sp: read(emu, RegisterARM::SP)?,
+                lr: read(emu, RegisterARM::LR)?,
+                spsr,
+            })
+        })();
+        // Restore the original mode even if a read failed
+        switch(emu, cpsr)?;
+        banked
+    }
+
+    /// Unicorn instance emulating `core`
+    pub fn emu(&self, core: Core) -> &Unicorn<'static, mmio::EmulatorState> {
+        match core {
+            Core::Arm9 => &self.arm9_emu,
+            Core::Arm11 => &self.arm11_emu,
+        }
+    }
+
+    /// Attach custom MMIO handlers to `base..base + len` on `core`
+    ///
+    /// This is meant for experimenting with device models outside the crate. The range
+    /// must be 4 KiB aligned. Whatever was mapped there before, usually the generic
+    /// MMIO stub but possibly a built-in device or RAM, is unmapped and replaced for the
+    /// overlapping part only. A `None` handler falls back to the generic stub for that
+    /// access direction. To model a device both cores see, map it on each core.
+    ///
+    /// Handlers receive offsets relative to `base` and run with the core's
+    /// `EmulatorState`, so they can reach the shared device state through it.
+    pub fn map_mmio(
+        &mut self,
+        core: Core,
+        base: u64,
+        len: u64,
+        read: Option<mmio::CustomReadHandler>,
+        write: Option<mmio::CustomWriteHandler>,
+    ) -> Result<(), String> {
+        let emu = match core {
+            Core::Arm9 => &mut self.arm9_emu,
+            Core::Arm11 => &mut self.arm11_emu,
+        };
+        memory::map_custom_mmio(emu, base, len, read, write)
+            .map_err(|e| format!("{:?}: {}", core, e))
+    }
+
+    /// List the memory regions mapped on a core, sorted by base address
+    pub fn memory_regions(&self, core: Core) -> Vec<MemRegion> {
+        let emu = self.emu(core);
+        let mut regions: Vec<MemRegion> = emu
+            .mem_regions()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|region| MemRegion {
+                begin: region.begin,
+                end: region.end,
+                perms: region.perms,
+            })
+            .collect();
+        regions.sort_by_key(|region| region.begin);
+        regions
     }
 
     /// Get a reference to the ARM11 emulator (for GPU state access)
+    #[deprecated(note = "use `emu(Core::Arm11)`")]
     pub fn arm11_emu(&self) -> &Unicorn<'static, mmio::EmulatorState> {
-        &self.arm11_emu
+        self.emu(Core::Arm11)
     }
 
     /// Get a reference to the ARM9 emulator
+    #[deprecated(note = "use `emu(Core::Arm9)`")]
     pub fn arm9_emu(&self) -> &Unicorn<'static, mmio::EmulatorState> {
-        &self.arm9_emu
+        self.emu(Core::Arm9)
+    }
+
+    /// Channel order renderers should read framebuffer pixels in
+    pub fn fb_byte_order(&self) -> FbByteOrder {
+        self.fb_byte_order
+    }
+
+    /// Framebuffer problems renderers have already warned about for this emulator
+    pub(crate) fn fb_warnings(&self) -> &FramebufferWarnings {
+        &self.fb_warnings
+    }
+
+    /// Rotation renderers should undo when reading framebuffers
+    pub fn fb_rotation(&self) -> FbRotation {
+        self.fb_rotation
     }
 
     /// Get FCRAM buffer
@@ -294,77 +1628,751 @@ impl EmulatorCore {
         self.vram
     }
 
-    /// Read memory from ARM9's perspective
-    pub fn arm9_mem_read(&self, addr: u64, size: usize) -> Result<Vec<u8>, String> {
+    /// Get AXI WRAM buffer
+    pub fn axi_wram(&self) -> &[u8] {
+        self.axi_wram
+    }
+
+    /// RAM shared by both cores and the display controllers, as (physical base,
+    /// contents) pairs
+    ///
+    /// The VRAM banks are listed separately: a framebuffer that starts in one bank
+    /// doesn't continue into the other.
+    fn phys_regions(&self) -> [(u32, &[u8]); 4] {
+        let (bank_a, bank_b) = self.vram.split_at(VRAM_SIZE / 2);
+        [
+            (memory::FCRAM_BASE, &*self.fcram),
+            (memory::VRAM_BASE, bank_a),
+            (memory::VRAM_BASE + (VRAM_SIZE / 2) as u32, bank_b),
+            (memory::AXI_WRAM_BASE, &*self.axi_wram),
+        ]
+    }
+
+    /// Read up to `len` bytes of shared RAM (FCRAM, VRAM, or AXI WRAM) at physical
+    /// address `addr`, without going through either core's MMU or MMIO handlers
+    ///
+    /// The result is shorter than `len` if the region ends first. Returns `None` if
+    /// `addr` isn't in shared RAM.
+    pub fn read_phys(&self, addr: u32, len: usize) -> Option<&[u8]> {
+        self.phys_regions().into_iter().find_map(|(base, data)| {
+            let offset = addr.checked_sub(base)? as usize;
+            let rest = data.get(offset..).filter(|rest| !rest.is_empty())?;
+            Some(&rest[..len.min(rest.len())])
+        })
+    }
+
+    /// Read memory from `core`'s perspective
+    ///
+    /// MMIO registers read without side effects where the device model supports it
+    /// (e.g. SDMMC busy polls aren't counted).
+    pub fn mem_read(&self, core: Core, addr: u64, size: usize) -> Result<Vec<u8>, String> {
         let mut buf = vec![0u8; size];
-        self.arm9_emu
-            .mem_read(addr, &mut buf)
-            .map_err(|e| format!("ARM9 mem read error: {:?}", e))?;
+        let emu = self.emu(core);
+        let host_access = &emu.get_data().host_access;
+        host_access.store(true, Ordering::Relaxed);
+        let result = emu.mem_read(addr, &mut buf);
+        host_access.store(false, Ordering::Relaxed);
+        result.map_err(|e| format!("{:?} mem read error: {:?}", core, e))?;
         Ok(buf)
     }
 
+    /// Read memory from ARM9's perspective
+    #[deprecated(note = "use `mem_read(Core::Arm9, addr, size)`")]
+    pub fn arm9_mem_read(&self, addr: u64, size: usize) -> Result<Vec<u8>, String> {
+        self.mem_read(Core::Arm9, addr, size)
+    }
+
     /// Read memory from ARM11's perspective
+    #[deprecated(note = "use `mem_read(Core::Arm11, addr, size)`")]
     pub fn arm11_mem_read(&self, addr: u64, size: usize) -> Result<Vec<u8>, String> {
-        let mut buf = vec![0u8; size];
-        self.arm11_emu
-            .mem_read(addr, &mut buf)
-            .map_err(|e| format!("ARM11 mem read error: {:?}", e))?;
-        Ok(buf)
+        self.mem_read(Core::Arm11, addr, size)
+    }
+
+    /// Capture registers and main memory of both cores
+    pub fn snapshot(&self) -> Result<SaveState, String> {
+        let registers = |core| ArmRegister::ALL.map(|reg| self.read_register(core, reg) as u32);
+        Ok(SaveState {
+            arm9_registers: registers(Core::Arm9),
+            arm11_registers: registers(Core::Arm11),
+            memory: vec![
+                MemorySnapshot {
+                    name: "FCRAM".to_string(),
+                    base: memory::FCRAM_BASE,
+                    data: self.fcram.to_vec(),
+                },
+                MemorySnapshot {
+                    name: "VRAM".to_string(),
+                    base: memory::VRAM_BASE,
+                    data: self.vram.to_vec(),
+                },
+                snapshot_region(
+                    &self.arm11_emu,
+                    "AXI WRAM",
+                    memory::AXI_WRAM_BASE,
+                    AXI_WRAM_SIZE,
+                )?,
+                snapshot_region(
+                    &self.arm9_emu,
+                    "ARM9 private WRAM",
+                    memory::ARM9_PRIVATE_WRAM_BASE,
+                    ARM9_PRIVATE_WRAM_SIZE,
+                )?,
+            ],
+        })
+    }
+
+    /// Write a save state of the current emulator state to `path`
+    pub fn save_state(&self, path: &Path) -> Result<(), String> {
+        self.snapshot()?
+            .save(path)
+            .map_err(|e| format!("Failed to write save state {:?}: {}", path, e))
+    }
+
+    /// Write out any buffered PC trace entries
+    ///
+    /// The trace is also flushed when the emulator is dropped, but callers that exit
+    /// the process directly need to flush it themselves.
+    pub fn flush_pc_trace(&self) {
+        if let Some(trace) = &self.pc_trace {
+            let mut trace = trace.lock().expect("PC trace lock poisoned");
+            trace.flush();
+            info!("PC trace: {} entries", trace.written());
+        }
+    }
+
+    /// Write out the recorded MMIO write log, or check that the run made every write
+    /// in the log being verified against
+    ///
+    /// Does nothing unless `mmio_replay` is configured.
+    pub fn finish_mmio_write_log(&self) -> Result<(), String> {
+        match &self.mmio_writes {
+            Some(log) => log.lock().expect("MMIO write log lock poisoned").finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Log the boot milestones that were never reached, if `verbose_boot` is set
+    pub fn report_boot_milestones(&self) {
+        self.milestones.report();
+    }
+
+    /// Log the opcode coverage histogram, if coverage is enabled
+    pub fn report_opcode_coverage(&self) {
+        if let Some(coverage) = &self.opcode_coverage {
+            coverage.borrow().report();
+        }
+    }
+
+    /// Registers hit during the run that the emulator doesn't implement, across all
+    /// devices and both cores, most accessed first
+    pub fn unimplemented_mmio(&self) -> Vec<mmio::UnimplementedAccess> {
+        let mut all = mmio::UnimplementedMmio::default();
+        all.merge(&self.arm9_emu.get_data().unimplemented);
+        all.merge(&self.arm11_emu.get_data().unimplemented);
+        all.merge(&self.arm11_emu.get_data().gpu.unimplemented);
+        all.merge(&self.arm11_emu.get_data().dsp.unimplemented);
+        all.merge(
+            &self
+                .arm9_emu
+                .get_data()
+                .sdmmc
+                .lock()
+                .expect("SDMMC state lock poisoned")
+                .unimplemented,
+        );
+        all.merge(
+            &self
+                .arm9_emu
+                .get_data()
+                .hid
+                .lock()
+                .expect("HID state lock poisoned")
+                .unimplemented,
+        );
+        all.by_count()
+    }
+
+    /// Log the decoded GPU and SDMMC register state
+    pub fn dump_device_registers(&self) {
+        let gpu = self.arm11_emu.get_data().gpu.dump();
+        let sdmmc = self
+            .arm9_emu
+            .get_data()
+            .sdmmc
+            .lock()
+            .expect("SDMMC state lock poisoned")
+            .dump();
+        info!("GPU registers:");
+        for line in gpu.lines() {
+            info!("  {}", line);
+        }
+        info!("SDMMC registers:");
+        for line in sdmmc.lines() {
+            info!("  {}", line);
+        }
     }
 
-    /// Print final emulator state
-    pub fn print_final_state(&self) {
+    /// Print final emulator state, including the MMIO access log and the device
+    /// register dump when they are enabled
+    ///
+    /// Also finishes the MMIO write log, returning its error if the run diverged from
+    /// the log being verified against.
+    pub fn print_final_state(&self) -> Result<(), String> {
         info!("Total instructions executed: {}", self.total_executed());
         info!("Elapsed time: {:.2?}", self.elapsed());
+        info!("Emulated time: {:.2?}", self.emulated_time());
 
-        // Read ARM9 registers
-        let arm9_r0 = self.arm9_reg(RegisterARM::R0);
-        let arm9_r1 = self.arm9_reg(RegisterARM::R1);
-        let arm9_r2 = self.arm9_reg(RegisterARM::R2);
-        let arm9_r3 = self.arm9_reg(RegisterARM::R3);
-        let arm9_r4 = self.arm9_reg(RegisterARM::R4);
-        let arm9_r5 = self.arm9_reg(RegisterARM::R5);
-        let arm9_r6 = self.arm9_reg(RegisterARM::R6);
-        let arm9_sp = self.arm9_reg(RegisterARM::SP);
-        let arm9_lr = self.arm9_reg(RegisterARM::LR);
-
-        // Read ARM11 registers
-        let arm11_r0 = self.arm11_reg(RegisterARM::R0);
-        let arm11_r1 = self.arm11_reg(RegisterARM::R1);
-        let arm11_r2 = self.arm11_reg(RegisterARM::R2);
-        let arm11_r3 = self.arm11_reg(RegisterARM::R3);
-        let arm11_r4 = self.arm11_reg(RegisterARM::R4);
-        let arm11_r5 = self.arm11_reg(RegisterARM::R5);
-        let arm11_r6 = self.arm11_reg(RegisterARM::R6);
-        let arm11_sp = self.arm11_reg(RegisterARM::SP);
-        let arm11_lr = self.arm11_reg(RegisterARM::LR);
+        for core in [Core::Arm9, Core::Arm11] {
+            let reg = |reg| self.reg(core, reg);
+            info!(
+                "{:?}: pc={:#x} r0={:#x} r1={:#x} r2={:#x} r3={:#x} r4={:#x} r5={:#x} r6={:#x} sp={:#x} lr={:#x}",
+                core,
+                self.pc(core),
+                reg(RegisterARM::R0),
+                reg(RegisterARM::R1),
+                reg(RegisterARM::R2),
+                reg(RegisterARM::R3),
+                reg(RegisterARM::R4),
+                reg(RegisterARM::R5),
+                reg(RegisterARM::R6),
+                reg(RegisterARM::SP),
+                reg(RegisterARM::LR)
+            );
+        }
+        for core in [Core::Arm9, Core::Arm11] {
+            info!(
+                "{:?}: {}",
+                core,
+                describe_cpsr(self.reg(core, RegisterARM::CPSR) as u32)
+            );
+        }
 
-        info!(
-            "ARM9: pc={:#x} r0={:#x} r1={:#x} r2={:#x} r3={:#x} r4={:#x} r5={:#x} r6={:#x} sp={:#x} lr={:#x}",
-            self.arm9_pc(),
-            arm9_r0,
-            arm9_r1,
-            arm9_r2,
-            arm9_r3,
-            arm9_r4,
-            arm9_r5,
-            arm9_r6,
-            arm9_sp,
-            arm9_lr
+        if self.dump_devices {
+            self.dump_device_registers();
+        }
+
+        if let Some(log) = &self.arm9_emu.get_data().mmio_log {
+            log.dump("ARM9");
+        }
+        if let Some(log) = &self.arm11_emu.get_data().mmio_log {
+            log.dump("ARM11");
+        }
+
+        self.report_boot_milestones();
+        self.report_opcode_coverage();
+
+        let unimplemented = self.unimplemented_mmio();
+        if !unimplemented.is_empty() {
+            info!("Unimplemented MMIO registers: {}", unimplemented.len());
+            for access in &unimplemented {
+                info!(
+                    "  {} {:#X}: {} reads, {} writes",
+                    access.region, access.offset, access.reads, access.writes
+                );
+            }
+        }
+
+        self.finish_mmio_write_log()
+    }
+}
+
+/// Select the CPU model `emu` emulates for `core`, defaulting to the hardware's
+///
+/// This must happen before anything is mapped: Unicorn creates the CPU on first use.
+fn set_cpu_model(
+    emu: &mut Unicorn<'static, mmio::EmulatorState>,
+    core: Core,
+    model: Option<CpuModel>,
+) -> Result<(), EmulatorError> {
+    let model = model.unwrap_or(CpuModel::default_for(core));
+    info!("{:?} CPU model: {:?}", core, model);
+    emu.ctl_set_cpu_model(model.unicorn_model().into())
+        .map_err(|e| {
+            EmulatorError::Setup(format!(
+                "Failed to set {:?} CPU model to {:?}: {:?}",
+                core, model, e
+            ))
+        })
+}
+
+/// Copy `size` bytes of memory at `base`, as seen by `emu`, into a save state region
+fn snapshot_region(
+    emu: &Unicorn<'static, mmio::EmulatorState>,
+    name: &str,
+    base: u32,
+    size: usize,
+) -> Result<MemorySnapshot, String> {
+    let mut data = vec![0u8; size];
+    emu.mem_read(base as u64, &mut data)
+        .map_err(|e| format!("Failed to read {}: {:?}", name, e))?;
+    Ok(MemorySnapshot {
+        name: name.to_string(),
+        base,
+        data,
+    })
+}
+
+/// Decode CPSR into instruction set, processor mode, data endianness, and
+/// condition flags
+///
+/// Flags are printed upper-case when set, e.g. `nZCv`.
+fn describe_cpsr(cpsr: u32) -> String {
+    let instruction_set = if cpsr & cpu_types::CPSR_THUMB != 0 {
+        "thumb"
+    } else {
+        "arm"
+    };
+    let processor_mode = match ProcessorMode::from_cpsr(cpsr) {
+        Some(mode) => format!("{:?}", mode),
+        None => format!("invalid({:#x})", cpsr & cpu_types::CPSR_MODE_MASK),
+    };
+    let endianness = if cpsr & cpu_types::CPSR_BIG_ENDIAN != 0 {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    };
+    let flags: String = [(31, 'n'), (30, 'z'), (29, 'c'), (28, 'v')]
+        .iter()
+        .map(|&(bit, name)| {
+            if cpsr & (1 << bit) != 0 {
+                name.to_ascii_uppercase()
+            } else {
+                name
+            }
+        })
+        .collect();
+    format!(
+        "cpsr={:#x} mode={} cpu_mode={} endianness={:?} flags={}",
+        cpsr, instruction_set, processor_mode, endianness, flags
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `b .`
+    const SPIN: [u8; 4] = [0xFE, 0xFF, 0xFF, 0xEA];
+
+    /// Emulator running `code` on `core` from the start of ARM9 private WRAM (or AXI
+    /// WRAM for ARM11), with the other core parked
+    fn emulator_with(code: &[u8], core: Core, config: EmulatorConfig) -> EmulatorCore {
+        let base = match core {
+            Core::Arm9 => memory::ARM9_PRIVATE_WRAM_BASE,
+            Core::Arm11 => memory::AXI_WRAM_BASE,
+        };
+        let firm = firm::build_raw_firm(code, core, base, base);
+        EmulatorCore::new(&firm, config).unwrap()
+    }
+
+    fn emulator(code: &[u8], core: Core) -> EmulatorCore {
+        emulator_with(code, core, EmulatorConfig::default())
+    }
+
+    #[test]
+    fn arm9_only_run_leaves_arm11_alone() {
+        // mov r0, #1; b .
+        let code = [0x01, 0x00, 0xA0, 0xE3, 0xFE, 0xFF, 0xFF, 0xEA];
+        let stop_pc = memory::ARM9_PRIVATE_WRAM_BASE as u64 + 4;
+        let config = EmulatorConfig::builder()
+            .cores(CoreSelection::Arm9Only)
+            .arm9_stop_pc(stop_pc)
+            .arm11_stop_pc(0)
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&code, Core::Arm9, config);
+        assert!(emulator.stopped(Core::Arm11));
+
+        assert_eq!(emulator.run(), StopReason::StopCondition);
+        assert_eq!(emulator.stop_detail(), Some(StopDetail::StopPcs));
+        assert_eq!(emulator.pc(Core::Arm9), stop_pc);
+        assert_eq!(emulator.reg(Core::Arm9, RegisterARM::R0), 1);
+        assert_eq!(emulator.executed(Core::Arm11), 0);
+    }
+
+    #[test]
+    fn arm9_stops_at_its_own_limit_while_arm11_runs_on() {
+        let config = EmulatorConfig::builder()
+            .arm9_max_instructions(1_000)
+            .max_instructions(3 * scheduler::ARM11_INSTRUCTIONS_PER_QUANTUM)
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&SPIN, Core::Arm9, config);
+
+        assert_eq!(emulator.run(), StopReason::StopCondition);
+        assert_eq!(emulator.stop_detail(), Some(StopDetail::MaxInstructions));
+        assert_eq!(emulator.executed(Core::Arm9), 1_000);
+        assert!(emulator.stopped(Core::Arm9));
+        assert!(!emulator.stopped(Core::Arm11));
+        assert!(emulator.executed(Core::Arm11) > 2 * scheduler::ARM11_INSTRUCTIONS_PER_QUANTUM);
+    }
+
+    #[test]
+    fn headless_run_stops_after_max_frames() {
+        let config = EmulatorConfig::builder()
+            .max_frames(3)
+            .timeout(Duration::from_secs(30))
+            .build();
+        let mut emulator = emulator_with(&SPIN, Core::Arm9, config);
+
+        assert_eq!(emulator.run(), StopReason::StopCondition);
+        assert_eq!(emulator.stop_detail(), Some(StopDetail::MaxFrames));
+        assert_eq!(emulator.frames_completed(), 3);
+        assert!(emulator.frame_ended());
+    }
+
+    #[test]
+    fn memory_regions_list_shared_ram_in_address_order() {
+        let emulator = emulator(&SPIN, Core::Arm9);
+        for core in [Core::Arm9, Core::Arm11] {
+            let regions = emulator.memory_regions(core);
+            assert!(regions.windows(2).all(|pair| pair[0].end < pair[1].begin));
+            for (base, size) in [
+                (memory::FCRAM_BASE, FCRAM_SIZE),
+                (memory::VRAM_BASE, VRAM_SIZE),
+            ] {
+                let (first, last) = (base as u64, base as u64 + size as u64 - 1);
+                let region = regions
+                    .iter()
+                    .find(|region| region.begin == first)
+                    .unwrap_or_else(|| panic!("{:?}: nothing mapped at {:#X}", core, base));
+                let read_write = Prot::READ | Prot::WRITE;
+                assert!(region.perms & read_write == read_write);
+                assert!(
+                    regions
+                        .iter()
+                        .any(|region| region.begin <= last && last <= region.end)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ram_init_fills_are_deterministic() {
+        let fill = |init: RamInit| {
+            let (mut a, mut b) = ([0u8; 12], [0u8; 12]);
+            init.apply(&mut [&mut a, &mut b]);
+            (a, b)
+        };
+        assert_eq!(fill(RamInit::Zero), ([0; 12], [0; 12]));
+        assert_eq!(fill(RamInit::Pattern(0xA5)), ([0xA5; 12], [0xA5; 12]));
+
+        let (a, b) = fill(RamInit::Random(1));
+        assert_eq!(fill(RamInit::Random(1)), (a, b));
+        assert_ne!(fill(RamInit::Random(2)).0, a);
+        // The sequence carries on into the next buffer rather than restarting
+        assert_ne!(a, b);
+        assert!(a.iter().any(|&byte| byte != 0));
+    }
+
+    #[test]
+    fn ram_pattern_is_visible_before_any_code_runs() {
+        let config = EmulatorConfig::builder()
+            .ram_init(RamInit::Pattern(0x5A))
+            .build();
+        let emulator = emulator_with(&SPIN, Core::Arm9, config);
+        for addr in [memory::FCRAM_BASE, memory::VRAM_BASE + 0x100] {
+            assert_eq!(
+                emulator.mem_read(Core::Arm9, addr as u64, 4),
+                Ok(vec![0x5A; 4])
+            );
+        }
+        // Loaded sections are copied over the fill
+        assert_eq!(
+            emulator.mem_read(Core::Arm9, memory::ARM9_PRIVATE_WRAM_BASE as u64, 4),
+            Ok(SPIN.to_vec())
         );
+    }
 
-        info!(
-            "ARM11: pc={:#x} r0={:#x} r1={:#x} r2={:#x} r3={:#x} r4={:#x} r5={:#x} r6={:#x} sp={:#x} lr={:#x}",
-            self.arm11_pc(),
-            arm11_r0,
-            arm11_r1,
-            arm11_r2,
-            arm11_r3,
-            arm11_r4,
-            arm11_r5,
-            arm11_r6,
-            arm11_sp,
-            arm11_lr
+    /// Little-endian bytes of the ARM instructions `words`
+    fn assemble(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn stop_on_mem_reports_the_matching_word() {
+        let addr = memory::ARM9_PRIVATE_WRAM_BASE as u64 + 0x100;
+        let code = assemble(&[
+            0xE3A00408, // mov r0, #0x08000000
+            0xE2800C01, // add r0, r0, #0x100
+            0xE3A01042, // mov r1, #0x42
+            0xE5801000, // str r1, [r0]
+            0xEAFFFFFE, // b .
+        ]);
+        let config = EmulatorConfig::builder()
+            .stop_on_mem(Core::Arm9, addr, 0x42)
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&code, Core::Arm9, config);
+
+        assert_eq!(
+            emulator.run(),
+            StopReason::MemoryValue {
+                core: Core::Arm9,
+                addr,
+                value: 0x42
+            }
+        );
+    }
+
+    #[test]
+    fn stop_on_mem_reads_in_the_cores_endianness() {
+        let addr = memory::AXI_WRAM_BASE as u64 + 0x100;
+        let code = assemble(&[
+            0xF1010200, // setend be
+            0xE3A01042, // mov r1, #0x42
+            0xE5801000, // str r1, [r0]
+            0xEAFFFFFE, // b .
+        ]);
+        let config = EmulatorConfig::builder()
+            .initial_register(Core::Arm11, ArmRegister::R0, addr as u32)
+            .stop_on_mem(Core::Arm11, addr, 0x42)
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&code, Core::Arm11, config);
+
+        assert_eq!(
+            emulator.run(),
+            StopReason::MemoryValue {
+                core: Core::Arm11,
+                addr,
+                value: 0x42
+            }
+        );
+        assert_eq!(
+            emulator.read_phys(addr as u32, 4),
+            Some(&[0, 0, 0, 0x42][..])
+        );
+    }
+
+    #[test]
+    fn callback_stops_the_run_when_it_returns_false() {
+        let config = EmulatorConfig::builder()
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&SPIN, Core::Arm9, config);
+        let mut quanta = 0;
+        let reason = emulator.run_with_callback(|emulator| {
+            quanta += 1;
+            assert!(emulator.total_executed() > 0);
+            quanta < 5
+        });
+
+        assert_eq!(reason, StopReason::Callback);
+        assert_eq!(quanta, 5);
+    }
+
+    #[test]
+    fn axi_wram_is_readable_and_renders_as_a_framebuffer() {
+        use crate::display::{self, Screen};
+        use oxidiz3ds_hw::mmio::gpu::{pdc_control, registers as gpu_regs};
+
+        let mut emulator = emulator(&SPIN, Core::Arm11);
+        let base = memory::AXI_WRAM_BASE;
+        assert_eq!(emulator.read_phys(base, 4), Some(&SPIN[..]));
+        assert_eq!(emulator.read_phys(base - 4, 4), None);
+        // Reads stop at the end of the first VRAM bank
+        let bank_end = memory::VRAM_BASE + (VRAM_SIZE / 2) as u32;
+        assert_eq!(
+            emulator.read_phys(bank_end - 2, 4).map(<[u8]>::len),
+            Some(2)
+        );
+
+        let fb_addr = base + 0x1000;
+        let (width, height) = (Screen::Top.width(), Screen::Top.height());
+        let pixels = [0x10, 0x20, 0x30].repeat((width * height) as usize);
+        emulator
+            .arm11_emu
+            .mem_write(fb_addr as u64, &pixels)
+            .unwrap();
+        let gpu = &mut emulator.arm11_emu.get_data_mut().gpu;
+        gpu.write(gpu_regs::FRAMEBUFFER_TOP_LEFT, 4, fb_addr);
+        gpu.write(gpu_regs::PDC0_CONTROL, 4, pdc_control::ENABLE);
+
+        assert_eq!(
+            display::render_screen_to_rgb(&emulator, Screen::Top),
+            pixels
+        );
+    }
+
+    #[test]
+    fn arm11_model_runs_armv6_instructions() {
+        let code = assemble(&[
+            0xE6BF0F31, // rev r0, r1
+            0xEAFFFFFE, // b .
+        ]);
+        let stop_pc = memory::AXI_WRAM_BASE as u64 + 4;
+        let config = EmulatorConfig::builder()
+            .initial_register(Core::Arm11, ArmRegister::R1, 0x1122_3344)
+            .arm11_stop_pc(stop_pc)
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&code, Core::Arm11, config);
+
+        assert_eq!(emulator.run(), StopReason::StopCondition);
+        assert_eq!(emulator.pc(Core::Arm11), stop_pc);
+        assert_eq!(emulator.reg(Core::Arm11, RegisterARM::R0), 0x4433_2211);
+    }
+
+    #[test]
+    fn breakpoints_can_be_added_and_removed_at_runtime() {
+        let code = assemble(&[
+            0xE1A00000, // mov r0, r0
+            0xE1A00000, // mov r0, r0
+            0xEAFFFFFE, // b .
+        ]);
+        let base = memory::ARM9_PRIVATE_WRAM_BASE as u64;
+        let config = EmulatorConfig::builder()
+            .arm11_stop_pc(0x1234)
+            .timeout(Duration::from_secs(10))
+            .build();
+        let mut emulator = emulator_with(&code, Core::Arm9, config);
+
+        // Without ARM11's breakpoint, ARM9 reaching its own ends the run
+        assert_eq!(emulator.breakpoints(Core::Arm11), vec![0x1234]);
+        emulator.clear_breakpoints(Core::Arm11);
+        assert!(emulator.breakpoints(Core::Arm11).is_empty());
+
+        assert_eq!(emulator.add_breakpoint(Core::Arm9, base + 8), None);
+        assert_eq!(
+            emulator.add_breakpoint(Core::Arm9, base + 4),
+            Some(base + 8)
+        );
+        assert_eq!(emulator.breakpoints(Core::Arm9), vec![base + 4]);
+        assert_eq!(emulator.run(), StopReason::StopCondition);
+        assert_eq!(emulator.pc(Core::Arm9), base + 4);
+        assert!(emulator.stopped(Core::Arm9));
+
+        assert!(emulator.remove_breakpoint(Core::Arm9, base + 4));
+        assert!(!emulator.remove_breakpoint(Core::Arm9, base + 4));
+        assert!(emulator.breakpoints(Core::Arm9).is_empty());
+        assert!(!emulator.stopped(Core::Arm9));
+
+        assert_eq!(emulator.run_for(1), StopReason::Yielded);
+        assert_eq!(emulator.pc(Core::Arm9), base + 8);
+        assert!(!emulator.stopped(Core::Arm9));
+    }
+
+    #[test]
+    fn frequencies_too_low_for_a_quantum_are_rejected() {
+        let firm = firm::build_raw_firm(&SPIN, Core::Arm9, 0x0800_0000, 0x0800_0000);
+        let config = EmulatorConfig::builder().arm11_freq_hz(599).build();
+        assert!(matches!(
+            EmulatorCore::new(&firm, config),
+            Err(EmulatorError::Setup(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_firm_is_rejected_before_loading() {
+        let mut firm_data = firm::build_raw_firm(&SPIN, Core::Arm9, 0x0800_0000, 0x0800_0000);
+        let mut header = FirmHeader::parse(&firm_data).unwrap();
+        header.sections[0].offset = 0x100;
+        assert!(matches!(
+            EmulatorCore::from_firm(header, &firm_data, EmulatorConfig::default()),
+            Err(EmulatorError::Firm(FirmError::SectionInHeader {
+                section: 0
+            }))
+        ));
+
+        firm_data.truncate(firm_data.len() - 1);
+        assert!(matches!(
+            EmulatorCore::new(&firm_data, EmulatorConfig::default()),
+            Err(EmulatorError::Firm(FirmError::SectionOutOfBounds {
+                section: 1
+            }))
+        ));
+    }
+
+    #[test]
+    fn cpsr_description_decodes_mode_and_state_bits() {
+        assert_eq!(
+            describe_cpsr(0x6000_00D3),
+            "cpsr=0x600000d3 mode=arm cpu_mode=Supervisor endianness=Little flags=nZCv"
+        );
+        assert_eq!(
+            describe_cpsr(0x8000_0230),
+            "cpsr=0x80000230 mode=thumb cpu_mode=User endianness=Big flags=Nzcv"
+        );
+        assert_eq!(
+            describe_cpsr(0x15),
+            "cpsr=0x15 mode=arm cpu_mode=invalid(0x15) endianness=Little flags=nzcv"
+        );
+    }
+
+    #[test]
+    fn request_stop_without_a_handle_stops_between_quanta() {
+        let mut emulator = emulator(&SPIN, Core::Arm9);
+        let mut quanta = 0;
+        let reason = emulator.run_with_callback(|emulator| {
+            quanta += 1;
+            emulator.request_stop();
+            true
+        });
+        assert_eq!(reason, StopReason::Interrupted);
+        assert_eq!(quanta, 1);
+
+        let executed = emulator.total_executed();
+        assert_eq!(emulator.run(), StopReason::Interrupted);
+        assert_eq!(emulator.total_executed(), executed);
+    }
+
+    #[test]
+    fn raw_screen_dump_rejects_bogus_framebuffer_registers() {
+        use crate::screenshot::{self, DumpFormat};
+        use oxidiz3ds_hw::mmio::gpu::registers as gpu_regs;
+
+        let mut emulator = emulator(&SPIN, Core::Arm11);
+        let dir = std::env::temp_dir().join(format!("threemu-raw-dump-{}", std::process::id()));
+        let mut dump = |addr: u32, stride: u32| {
+            let gpu = &mut emulator.arm11_emu.get_data_mut().gpu;
+            gpu.write(gpu_regs::FRAMEBUFFER_TOP_LEFT, 4, addr);
+            gpu.write(gpu_regs::FRAMEBUFFER_TOP_STRIDE, 4, stride);
+            screenshot::dump_screens(&emulator, &dir, DumpFormat::Raw)
+        };
+
+        let vram_end = memory::VRAM_BASE + VRAM_SIZE as u32;
+        assert!(dump(vram_end - 0x100, 240 * 3).is_err());
+        assert!(dump(memory::VRAM_BASE, u32::MAX).is_err());
+        assert!(dump(0x3000_0000, 240 * 3).is_err());
+        assert_eq!(dump(memory::VRAM_BASE, 240 * 3), Ok(()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn banked_registers_of_other_modes_are_readable() {
+        let mut emulator = emulator(&SPIN, Core::Arm9);
+        let svc = ProcessorMode::Supervisor.bits() as u64;
+        let irq = ProcessorMode::Irq.bits() as u64;
+        let emu = &mut emulator.arm9_emu;
+        emu.reg_write(RegisterARM::CPSR, irq).unwrap();
+        emu.reg_write(RegisterARM::SP, 0x0800_1000).unwrap();
+        emu.reg_write(RegisterARM::LR, 0x0800_0004).unwrap();
+        emu.reg_write(RegisterARM::SPSR, svc).unwrap();
+        emu.reg_write(RegisterARM::CPSR, svc).unwrap();
+        emu.reg_write(RegisterARM::SP, 0x0800_2000).unwrap();
+
+        let cpsr = emulator.read_register(Core::Arm9, ArmRegister::CPSR) as u32;
+        assert_eq!(
+            ProcessorMode::from_cpsr(cpsr),
+            Some(ProcessorMode::Supervisor)
+        );
+        assert_eq!(
+            emulator.banked_registers(Core::Arm9, ProcessorMode::Irq),
+            Ok(BankedRegisters {
+                sp: 0x0800_1000,
+                lr: 0x0800_0004,
+                spsr: Some(svc as u32),
+            })
+        );
+        // The current mode and its registers are left as they were
+        assert_eq!(
+            emulator.read_register(Core::Arm9, ArmRegister::CPSR),
+            cpsr as u64
+        );
+        assert_eq!(
+            emulator.read_register(Core::Arm9, ArmRegister::R13),
+            0x0800_2000
         );
     }
 }
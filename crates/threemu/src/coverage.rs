@@ -0,0 +1,130 @@
+//! Opcode coverage: executed instructions tallied by mnemonic.
+//!
+//! When enabled, a code hook on each core disassembles every executed instruction
+//! with capstone and counts it under its mnemonic, as capstone prints it (condition
+//! codes and the flag-setting suffix are included, e.g. `bne`, `movs`). The
+//! histogram printed at exit shows what a firmware actually exercises, such as
+//! coprocessor or SIMD instructions the emulator may not handle well.
+//!
+//! Disassembly is cached per instruction encoding, so each distinct encoding is only
+//! disassembled once; the remaining per-instruction cost is reading CPSR and the
+//! instruction bytes.
+
+use crate::cpu_types::Core;
+use capstone::prelude::*;
+use std::collections::HashMap;
+use tracing::info;
+
+/// Mnemonic counted for encodings capstone can't decode
+const UNDECODABLE: &str = "<undecodable>";
+
+/// Executed-instruction counts by mnemonic, for both cores
+pub struct OpcodeCoverage {
+    arm: Capstone,
+    thumb: Capstone,
+    /// Number of mnemonics shown in the report
+    top: usize,
+    /// Mnemonic index of each encoding seen so far, keyed by (thumb, length, bytes)
+    decoded: HashMap<(bool, usize, u32), usize>,
+    mnemonics: Vec<String>,
+    /// Executions per mnemonic, indexed like `mnemonics`: [ARM9, ARM11]
+    counts: Vec<[u64; 2]>,
+}
+
+impl OpcodeCoverage {
+    /// Create an empty tally whose report lists the `top` most executed mnemonics
+    pub fn new(top: usize) -> Result<Self, String> {
+        let build = |mode| {
+            Capstone::new()
+                .arm()
+                .mode(mode)
+                .build()
+                .map_err(|e| format!("Failed to create disassembler: {}", e))
+        };
+        Ok(Self {
+            arm: build(arch::arm::ArchMode::Arm)?,
+            thumb: build(arch::arm::ArchMode::Thumb)?,
+            top,
+            decoded: HashMap::new(),
+            mnemonics: Vec::new(),
+            counts: Vec::new(),
+        })
+    }
+
+    /// Count one execution of the instruction `bytes` (2 or 4 bytes) at `addr`
+    pub fn record(&mut self, core: Core, thumb: bool, addr: u64, bytes: &[u8]) {
+        let mut word = [0u8; 4];
+        word[..bytes.len()].copy_from_slice(bytes);
+        let key = (thumb, bytes.len(), u32::from_le_bytes(word));
+
+        let index = match self.decoded.get(&key) {
+            Some(&index) => index,
+            None => {
+                let cs = if thumb { &self.thumb } else { &self.arm };
+                let mnemonic = cs
+                    .disasm_count(bytes, addr, 1)
+                    .ok()
+                    .and_then(|insns| {
+                        insns
+                            .iter()
+                            .next()
+                            .and_then(|insn| insn.mnemonic().map(str::to_string))
+                    })
+                    .unwrap_or_else(|| UNDECODABLE.to_string());
+                let index = match self.mnemonics.iter().position(|m| *m == mnemonic) {
+                    Some(index) => index,
+                    None => {
+                        self.mnemonics.push(mnemonic);
+                        self.counts.push([0; 2]);
+                        self.mnemonics.len() - 1
+                    }
+                };
+                self.decoded.insert(key, index);
+                index
+            }
+        };
+
+        let core_index = match core {
+            Core::Arm9 => 0,
+            Core::Arm11 => 1,
+        };
+        self.counts[index][core_index] += 1;
+    }
+
+    /// Mnemonics with their [ARM9, ARM11] execution counts, most executed first
+    pub fn histogram(&self) -> Vec<(&str, [u64; 2])> {
+        let mut histogram: Vec<_> = self
+            .mnemonics
+            .iter()
+            .map(String::as_str)
+            .zip(self.counts.iter().copied())
+            .collect();
+        histogram.sort_by(|a, b| (b.1[0] + b.1[1]).cmp(&(a.1[0] + a.1[1])).then(a.0.cmp(b.0)));
+        histogram
+    }
+
+    /// Log the most executed mnemonics
+    pub fn report(&self) {
+        let histogram = self.histogram();
+        let total: u64 = histogram.iter().map(|(_, [a, b])| a + b).sum();
+        info!(
+            "Opcode coverage: {} instructions, {} distinct mnemonics (top {}):",
+            total,
+            histogram.len(),
+            self.top.min(histogram.len())
+        );
+        info!(
+            "  {:<12} {:>12} {:>12} {:>8}",
+            "mnemonic", "arm9", "arm11", "share"
+        );
+        for (mnemonic, [arm9, arm11]) in histogram.iter().take(self.top) {
+            info!(
+                "  {:<12} {:>12} {:>12} {:>7.2}%",
+                mnemonic,
+                arm9,
+                arm11,
+                (arm9 + arm11) as f64 * 100.0 / total as f64
+            );
+        }
+    }
+}
@@ -15,6 +15,7 @@
 //! - [ARM946E-S Technical Reference Manual](https://developer.arm.com/documentation/ddi0201/latest/)
 //! - [GBATEK ARM CP15 Documentation](https://problemkaputt.de/gbatek.htm#armcp15systemcontrolcoprocessor)
 
+use crate::cpu_types::in_thumb_state;
 use tracing::{debug, warn};
 use unicorn_engine::{RegisterARM, Unicorn};
 
@@ -36,7 +37,9 @@ const ARM_INSN_SIZE: u64 = 4;
 /// Handles CP15 coprocessor instructions for ARM9
 ///
 /// This function is called from a code hook and processes CP15 instructions.
-/// It returns true if a CP15 instruction was handled, false otherwise.
+/// It returns true if a CP15 instruction was handled, false otherwise. MCR/MRC only
+/// exist as ARM encodings on the ARM9, so nothing is decoded in Thumb state, where
+/// `insn` would be two unrelated halfwords.
 ///
 /// # Supported Instructions
 ///
@@ -46,6 +49,10 @@ const ARM_INSN_SIZE: u64 = 4;
 ///
 /// All other CP15 instructions are logged as warnings and skipped.
 pub fn handle_cp15_instruction<D>(uc: &mut Unicorn<D>, addr: u64, insn: u32) -> bool {
+    if in_thumb_state(uc) {
+        return false;
+    }
+
     // Check if it's a CP15 instruction
     let is_cp15 = (insn & CP15_MASK) == CP15_VALUE && (insn & CP15_REG_MASK) == CP15_REG_VALUE;
 
@@ -3,6 +3,138 @@
 //! This module contains types related to CPU emulation that are used
 //! throughout the emulator.
 
+use std::str::FromStr;
+use unicorn_engine::{ArmCpuModel, RegisterARM, Unicorn, unicorn_const::Mode};
+
+/// CPSR T bit: set while the core executes Thumb instructions
+pub const CPSR_THUMB: u32 = 1 << 5;
+
+/// Whether `uc` is currently in Thumb state
+pub fn in_thumb_state<D>(uc: &Unicorn<D>) -> bool {
+    uc.reg_read(RegisterARM::CPSR)
+        .is_ok_and(|cpsr| cpsr as u32 & CPSR_THUMB != 0)
+}
+
+/// CPSR E bit: set while data accesses are big-endian (toggled by ARMv6 `SETEND`)
+pub const CPSR_BIG_ENDIAN: u32 = 1 << 9;
+
+/// Whether `uc` currently makes big-endian data accesses
+pub fn big_endian_data<D>(uc: &Unicorn<D>) -> bool {
+    uc.reg_read(RegisterARM::CPSR)
+        .is_ok_and(|cpsr| cpsr as u32 & CPSR_BIG_ENDIAN != 0)
+}
+
+/// CPSR M field: the current processor mode
+pub const CPSR_MODE_MASK: u32 = 0x1F;
+
+/// ARM processor mode, which selects the banked SP, LR, and SPSR in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessorMode {
+    User,
+    Fiq,
+    Irq,
+    Supervisor,
+    Abort,
+    Undefined,
+    System,
+}
+
+impl ProcessorMode {
+    /// Value of the CPSR M field selecting this mode
+    pub fn bits(self) -> u32 {
+        match self {
+            ProcessorMode::User => 0x10,
+            ProcessorMode::Fiq => 0x11,
+            ProcessorMode::Irq => 0x12,
+            ProcessorMode::Supervisor => 0x13,
+            ProcessorMode::Abort => 0x17,
+            ProcessorMode::Undefined => 0x1B,
+            ProcessorMode::System => 0x1F,
+        }
+    }
+
+    /// Mode selected by `cpsr`, or `None` if its M field is invalid
+    pub fn from_cpsr(cpsr: u32) -> Option<Self> {
+        [
+            ProcessorMode::User,
+            ProcessorMode::Fiq,
+            ProcessorMode::Irq,
+            ProcessorMode::Supervisor,
+            ProcessorMode::Abort,
+            ProcessorMode::Undefined,
+            ProcessorMode::System,
+        ]
+        .into_iter()
+        .find(|mode| mode.bits() == cpsr & CPSR_MODE_MASK)
+    }
+
+    /// Whether the mode has an SPSR (User and System mode don't)
+    pub fn has_spsr(self) -> bool {
+        !matches!(self, ProcessorMode::User | ProcessorMode::System)
+    }
+}
+
+/// Byte order the cores are created in
+///
+/// The 3DS runs little-endian. Big-endian is for experimentation only: memory
+/// views, MMIO handlers, and FIRM loading all assume little-endian values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endianness {
+    /// Unicorn mode selecting this byte order
+    pub fn mode(self) -> Mode {
+        match self {
+            Endianness::Little => Mode::LITTLE_ENDIAN,
+            Endianness::Big => Mode::BIG_ENDIAN,
+        }
+    }
+}
+
+/// CPU model Unicorn emulates for a core, which decides the instruction set and
+/// CP15 features available
+///
+/// Without one, Unicorn picks a Cortex-A15 (ARMv7), which accepts instructions
+/// neither 3DS core has. The defaults match the hardware: an ARM946E-S (ARMv5TE)
+/// for ARM9 and an ARM11 MPCore (ARMv6K) for ARM11.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CpuModel {
+    Arm926,
+    Arm946,
+    Arm1136,
+    Arm1176,
+    Arm11Mpcore,
+    CortexA9,
+    CortexA15,
+}
+
+impl CpuModel {
+    /// Model matching the hardware of `core`
+    pub fn default_for(core: Core) -> Self {
+        match core {
+            Core::Arm9 => CpuModel::Arm946,
+            Core::Arm11 => CpuModel::Arm11Mpcore,
+        }
+    }
+
+    /// Unicorn's identifier for this model
+    pub fn unicorn_model(self) -> ArmCpuModel {
+        match self {
+            CpuModel::Arm926 => ArmCpuModel::Model_926,
+            CpuModel::Arm946 => ArmCpuModel::Model_946,
+            CpuModel::Arm1136 => ArmCpuModel::Model_1136,
+            CpuModel::Arm1176 => ArmCpuModel::Model_1176,
+            CpuModel::Arm11Mpcore => ArmCpuModel::Model_11MPCORE,
+            CpuModel::CortexA9 => ArmCpuModel::CORTEX_A9,
+            CpuModel::CortexA15 => ArmCpuModel::CORTEX_A15,
+        }
+    }
+}
+
 /// ARM general-purpose and special registers
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArmRegister {
@@ -24,3 +156,187 @@ pub enum ArmRegister {
     R15, // Program Counter (PC)
     CPSR,
 }
+
+impl ArmRegister {
+    /// All registers, in encoding order
+    pub const ALL: [ArmRegister; 17] = [
+        ArmRegister::R0,
+        ArmRegister::R1,
+        ArmRegister::R2,
+        ArmRegister::R3,
+        ArmRegister::R4,
+        ArmRegister::R5,
+        ArmRegister::R6,
+        ArmRegister::R7,
+        ArmRegister::R8,
+        ArmRegister::R9,
+        ArmRegister::R10,
+        ArmRegister::R11,
+        ArmRegister::R12,
+        ArmRegister::R13,
+        ArmRegister::R14,
+        ArmRegister::R15,
+        ArmRegister::CPSR,
+    ];
+}
+
+impl From<ArmRegister> for RegisterARM {
+    fn from(reg: ArmRegister) -> Self {
+        match reg {
+            ArmRegister::R0 => RegisterARM::R0,
+            ArmRegister::R1 => RegisterARM::R1,
+            ArmRegister::R2 => RegisterARM::R2,
+            ArmRegister::R3 => RegisterARM::R3,
+            ArmRegister::R4 => RegisterARM::R4,
+            ArmRegister::R5 => RegisterARM::R5,
+            ArmRegister::R6 => RegisterARM::R6,
+            ArmRegister::R7 => RegisterARM::R7,
+            ArmRegister::R8 => RegisterARM::R8,
+            ArmRegister::R9 => RegisterARM::R9,
+            ArmRegister::R10 => RegisterARM::R10,
+            ArmRegister::R11 => RegisterARM::R11,
+            ArmRegister::R12 => RegisterARM::R12,
+            ArmRegister::R13 => RegisterARM::SP,
+            ArmRegister::R14 => RegisterARM::LR,
+            ArmRegister::R15 => RegisterARM::PC,
+            ArmRegister::CPSR => RegisterARM::CPSR,
+        }
+    }
+}
+
+impl FromStr for ArmRegister {
+    type Err = String;
+
+    /// Parse a register name (`r0`-`r15`, `sp`, `lr`, `pc`, `cpsr`), case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let reg = match s.to_ascii_lowercase().as_str() {
+            "r0" => ArmRegister::R0,
+            "r1" => ArmRegister::R1,
+            "r2" => ArmRegister::R2,
+            "r3" => ArmRegister::R3,
+            "r4" => ArmRegister::R4,
+            "r5" => ArmRegister::R5,
+            "r6" => ArmRegister::R6,
+            "r7" => ArmRegister::R7,
+            "r8" => ArmRegister::R8,
+            "r9" => ArmRegister::R9,
+            "r10" => ArmRegister::R10,
+            "r11" => ArmRegister::R11,
+            "r12" => ArmRegister::R12,
+            "r13" | "sp" => ArmRegister::R13,
+            "r14" | "lr" => ArmRegister::R14,
+            "r15" | "pc" => ArmRegister::R15,
+            "cpsr" => ArmRegister::CPSR,
+            _ => return Err(format!("unknown register {:?}", s)),
+        };
+        Ok(reg)
+    }
+}
+
+/// One of the two emulated CPU cores
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Core {
+    Arm9,
+    Arm11,
+}
+
+impl Core {
+    /// Span that per-core work runs in, so logs can be filtered to one core, e.g.
+    /// `RUST_LOG=threemu[arm11]=trace`
+    ///
+    /// Everything a core does while it executes (MMIO handlers, CP15 and bootrom
+    /// hooks) is logged inside its span, as is its memory setup.
+    pub fn span(self) -> tracing::Span {
+        match self {
+            Core::Arm9 => tracing::error_span!("arm9"),
+            Core::Arm11 => tracing::error_span!("arm11"),
+        }
+    }
+}
+
+impl FromStr for Core {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "arm9" => Ok(Core::Arm9),
+            "arm11" => Ok(Core::Arm11),
+            _ => Err(format!("unknown core {:?} (expected arm9 or arm11)", s)),
+        }
+    }
+}
+
+/// Which cores the emulator runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoreSelection {
+    /// Run both ARM9 and ARM11
+    #[default]
+    Both,
+    /// Run only ARM9; ARM11 is held stopped
+    Arm9Only,
+    /// Run only ARM11; ARM9 is held stopped
+    Arm11Only,
+}
+
+impl CoreSelection {
+    /// Whether `core` runs under this selection
+    pub fn runs(self, core: Core) -> bool {
+        !matches!(
+            (self, core),
+            (CoreSelection::Arm9Only, Core::Arm11) | (CoreSelection::Arm11Only, Core::Arm9)
+        )
+    }
+}
+
+impl From<Option<Core>> for CoreSelection {
+    /// A single core runs only that core, `None` runs both
+    fn from(core: Option<Core>) -> Self {
+        match core {
+            None => CoreSelection::Both,
+            Some(Core::Arm9) => CoreSelection::Arm9Only,
+            Some(Core::Arm11) => CoreSelection::Arm11Only,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processor_mode_round_trips_through_cpsr() {
+        for cpsr in 0..=CPSR_MODE_MASK {
+            if let Some(mode) = ProcessorMode::from_cpsr(0x6000_0000 | cpsr) {
+                assert_eq!(mode.bits(), cpsr);
+            }
+        }
+        assert_eq!(
+            ProcessorMode::from_cpsr(0x6000_00D3),
+            Some(ProcessorMode::Supervisor)
+        );
+        assert_eq!(ProcessorMode::from_cpsr(0x15), None);
+        assert!(!ProcessorMode::System.has_spsr());
+        assert!(ProcessorMode::Irq.has_spsr());
+    }
+
+    #[test]
+    fn cpsr_maps_to_unicorn_cpsr() {
+        assert_eq!(RegisterARM::from(ArmRegister::CPSR), RegisterARM::CPSR);
+        assert_eq!(RegisterARM::from(ArmRegister::R13), RegisterARM::SP);
+        assert_eq!("CPSR".parse::<ArmRegister>(), Ok(ArmRegister::CPSR));
+    }
+
+    #[test]
+    fn default_cpu_models_match_the_hardware() {
+        assert_eq!(CpuModel::default_for(Core::Arm9), CpuModel::Arm946);
+        assert_eq!(CpuModel::default_for(Core::Arm11), CpuModel::Arm11Mpcore);
+        assert!(matches!(
+            CpuModel::Arm946.unicorn_model(),
+            ArmCpuModel::Model_946
+        ));
+        assert!(matches!(
+            CpuModel::Arm11Mpcore.unicorn_model(),
+            ArmCpuModel::Model_11MPCORE
+        ));
+    }
+}
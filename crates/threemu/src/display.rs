@@ -4,11 +4,16 @@
 //! window management and softbuffer for software rendering.
 
 use crate::core::EmulatorCore;
+use crate::cpu_types::Core;
+use crate::mmio::PixelFormat;
 use crate::scheduler::QuantumResult;
+use oxidiz3ds_hw::specs::display::BYTES_PER_PIXEL_RGB8;
 use softbuffer::{Context, Surface};
 use std::num::NonZeroU32;
 use std::rc::Rc;
-use tracing::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{error, info, warn};
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
@@ -62,51 +67,30 @@ const BOTTOM_SCREEN_Y: u32 = BORDER_SIZE + TOP_SCREEN_HEIGHT + SCREEN_GAP;
 /// Border color in RGB format (dark grey: 0x333333)
 const BORDER_COLOR: u32 = 0x333333;
 
-// ================================================================================================
-// Framebuffer Format Constants
-// ================================================================================================
-
-/// Number of bytes per pixel in RGB8 format (Red, Green, Blue)
-const BYTES_PER_PIXEL_RGB8: u32 = 3;
-
-// ================================================================================================
-// Memory Address Range Constants
-// ================================================================================================
-
-/// Base address of VRAM (Video RAM) - 6 MB region
-const VRAM_BASE: u32 = 0x18000000;
-
-/// End address of VRAM (exclusive)
-const VRAM_END: u32 = 0x18600000;
-
-/// Base address of FCRAM (Fast Cycle RAM) - 128 MB region
-const FCRAM_BASE: u32 = 0x20000000;
-
-// ================================================================================================
-// Display Timing Constants
-// ================================================================================================
-
-/// Number of emulation quanta per frame
-const QUANTUMS_PER_FRAME: usize = 10;
-
 /// Emulator display application
 pub struct EmulatorDisplay {
     emulator: EmulatorCore,
+    /// Keep the window open once emulation stops instead of exiting
+    keep_window_on_stop: bool,
+    /// Emulation has stopped; only redraws and window events are processed
+    stopped: bool,
+    /// Seconds until the timeout last shown in the window title
+    title_secs_left: Option<u64>,
 
     // Display state
     window: Option<Rc<Window>>,
     surface: Option<Surface<Rc<Window>, Rc<Window>>>,
-
-    quantums_completed_in_this_frame: usize,
 }
 
 impl EmulatorDisplay {
-    pub fn new(emulator: EmulatorCore) -> Self {
+    pub fn new(emulator: EmulatorCore, keep_window_on_stop: bool) -> Self {
         Self {
             emulator,
+            keep_window_on_stop,
+            stopped: false,
+            title_secs_left: None,
             window: None,
             surface: None,
-            quantums_completed_in_this_frame: 0,
         }
     }
 }
@@ -150,8 +134,13 @@ impl ApplicationHandler for EmulatorDisplay {
     ) {
         match event {
             WindowEvent::CloseRequested => {
-                info!("=== Emulation Stopped ===");
-                self.emulator.print_final_state();
+                // The final state was already printed if emulation stopped on its own
+                if !self.stopped {
+                    info!("=== Emulation Stopped ===");
+                    if let Err(e) = self.emulator.print_final_state() {
+                        error!("{}", e);
+                    }
+                }
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
@@ -164,6 +153,10 @@ impl ApplicationHandler for EmulatorDisplay {
     }
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.stopped {
+            return;
+        }
+
         // Run a quantum
         let result = self.emulator.step();
 
@@ -172,17 +165,44 @@ impl ApplicationHandler for EmulatorDisplay {
 
         if should_stop {
             info!("=== Stop Condition Reached ===");
-            self.emulator.print_final_state();
-            event_loop.exit();
+            if let Err(e) = self.emulator.print_final_state() {
+                error!("{}", e);
+            }
+            if !self.keep_window_on_stop {
+                event_loop.exit();
+                return;
+            }
+
+            // Stop stepping, but keep handling redraws and input until the window closes
+            self.stopped = true;
+            event_loop.set_control_flow(ControlFlow::Wait);
+            if let Some(window) = self.window.as_ref() {
+                if self.emulator.timeout_remaining() == Some(Duration::ZERO) {
+                    window.set_title("threemu [timed out]");
+                } else {
+                    window.set_title("threemu [stopped]");
+                }
+                window.request_redraw();
+            }
             return;
         }
 
-        self.quantums_completed_in_this_frame += 1;
-        if self.quantums_completed_in_this_frame >= QUANTUMS_PER_FRAME
+        // Count down to the timeout in the title, updating it once per second
+        if let Some(remaining) = self.emulator.timeout_remaining() {
+            let secs_left = remaining.as_millis().div_ceil(1000) as u64;
+            if self.title_secs_left != Some(secs_left)
+                && let Some(window) = self.window.as_ref()
+            {
+                window.set_title(&format!("threemu [{}s left]", secs_left));
+                self.title_secs_left = Some(secs_left);
+            }
+        }
+
+        // Redraw at each frame boundary, as tracked by the scheduler
+        if self.emulator.frame_ended()
             && let Some(window) = self.window.as_mut()
         {
             window.request_redraw();
-            self.quantums_completed_in_this_frame = 0;
         }
         event_loop.set_control_flow(ControlFlow::Poll);
     }
@@ -197,114 +217,268 @@ impl EmulatorDisplay {
             *pixel = BORDER_COLOR;
         }
 
-        // Get GPU state from ARM11
-        let gpu_state = &emulator.arm11_emu().get_data().gpu;
-
-        // Get memory buffers
-        let fcram = emulator.fcram();
-        let vram = emulator.vram();
-
-        // Render top screen if we have an address
-        if gpu_state.top_left_addr != 0 {
-            Self::render_screen(
-                &mut buffer,
-                fcram,
-                vram,
-                gpu_state.top_left_addr,
-                TOP_SCREEN_X,
-                TOP_SCREEN_Y,
-                TOP_SCREEN_WIDTH,
-                TOP_SCREEN_HEIGHT,
-            );
+        for (screen, screen_x, screen_y) in [
+            (Screen::Top, TOP_SCREEN_X, TOP_SCREEN_Y),
+            (Screen::Bottom, BOTTOM_SCREEN_X, BOTTOM_SCREEN_Y),
+        ] {
+            // Leave the screen area as border until a framebuffer has been configured
+            if screen.framebuffer_addr(emulator) == 0 {
+                continue;
+            }
+            let rgb = render_screen_to_rgb(emulator, screen);
+            Self::blit(&mut buffer, &rgb, screen_x, screen_y, screen.width());
         }
 
-        // Render bottom screen if we have an address
-        if gpu_state.bottom_addr != 0 {
-            Self::render_screen(
-                &mut buffer,
-                fcram,
-                vram,
-                gpu_state.bottom_addr,
-                BOTTOM_SCREEN_X,
-                BOTTOM_SCREEN_Y,
-                BOTTOM_SCREEN_WIDTH,
-                BOTTOM_SCREEN_HEIGHT,
-            );
+        buffer.present().unwrap();
+    }
+
+    /// Copy an RGB8 screen image into the window buffer at the given position
+    fn blit(buffer: &mut [u32], rgb: &[u8], screen_x: u32, screen_y: u32, width: u32) {
+        for (i, pixel) in rgb.chunks_exact(3).enumerate() {
+            let window_x = screen_x + (i as u32 % width);
+            let window_y = screen_y + (i as u32 / width);
+            let idx = (window_y * WINDOW_WIDTH + window_x) as usize;
+
+            // Write pixel to output buffer in 0xRRGGBB format
+            if idx < buffer.len() {
+                let (r, g, b) = (pixel[0] as u32, pixel[1] as u32, pixel[2] as u32);
+                buffer[idx] = (r << 16) | (g << 8) | b;
+            }
         }
+    }
+}
 
-        buffer.present().unwrap();
+/// Order of the color channels of an RGB8 framebuffer pixel, in address order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum FbByteOrder {
+    /// Red first
+    #[default]
+    Rgb,
+    /// Blue first
+    Bgr,
+}
+
+/// Rotation of the stored framebuffer relative to the display
+///
+/// The 3DS stores framebuffers rotated 90° counter-clockwise, so each framebuffer
+/// line is one display column. Other rotations are for content that isn't laid out
+/// like hardware expects, or for checking whether a rendering bug is a rotation issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum FbRotation {
+    /// Stored in display orientation, row by row
+    #[value(name = "0")]
+    Deg0,
+    /// Stored rotated 90° counter-clockwise, as on hardware
+    #[default]
+    #[value(name = "90")]
+    Deg90,
+    /// Stored upside down
+    #[value(name = "180")]
+    Deg180,
+    /// Stored rotated 90° clockwise
+    #[value(name = "270")]
+    Deg270,
+}
+
+impl FbRotation {
+    /// Index in the framebuffer of the pixel shown at (`x`, `y`) on a `width` by
+    /// `height` screen (display coordinates, from the top-left corner)
+    ///
+    /// ```
+    /// use threemu::display::FbRotation;
+    ///
+    /// // Unrotated, pixels are stored row by row
+    /// assert_eq!(FbRotation::Deg0.pixel_index(0, 0, 400, 240), 0);
+    /// assert_eq!(FbRotation::Deg0.pixel_index(399, 0, 400, 240), 399);
+    /// assert_eq!(FbRotation::Deg0.pixel_index(5, 2, 400, 240), 2 * 400 + 5);
+    ///
+    /// // On hardware, the first framebuffer line is the leftmost column, bottom first
+    /// assert_eq!(FbRotation::Deg90.pixel_index(0, 239, 400, 240), 0);
+    /// ```
+    pub fn pixel_index(self, x: u32, y: u32, width: u32, height: u32) -> u32 {
+        match self {
+            FbRotation::Deg0 => y * width + x,
+            FbRotation::Deg90 => x * height + (height - 1 - y),
+            FbRotation::Deg180 => (height - 1 - y) * width + (width - 1 - x),
+            FbRotation::Deg270 => (width - 1 - x) * height + y,
+        }
     }
+}
 
-    /// Renders a 3DS screen framebuffer to the display buffer with 90° rotation
-    #[expect(clippy::too_many_arguments)]
-    fn render_screen(
-        buffer: &mut [u32],
-        fcram: &[u8],
-        vram: &[u8],
-        fb_addr: u32,
-        screen_x: u32,
-        screen_y: u32,
-        width: u32,
-        height: u32,
-    ) {
-        // Iterate over each pixel in the screen's display coordinates
-        for screen_y_offset in 0..height {
-            for screen_x_offset in 0..width {
-                // The 3DS framebuffer is stored rotated 90° counter-clockwise from the display.
-                // To render correctly, we need to rotate 90° clockwise when reading.
-                let fb_x = height - 1 - screen_y_offset;
-                let fb_y = screen_x_offset;
-
-                // Calculate pixel address in framebuffer using the rotated coordinates
-                let pixel_addr = fb_addr + ((fb_y * height + fb_x) * BYTES_PER_PIXEL_RGB8);
-
-                // Read pixel data from the appropriate memory region based on address
-                let (r, g, b) = if (VRAM_BASE..VRAM_END).contains(&pixel_addr) {
-                    // VRAM region: 0x18000000 - 0x18600000 (6 MB)
-                    let vram_offset = (pixel_addr - VRAM_BASE) as usize;
-                    if vram_offset + 2 < vram.len() {
-                        (
-                            vram[vram_offset] as u32,
-                            vram[vram_offset + 1] as u32,
-                            vram[vram_offset + 2] as u32,
-                        )
-                    } else {
-                        (0, 0, 0)
-                    }
-                } else if pixel_addr >= FCRAM_BASE {
-                    // FCRAM region: 0x20000000+ (128 MB)
-                    let fcram_offset = (pixel_addr - FCRAM_BASE) as usize;
-                    if fcram_offset + 2 < fcram.len() {
-                        (
-                            fcram[fcram_offset] as u32,
-                            fcram[fcram_offset + 1] as u32,
-                            fcram[fcram_offset + 2] as u32,
-                        )
-                    } else {
-                        (0, 0, 0)
+/// One of the two 3DS screens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Top,
+    Bottom,
+}
+
+impl Screen {
+    /// Width of the screen in pixels, in display orientation
+    pub fn width(self) -> u32 {
+        match self {
+            Screen::Top => TOP_SCREEN_WIDTH,
+            Screen::Bottom => BOTTOM_SCREEN_WIDTH,
+        }
+    }
+
+    /// Height of the screen in pixels, in display orientation
+    pub fn height(self) -> u32 {
+        match self {
+            Screen::Top => TOP_SCREEN_HEIGHT,
+            Screen::Bottom => BOTTOM_SCREEN_HEIGHT,
+        }
+    }
+
+    /// Address of the framebuffer currently scanned out to this screen (0 if unset)
+    pub fn framebuffer_addr(self, emulator: &EmulatorCore) -> u32 {
+        let gpu_state = &emulator.emu(Core::Arm11).get_data().gpu;
+        match self {
+            Screen::Top => gpu_state.active_top_addr(),
+            Screen::Bottom => gpu_state.active_bottom_addr(),
+        }
+    }
+
+    /// Whether this screen's display controller (PDC) is scanning its framebuffer out
+    pub fn scanout_enabled(self, emulator: &EmulatorCore) -> bool {
+        let gpu_state = &emulator.emu(Core::Arm11).get_data().gpu;
+        match self {
+            Screen::Top => gpu_state.top_enabled(),
+            Screen::Bottom => gpu_state.bottom_enabled(),
+        }
+    }
+
+    /// Pixel format and stride (bytes per framebuffer line) programmed for this screen
+    pub fn framebuffer_layout(self, emulator: &EmulatorCore) -> (PixelFormat, u32) {
+        let gpu_state = &emulator.emu(Core::Arm11).get_data().gpu;
+        match self {
+            Screen::Top => (gpu_state.top_format, gpu_state.top_stride),
+            Screen::Bottom => (gpu_state.bottom_format, gpu_state.bottom_stride),
+        }
+    }
+}
+
+/// Render a screen's current framebuffer to RGB8 bytes in display orientation
+///
+/// The result holds `width * height` pixels, row by row from the top-left corner.
+/// Pixels outside the memory holding the framebuffer, or all of them if no
+/// framebuffer is configured or the screen's PDC isn't enabled, are black.
+pub fn render_screen_to_rgb(emulator: &EmulatorCore, screen: Screen) -> Vec<u8> {
+    let fb_addr = screen.framebuffer_addr(emulator);
+    let (width, height) = (screen.width(), screen.height());
+    let byte_order = emulator.fb_byte_order();
+    let rotation = emulator.fb_rotation();
+
+    let mut rgb = vec![0u8; (width * height * 3) as usize];
+    if fb_addr == 0 || !screen.scanout_enabled(emulator) {
+        return rgb;
+    }
+    // Framebuffers are decoded as RGB8 whatever their configured format
+    let fb_len = (width * height) as usize * BYTES_PER_PIXEL_RGB8;
+    let Some(fb) = framebuffer_memory(emulator, screen, fb_addr, fb_len) else {
+        return rgb;
+    };
+
+    // Iterate over each pixel in the screen's display coordinates
+    for screen_y_offset in 0..height {
+        for screen_x_offset in 0..width {
+            // The 3DS framebuffer is normally stored rotated 90° counter-clockwise from
+            // the display, so it has to be rotated back when reading.
+            let fb_index = rotation.pixel_index(screen_x_offset, screen_y_offset, width, height);
+            let pixel_offset = fb_index as usize * BYTES_PER_PIXEL_RGB8;
+
+            if let Some(pixel) = fb.get(pixel_offset..pixel_offset + 3) {
+                let idx = ((screen_y_offset * width + screen_x_offset) * 3) as usize;
+                let out = &mut rgb[idx..idx + 3];
+                match byte_order {
+                    FbByteOrder::Rgb => out.copy_from_slice(pixel),
+                    FbByteOrder::Bgr => {
+                        out[0] = pixel[2];
+                        out[1] = pixel[1];
+                        out[2] = pixel[0];
                     }
-                } else {
-                    // Invalid address - render as black
-                    (0, 0, 0)
-                };
-
-                // Calculate position in the output window buffer
-                let window_x = screen_x + screen_x_offset;
-                let window_y = screen_y + screen_y_offset;
-                let idx = (window_y * WINDOW_WIDTH + window_x) as usize;
-
-                // Write pixel to output buffer in 0xRRGGBB format
-                if idx < buffer.len() {
-                    buffer[idx] = (r << 16) | (g << 8) | b;
                 }
             }
         }
     }
+
+    rgb
+}
+
+/// Per-screen flags so each problem with a framebuffer address is only reported once
+/// per emulator
+#[derive(Debug, Default)]
+pub struct FramebufferWarnings {
+    unmapped: [AtomicBool; 2],
+    truncated: [AtomicBool; 2],
+}
+
+impl FramebufferWarnings {
+    /// Whether this is the first time `screen`'s framebuffer was found unmapped
+    fn first_unmapped(&self, screen: Screen) -> bool {
+        !self.unmapped[screen as usize].swap(true, Ordering::Relaxed)
+    }
+
+    /// Whether this is the first time `screen`'s framebuffer was found truncated
+    fn first_truncated(&self, screen: Screen) -> bool {
+        !self.truncated[screen as usize].swap(true, Ordering::Relaxed)
+    }
 }
 
-pub fn run(emulator: EmulatorCore) -> Result<(), Box<dyn std::error::Error>> {
+/// Memory holding a framebuffer of `len` bytes at `fb_addr`, starting at its first byte
+///
+/// The framebuffer can be anywhere in shared RAM (see [`EmulatorCore::read_phys`]). It
+/// is confined to the region it starts in, so e.g. one in VRAM is truncated at the
+/// bank's end rather than spilling into the other bank. Returns `None` (warning once
+/// per screen) if the address isn't in shared RAM.
+fn framebuffer_memory(
+    emulator: &EmulatorCore,
+    screen: Screen,
+    fb_addr: u32,
+    len: usize,
+) -> Option<&[u8]> {
+    let warnings = emulator.fb_warnings();
+    let Some(fb) = emulator.read_phys(fb_addr, len) else {
+        if warnings.first_unmapped(screen) {
+            warn!(
+                "{:?} framebuffer address {:#X} is outside FCRAM, VRAM and AXI WRAM; rendering black",
+                screen, fb_addr
+            );
+        }
+        return None;
+    };
+    if fb.len() < len && warnings.first_truncated(screen) {
+        warn!(
+            "{:?} framebuffer at {:#X} runs past the end of its memory region; the rest renders black",
+            screen, fb_addr
+        );
+    }
+    Some(fb)
+}
+
+pub fn run(
+    emulator: EmulatorCore,
+    keep_window_on_stop: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let event_loop = EventLoop::new()?;
-    let mut app = EmulatorDisplay::new(emulator);
+    let mut app = EmulatorDisplay::new(emulator, keep_window_on_stop);
     event_loop.run_app(&mut app)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framebuffer_warnings_fire_once_per_screen_and_instance() {
+        let warnings = FramebufferWarnings::default();
+        assert!(warnings.first_unmapped(Screen::Top));
+        assert!(!warnings.first_unmapped(Screen::Top));
+        assert!(warnings.first_unmapped(Screen::Bottom));
+        assert!(warnings.first_truncated(Screen::Top));
+        assert!(!warnings.first_truncated(Screen::Top));
+
+        let other = FramebufferWarnings::default();
+        assert!(other.first_unmapped(Screen::Top));
+    }
+}
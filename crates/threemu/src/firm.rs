@@ -1,3 +1,23 @@
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::memory_map;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pkcs1v15Sign, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Size of the FIRM header, including the signature
+const HEADER_SIZE: usize = 0x200;
+
+/// Size of the part of the FIRM header covered by the signature
+const SIGNED_HEADER_SIZE: usize = 0x100;
+
+/// RSA-2048 public key FIRM signatures are checked against
+pub type PublicKey = RsaPublicKey;
+
 /// Errors that can occur during FIRM parsing
 #[derive(Debug)]
 pub enum FirmError {
@@ -5,6 +25,14 @@ pub enum FirmError {
     FileTooSmall,
     /// FIRM magic bytes are invalid (not "FIRM")
     InvalidMagic,
+    /// A section's data starts inside the header
+    SectionInHeader { section: usize },
+    /// A section's data runs past the end of the file
+    SectionOutOfBounds { section: usize },
+    /// A section's load address range wraps past the end of the address space
+    SectionAddressOverflow { section: usize },
+    /// The header signature does not match the header under the given public key
+    InvalidSignature,
 }
 
 /// FIRM section header describing a loadable firmware section
@@ -23,6 +51,24 @@ pub struct FirmSectionHeader {
     pub hash: [u8; 32],
 }
 
+impl FirmSectionHeader {
+    /// Byte range of the section's data within the FIRM file
+    pub fn file_range(&self) -> Range<usize> {
+        let start = self.offset as usize;
+        start..start + self.size as usize
+    }
+
+    /// Check the section's SHA-256 hash against its data in `firm_data`
+    ///
+    /// Returns `false` if the section extends past the end of the file.
+    pub fn hash_matches(&self, firm_data: &[u8]) -> bool {
+        let Some(data) = firm_data.get(self.file_range()) else {
+            return false;
+        };
+        Sha256::digest(data).as_slice() == self.hash
+    }
+}
+
 /// FIRM format header containing ARM9/ARM11 entry points and section info
 #[repr(C)]
 #[derive(Debug)]
@@ -44,9 +90,150 @@ pub struct FirmHeader {
 }
 
 impl FirmHeader {
+    /// Sections that exist (non-zero size), with their index in the section table
+    ///
+    /// ```
+    /// use threemu::{Core, FirmHeader, firm};
+    ///
+    /// let firm_data = firm::build_raw_firm(&[0u8; 0x100], Core::Arm9, 0x0800_0000, 0x0800_0000);
+    /// let firm = FirmHeader::parse(&firm_data).unwrap();
+    ///
+    /// for (i, section) in firm.loadable_sections() {
+    ///     let data = &firm_data[section.file_range()];
+    ///     println!("section {}: {:#X} bytes at {:#X}", i, data.len(), section.load_address);
+    /// }
+    /// assert_eq!(firm.loadable_sections().count(), 2);
+    /// ```
+    pub fn loadable_sections(&self) -> impl Iterator<Item = (usize, &FirmSectionHeader)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.size != 0)
+    }
+
+    /// Pairs of sections, by index, whose load address ranges overlap
+    ///
+    /// Loading such a FIRM lets the later section silently overwrite part of the
+    /// earlier one, which usually means the file is malformed.
+    ///
+    /// ```
+    /// use threemu::{Core, FirmHeader, firm};
+    ///
+    /// let firm_data = firm::build_raw_firm(&[0u8; 0x100], Core::Arm9, 0x0800_0000, 0x0800_0000);
+    /// let mut firm = FirmHeader::parse(&firm_data).unwrap();
+    /// assert!(firm.overlapping_sections().is_empty());
+    ///
+    /// // Move the second section into the middle of the first
+    /// firm.sections[1].load_address = 0x0800_0080;
+    /// assert_eq!(firm.overlapping_sections(), vec![(0, 1)]);
+    /// ```
+    pub fn overlapping_sections(&self) -> Vec<(usize, usize)> {
+        let range = |section: &FirmSectionHeader| {
+            let start = section.load_address as u64;
+            start..start + section.size as u64
+        };
+        let mut overlaps = Vec::new();
+        for (i, a) in self.loadable_sections() {
+            for (j, b) in self.loadable_sections().filter(|(j, _)| *j > i) {
+                let (a, b) = (range(a), range(b));
+                if a.start < b.end && b.start < a.end {
+                    overlaps.push((i, j));
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// Serialize the signed part of the header (everything before the signature)
+    fn signed_bytes(&self) -> [u8; SIGNED_HEADER_SIZE] {
+        let mut data = [0u8; SIGNED_HEADER_SIZE];
+        data[0x000..0x004].copy_from_slice(&self.magic);
+        data[0x004..0x008].copy_from_slice(&self.boot_priority.to_le_bytes());
+        data[0x008..0x00C].copy_from_slice(&self.arm11_entrypoint.to_le_bytes());
+        data[0x00C..0x010].copy_from_slice(&self.arm9_entrypoint.to_le_bytes());
+        data[0x010..0x040].copy_from_slice(&self.reserved);
+        for (i, section) in self.sections.iter().enumerate() {
+            let base = 0x040 + (i * 0x30);
+            data[base..base + 4].copy_from_slice(&section.offset.to_le_bytes());
+            data[base + 4..base + 8].copy_from_slice(&section.load_address.to_le_bytes());
+            data[base + 8..base + 12].copy_from_slice(&section.size.to_le_bytes());
+            data[base + 12..base + 16].copy_from_slice(&section.copy_method.to_le_bytes());
+            data[base + 16..base + 48].copy_from_slice(&section.hash);
+        }
+        data
+    }
+
+    /// Check the header signature, as the boot ROM does: an RSA-2048 PKCS#1 v1.5
+    /// signature of the SHA-256 hash of the first 0x100 bytes of the header
+    ///
+    /// ```no_run
+    /// use threemu::{FirmHeader, firm};
+    ///
+    /// let key = firm::load_public_key("firm_key.pem").unwrap();
+    /// let firm_data = std::fs::read("boot.firm").unwrap();
+    /// let firm = FirmHeader::parse(&firm_data).unwrap();
+    /// if firm.verify_signature(&key).is_err() {
+    ///     eprintln!("boot.firm isn't signed with firm_key.pem");
+    /// }
+    /// ```
+    pub fn verify_signature(&self, key: &PublicKey) -> Result<(), FirmError> {
+        let hash = Sha256::digest(self.signed_bytes());
+        key.verify(Pkcs1v15Sign::new::<Sha256>(), &hash, &self.signature)
+            .map_err(|_| FirmError::InvalidSignature)
+    }
+
+    /// Parse a FIRM header from raw file data and check that each section's data
+    /// lies within `data`, after the header, and loads within the 32-bit address space
+    ///
+    /// Never panics, whatever `data` holds, so a header returned by this can be loaded
+    /// without further bounds checks.
+    ///
+    /// ```
+    /// use threemu::{Core, FirmHeader, firm};
+    /// use threemu::firm::FirmError;
+    ///
+    /// let mut firm_data = firm::build_raw_firm(&[0u8; 0x100], Core::Arm9, 0x0800_0000, 0x0800_0000);
+    /// assert!(FirmHeader::parse_and_validate(&firm_data).is_ok());
+    ///
+    /// // Cut off the end of the last section
+    /// firm_data.truncate(firm_data.len() - 1);
+    /// assert!(matches!(
+    ///     FirmHeader::parse_and_validate(&firm_data),
+    ///     Err(FirmError::SectionOutOfBounds { section: 1 })
+    /// ));
+    /// ```
+    pub fn parse_and_validate(data: &[u8]) -> Result<Self, FirmError> {
+        let firm = Self::parse(data)?;
+        firm.validate(data)?;
+        Ok(firm)
+    }
+
+    /// Check that each section's data lies within `data`, after the header, and
+    /// loads within the 32-bit address space (see [`Self::parse_and_validate`])
+    ///
+    /// For a header that was parsed from `data` and then modified.
+    pub fn validate(&self, data: &[u8]) -> Result<(), FirmError> {
+        for (section, header) in self.loadable_sections() {
+            if (header.offset as usize) < HEADER_SIZE {
+                return Err(FirmError::SectionInHeader { section });
+            }
+            let end = (header.offset as usize).checked_add(header.size as usize);
+            if end.is_none_or(|end| end > data.len()) {
+                return Err(FirmError::SectionOutOfBounds { section });
+            }
+            if header.load_address.checked_add(header.size - 1).is_none() {
+                return Err(FirmError::SectionAddressOverflow { section });
+            }
+        }
+        Ok(())
+    }
+
     /// Parse a FIRM header from raw file data
+    ///
+    /// Only the header itself is checked; see [`Self::parse_and_validate`] to also
+    /// check the section table against the file.
     pub fn parse(data: &[u8]) -> Result<Self, FirmError> {
-        if data.len() < 0x200 {
+        if data.len() < HEADER_SIZE {
             return Err(FirmError::FileTooSmall);
         }
 
@@ -84,7 +271,7 @@ impl FirmHeader {
         }
 
         let mut signature = [0u8; 0x100];
-        signature.copy_from_slice(&data[0x100..0x200]);
+        signature.copy_from_slice(&data[0x100..HEADER_SIZE]);
 
         Ok(FirmHeader {
             magic,
@@ -97,3 +284,247 @@ impl FirmHeader {
         })
     }
 }
+
+/// Load a FIRM signing public key from a PEM file, in either SPKI (`BEGIN PUBLIC
+/// KEY`) or PKCS#1 (`BEGIN RSA PUBLIC KEY`) form
+pub fn load_public_key(path: &str) -> Result<PublicKey, String> {
+    let pem = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read public key {}: {:?}", path, e))?;
+    PublicKey::from_public_key_pem(&pem)
+        .or_else(|_| PublicKey::from_pkcs1_pem(&pem))
+        .map_err(|e| format!("Failed to parse public key {}: {:?}", path, e))
+}
+
+/// Index of the FIRM to boot among `candidates`, as a chainloader picking from
+/// several FIRMs would: the valid one with the highest boot priority, or the first
+/// of those on a tie
+///
+/// A FIRM is valid if it has an ARM9 entrypoint and at least one section, and no
+/// two of its sections overlap. Returns `None` if no candidate is valid.
+///
+/// ```
+/// use std::path::PathBuf;
+/// use threemu::{Core, FirmHeader, firm};
+///
+/// let header = |priority| {
+///     let data = firm::build_raw_firm(&[0u8; 0x100], Core::Arm9, 0x0800_0000, 0x0800_0000);
+///     let mut firm = FirmHeader::parse(&data).unwrap();
+///     firm.boot_priority = priority;
+///     firm
+/// };
+///
+/// let mut candidates = vec![
+///     (PathBuf::from("a.firm"), header(1)),
+///     (PathBuf::from("b.firm"), header(3)),
+///     (PathBuf::from("c.firm"), header(3)),
+///     (PathBuf::from("d.firm"), header(2)),
+/// ];
+/// assert_eq!(firm::select_firm(&candidates), Some(1));
+///
+/// // An invalid FIRM is skipped whatever its priority
+/// candidates[1].1.arm9_entrypoint = 0;
+/// assert_eq!(firm::select_firm(&candidates), Some(2));
+///
+/// assert_eq!(firm::select_firm(&[]), None);
+/// ```
+pub fn select_firm(candidates: &[(PathBuf, FirmHeader)]) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, firm))| {
+            firm.arm9_entrypoint != 0
+                && firm.loadable_sections().next().is_some()
+                && firm.overlapping_sections().is_empty()
+        })
+        .max_by_key(|(i, (_, firm))| (firm.boot_priority, Reverse(*i)))
+        .map(|(i, _)| i)
+}
+
+/// A FIRM's header fields and section table, displayed as a human-readable listing
+pub struct FirmInfo<'a> {
+    firm: FirmHeader,
+    firm_data: &'a [u8],
+}
+
+/// Describe a FIRM's header fields and section table (see [`FirmInfo`])
+pub fn info(firm_data: &[u8]) -> Result<FirmInfo<'_>, FirmError> {
+    Ok(FirmInfo {
+        firm: FirmHeader::parse_and_validate(firm_data)?,
+        firm_data,
+    })
+}
+
+impl fmt::Display for FirmInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let firm = &self.firm;
+        writeln!(
+            f,
+            "Magic:            {}",
+            String::from_utf8_lossy(&firm.magic)
+        )?;
+        writeln!(f, "Boot priority:    {}", firm.boot_priority)?;
+        writeln!(f, "ARM9 entrypoint:  {:#010X}", firm.arm9_entrypoint)?;
+        writeln!(f, "ARM11 entrypoint: {:#010X}", firm.arm11_entrypoint)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Section  Offset      Load address  Size        Copy method  Hash"
+        )?;
+        for (i, section) in firm.sections.iter().enumerate() {
+            if section.size == 0 {
+                writeln!(f, "{:<7}  (unused)", i)?;
+                continue;
+            }
+            let copy_method = match section.copy_method {
+                0 => "NDMA",
+                1 => "XDMA",
+                2 => "memcpy",
+                _ => "unknown",
+            };
+            writeln!(
+                f,
+                "{:<7}  {:#010X}  {:#010X}    {:#010X}  {:<11}  {}",
+                i,
+                section.offset,
+                section.load_address,
+                section.size,
+                copy_method,
+                if section.hash_matches(self.firm_data) {
+                    "ok"
+                } else {
+                    "MISMATCH"
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Instruction encoding of `b .` (branch to self), used to park an idle core
+const BRANCH_TO_SELF: u32 = 0xEAFF_FFFE;
+
+/// Parking loop address for ARM9 when running a raw ARM11 binary (end of ARM9 private WRAM)
+const ARM9_PARK_ADDR: u32 =
+    memory_map::arm9::private_wram::BASE + memory_map::arm9::private_wram::SIZE as u32 - 4;
+
+/// Parking loop address for ARM11 when running a raw ARM9 binary (end of VRAM)
+const ARM11_PARK_ADDR: u32 = memory_map::vram::BASE + memory_map::vram::SIZE as u32 - 4;
+
+/// Wrap a raw flat binary in an in-memory FIRM image
+///
+/// The binary becomes section 0, loaded at `load_addr`, and `core` starts at `entry`.
+/// The other core is parked on a `b .` loop placed in a small second section so it
+/// stays out of the way. Section hashes are left zeroed.
+pub fn build_raw_firm(binary: &[u8], core: Core, load_addr: u32, entry: u32) -> Vec<u8> {
+    let park_addr = match core {
+        Core::Arm9 => ARM11_PARK_ADDR,
+        Core::Arm11 => ARM9_PARK_ADDR,
+    };
+    let (arm9_entry, arm11_entry) = match core {
+        Core::Arm9 => (entry, park_addr),
+        Core::Arm11 => (park_addr, entry),
+    };
+
+    let binary_offset = HEADER_SIZE;
+    let park_offset = binary_offset + binary.len().next_multiple_of(4);
+
+    let mut data = vec![0u8; park_offset + 4];
+    data[0x000..0x004].copy_from_slice(b"FIRM");
+    data[0x008..0x00C].copy_from_slice(&arm11_entry.to_le_bytes());
+    data[0x00C..0x010].copy_from_slice(&arm9_entry.to_le_bytes());
+
+    let sections = [
+        (binary_offset, load_addr, binary.len()),
+        (park_offset, park_addr, 4),
+    ];
+    for (i, (offset, address, size)) in sections.into_iter().enumerate() {
+        let base = 0x040 + (i * 0x30);
+        data[base..base + 4].copy_from_slice(&(offset as u32).to_le_bytes());
+        data[base + 4..base + 8].copy_from_slice(&address.to_le_bytes());
+        data[base + 8..base + 12].copy_from_slice(&(size as u32).to_le_bytes());
+        // Copy method 2 (memcpy)
+        data[base + 12..base + 16].copy_from_slice(&2u32.to_le_bytes());
+    }
+
+    data[binary_offset..binary_offset + binary.len()].copy_from_slice(binary);
+    data[park_offset..park_offset + 4].copy_from_slice(&BRANCH_TO_SELF.to_le_bytes());
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_lists_header_and_sections() {
+        let firm = build_raw_firm(&[0; 0x10], Core::Arm9, 0x0800_0000, 0x0800_0000);
+        let listing = info(&firm).unwrap().to_string();
+        assert!(listing.contains("ARM9 entrypoint:  0x08000000"));
+        let sections: Vec<&str> = listing.lines().skip(6).collect();
+        assert_eq!(
+            sections[0],
+            "0        0x00000200  0x08000000    0x00000010  memcpy       MISMATCH"
+        );
+        assert_eq!(sections[3], "3        (unused)");
+    }
+
+    /// Public half of an RSA-2048 key generated for these tests
+    const TEST_KEY_PEM: &str = "\
+-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA4d4kmYb+wU++PCl2szDZ
+hOQ/SHHQZleM6lb9xU7e8H96ZgeWs4563M6l2odZ1CIBd/4fkL/RYhiXl4c1edi6
+vUxNdCGKsej2OpgBnR3OHJ7W9QPIm0t37lc4Ys8X5mMh6MijgoYXDCKCtrcA4gkA
+iBkx7wWh9/2XrwQx9jiEBldYOjw+I7hJEstp7kd3ahAMWwAx/hboNRGMbZiquDY1
+HfsvI2ecTRCDzKQtIpaNRRfClLxRkTkz0scGBItgxCOujSapCLHyHXKd2oKwsiEY
+fYba1O1z4qqxvduFjkkYbcB5BG/1BuS7PdSzQS/rIStKVKYnLSwSlHdMlD+RUEMX
+ZQIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    /// Signature of the header of `signed_test_firm` under `TEST_KEY_PEM`
+    const TEST_SIGNATURE: &str = concat!(
+        "702c1928f637ce4e9983f8600ef2ef7328cf8bdf8f90894f3f7e1f17067c98cb",
+        "47506d2c2c4b7c15a30cefb241443afbf346144f3c1b56ddc5369db4bf7441ae",
+        "bf59b907b6c07909a47fb3f04b76e815a9a95227a532197013e104d3a1ad599d",
+        "81b5524a6dc0aff3a166d1354c2757f333ed315149ea20344aab5f17e00b1c1c",
+        "a5e9afe1892013ac69244d62e8758e58957fd93c20e71bc845d5af8985601156",
+        "56f2f8ef193994f14786895ed7cda86f537ac5e4beb1fe13e6e700b03bc15c22",
+        "5fb883cd5fa3324c400f0d68477ab2cb98e2ece7d7aead8013c8001b2c6f7bed",
+        "2e9e81da3d4f012265a647275796388ab50ceb1f43a1e89d5a73677859418fa6",
+    );
+
+    /// FIRM carrying `TEST_SIGNATURE`
+    fn signed_test_firm() -> FirmHeader {
+        let mut data = build_raw_firm(&[0; 0x100], Core::Arm9, 0x0800_0000, 0x0800_0000);
+        for (i, byte) in data[SIGNED_HEADER_SIZE..HEADER_SIZE].iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&TEST_SIGNATURE[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        FirmHeader::parse(&data).unwrap()
+    }
+
+    #[test]
+    fn signature_verifies_under_the_signing_key() {
+        let key = PublicKey::from_public_key_pem(TEST_KEY_PEM).unwrap();
+        assert!(signed_test_firm().verify_signature(&key).is_ok());
+    }
+
+    #[test]
+    fn tampered_header_or_signature_fails_verification() {
+        let key = PublicKey::from_public_key_pem(TEST_KEY_PEM).unwrap();
+
+        // Any change to the signed header invalidates the signature
+        let mut firm = signed_test_firm();
+        firm.arm9_entrypoint += 4;
+        assert!(matches!(
+            firm.verify_signature(&key),
+            Err(FirmError::InvalidSignature)
+        ));
+
+        let mut firm = signed_test_firm();
+        firm.signature[0] ^= 1;
+        assert!(matches!(
+            firm.verify_signature(&key),
+            Err(FirmError::InvalidSignature)
+        ));
+    }
+}
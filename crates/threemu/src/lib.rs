@@ -1,17 +1,34 @@
 pub mod args;
 pub mod bootrom;
+pub mod container;
 pub mod core;
+pub mod coverage;
 pub mod cp15;
 pub mod cpu_types;
 pub mod display;
 pub mod firm;
 pub mod memory;
+pub mod milestones;
 pub mod mmio;
+pub mod pctrace;
+pub mod savestate;
 pub mod scheduler;
+pub mod screenshot;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 // Re-export commonly used types
 pub use args::{Args, load_firm_data};
-pub use core::{EmulatorConfig, EmulatorCore, StopReason};
-pub use cpu_types::ArmRegister;
-pub use mmio::{EmulatorState, GpuState, PixelFormat, SdmmcState};
-pub use scheduler::{QuantumResult, SchedulerConfig};
+pub use core::{
+    BankedRegisters, EmulatorConfig, EmulatorConfigBuilder, EmulatorCore, EmulatorError, MemRegion,
+    RamInit, StopHandle, StopReason,
+};
+pub use cpu_types::{ArmRegister, Core, CoreSelection, CpuModel, Endianness, ProcessorMode};
+pub use display::{FbByteOrder, FbRotation, Screen};
+pub use firm::FirmHeader;
+pub use mmio::{
+    Button, Cfg9State, Config11State, CustomReadHandler, CustomWriteHandler, EmulatorState,
+    GicState, GpuState, HidState, I2cState, PixelFormat, SdmmcState, UnimplementedAccess,
+};
+pub use savestate::SaveState;
+pub use scheduler::{QuantumProgress, QuantumResult, SchedulerConfig, StopDetail};
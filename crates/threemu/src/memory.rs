@@ -3,11 +3,15 @@
 //! This module provides functions for setting up memory maps for both ARM9 and ARM11
 //! processors, as well as loading FIRM sections into memory.
 
-use crate::firm::FirmSectionHeader;
+use crate::cpu_types::Core;
+use crate::firm::{FirmHeader, FirmSectionHeader};
 use crate::mmio;
 use oxidiz3ds_hw::{memory_map, mmio as hw_mmio};
-use tracing::debug;
-use unicorn_engine::{Unicorn, unicorn_const::Prot};
+use tracing::{debug, warn};
+use unicorn_engine::{
+    Unicorn,
+    unicorn_const::{HookType, MemRegion, Prot, uc_error},
+};
 
 // Memory constants from hardware definitions
 pub const FCRAM_BASE: u32 = memory_map::fcram::BASE;
@@ -26,12 +30,258 @@ const MMIO_REGION1_BASE: u32 = memory_map::mmio::region1::BASE;
 const MMIO_REGION1_END: u32 = memory_map::mmio::region1::END;
 const MMIO_REGION2_BASE: u32 = memory_map::mmio::region2::BASE;
 const MMIO_REGION2_END: u32 = memory_map::mmio::region2::END;
+const CFG9_MMIO_BASE: u32 = hw_mmio::cfg9::BASE;
+const CFG9_MMIO_END: u32 = hw_mmio::cfg9::END;
 const SDMMC_MMIO_BASE: u32 = hw_mmio::sdmmc::BASE;
 const SDMMC_MMIO_END: u32 = hw_mmio::sdmmc::END;
 const GPU_MMIO_BASE: u32 = hw_mmio::gpu::BASE;
 const GPU_MMIO_END: u32 = hw_mmio::gpu::END;
+const GIC_MMIO_BASE: u32 = hw_mmio::gic::BASE;
+const GIC_MMIO_END: u32 = hw_mmio::gic::END;
+const CONFIG11_MMIO_BASE: u32 = hw_mmio::config11::BASE;
+const CONFIG11_MMIO_END: u32 = hw_mmio::config11::END;
+const DSP_MMIO_BASE: u32 = hw_mmio::dsp::BASE;
+const DSP_MMIO_END: u32 = hw_mmio::dsp::END;
+const HID_MMIO_BASE: u32 = hw_mmio::hid::BASE;
+const HID_MMIO_END: u32 = hw_mmio::hid::END;
+const I2C_BUS_BASES: [u32; 3] = hw_mmio::i2c::BUS_BASES;
+const I2C_BUS_SIZE: u32 = hw_mmio::i2c::BUS_SIZE;
 const ARM11_MMIO_SPLIT: u32 = memory_map::mmio::ARM11_MMIO_SPLIT;
 
+/// MMIO read callback
+type MmioReadHandler = fn(&mut Unicorn<'_, mmio::EmulatorState>, u64, usize) -> u64;
+
+/// MMIO write callback
+type MmioWriteHandler = fn(&mut Unicorn<'_, mmio::EmulatorState>, u64, usize, u64);
+
+/// A device with dedicated MMIO handlers inside a larger MMIO region
+struct MmioDevice {
+    name: &'static str,
+    base: u32,
+    end: u32,
+    /// Read/write handlers, or `None` to intentionally leave the range unmapped
+    handlers: Option<(MmioReadHandler, MmioWriteHandler)>,
+}
+
+/// Devices present in the MMIO map of both ARM9 and ARM11, sorted by base address
+fn shared_mmio_devices() -> Vec<MmioDevice> {
+    vec![
+        MmioDevice {
+            name: "SDMMC",
+            base: SDMMC_MMIO_BASE,
+            end: SDMMC_MMIO_END,
+            handlers: Some((mmio::sdmmc::read_handler, mmio::sdmmc::write_handler)),
+        },
+        MmioDevice {
+            name: "unused",
+            base: SDMMC_MMIO_END,
+            end: SDMMC_MMIO_END + 0x1000,
+            handlers: None,
+        },
+        MmioDevice {
+            name: "HID",
+            base: HID_MMIO_BASE,
+            end: HID_MMIO_END,
+            handlers: Some((mmio::hid::read_handler, mmio::hid::write_handler)),
+        },
+        MmioDevice {
+            name: "I2C bus 0",
+            base: I2C_BUS_BASES[0],
+            end: I2C_BUS_BASES[0] + I2C_BUS_SIZE,
+            handlers: Some((mmio::i2c::read_handler::<0>, mmio::i2c::write_handler::<0>)),
+        },
+    ]
+}
+
+/// Devices present only in the ARM11 MMIO map, sorted by base address
+fn arm11_mmio_devices() -> Vec<MmioDevice> {
+    vec![
+        MmioDevice {
+            name: "CONFIG11",
+            base: CONFIG11_MMIO_BASE,
+            end: CONFIG11_MMIO_END,
+            handlers: Some((mmio::config11::read_handler, mmio::config11::write_handler)),
+        },
+        MmioDevice {
+            name: "I2C bus 1",
+            base: I2C_BUS_BASES[1],
+            end: I2C_BUS_BASES[1] + I2C_BUS_SIZE,
+            handlers: Some((mmio::i2c::read_handler::<1>, mmio::i2c::write_handler::<1>)),
+        },
+        MmioDevice {
+            name: "I2C bus 2",
+            base: I2C_BUS_BASES[2],
+            end: I2C_BUS_BASES[2] + I2C_BUS_SIZE,
+            handlers: Some((mmio::i2c::read_handler::<2>, mmio::i2c::write_handler::<2>)),
+        },
+        MmioDevice {
+            name: "DSP",
+            base: DSP_MMIO_BASE,
+            end: DSP_MMIO_END,
+            handlers: Some((mmio::dsp::read_handler, mmio::dsp::write_handler)),
+        },
+        MmioDevice {
+            name: "GPU",
+            base: GPU_MMIO_BASE,
+            end: GPU_MMIO_END,
+            handlers: Some((mmio::gpu::read_handler, mmio::gpu::write_handler)),
+        },
+        MmioDevice {
+            name: "GIC",
+            base: GIC_MMIO_BASE,
+            end: GIC_MMIO_END,
+            handlers: Some((mmio::gic::read_handler, mmio::gic::write_handler)),
+        },
+    ]
+}
+
+/// Map `start..end`, giving each device its own handlers and filling the gaps
+/// between devices with the generic handler
+///
+/// Devices must be sorted by base address and must not overlap.
+fn map_mmio_region(
+    emu: &mut Unicorn<mmio::EmulatorState>,
+    start: u32,
+    end: u32,
+    devices: &[MmioDevice],
+) -> Result<(), String> {
+    let mut cursor = start;
+    for device in devices {
+        if cursor < device.base {
+            map_generic_mmio(emu, cursor, device.base)?;
+        }
+
+        match device.handlers {
+            Some((read, write)) => {
+                debug!(
+                    "  Mapping {} MMIO region {:#X} - {:#X}",
+                    device.name, device.base, device.end
+                );
+                let base = device.base as u64;
+                emu.mmio_map(
+                    base,
+                    (device.end - device.base) as u64,
+                    Some(read),
+                    Some(
+                        move |uc: &mut Unicorn<'_, mmio::EmulatorState>, offset, size, value| {
+                            mmio::record_write(uc, base + offset, size, value);
+                            write(uc, offset, size, value)
+                        },
+                    ),
+                )
+                .map_err(|e| format!("failed to map {} MMIO region: {:?}", device.name, e))?;
+                emu.get_data_mut().mmio_devices.push(device.name);
+            }
+            None => {
+                debug!(
+                    "  Intentionally leaving {:#X} - {:#X} unmapped ({} region)",
+                    device.base, device.end, device.name
+                );
+            }
+        }
+        cursor = device.end;
+    }
+
+    if cursor < end {
+        map_generic_mmio(emu, cursor, end)?;
+    }
+    Ok(())
+}
+
+/// Map `start..end` with the generic (stub) MMIO handler
+///
+/// Unicorn passes offsets relative to `start`; the handlers receive absolute addresses.
+fn map_generic_mmio(
+    emu: &mut Unicorn<mmio::EmulatorState>,
+    start: u32,
+    end: u32,
+) -> Result<(), String> {
+    debug!("  Mapping generic MMIO region {:#X} - {:#X}", start, end);
+    let base = start as u64;
+    emu.mmio_map(
+        base,
+        (end - start) as u64,
+        Some(
+            move |uc: &mut Unicorn<'_, mmio::EmulatorState>, offset, size| {
+                mmio::generic::read_handler(uc, base + offset, size)
+            },
+        ),
+        Some(
+            move |uc: &mut Unicorn<'_, mmio::EmulatorState>, offset, size, value| {
+                mmio::record_write(uc, base + offset, size, value);
+                mmio::generic::write_handler(uc, base + offset, size, value)
+            },
+        ),
+    )
+    .map_err(|e| format!("failed to map generic MMIO region: {:?}", e))
+}
+
+/// Map `base..base + len` with caller-supplied MMIO handlers, replacing whatever is
+/// mapped there
+///
+/// Any overlapping part of an existing mapping (generic MMIO, a device, or RAM) is
+/// unmapped first; the rest of that mapping keeps working. A missing handler falls back
+/// to the generic one, so e.g. a write-only model still logs reads as unimplemented.
+/// Writes are recorded in the MMIO write log like any other.
+pub fn map_custom_mmio(
+    emu: &mut Unicorn<'static, mmio::EmulatorState>,
+    base: u64,
+    len: u64,
+    read: Option<mmio::CustomReadHandler>,
+    write: Option<mmio::CustomWriteHandler>,
+) -> Result<(), String> {
+    if len == 0 || !base.is_multiple_of(0x1000) || !len.is_multiple_of(0x1000) {
+        return Err(format!(
+            "custom MMIO range {:#X} + {:#X} must be non-empty and 4 KiB aligned",
+            base, len
+        ));
+    }
+    let end = base
+        .checked_add(len)
+        .filter(|&end| end <= 1 << 32)
+        .ok_or_else(|| format!("custom MMIO range {:#X} + {:#X} overflows", base, len))?;
+
+    let regions = emu
+        .mem_regions()
+        .map_err(|e| format!("failed to list memory regions: {:?}", e))?;
+    for region in regions {
+        let start = region.begin.max(base);
+        let stop = (region.end + 1).min(end);
+        if start < stop {
+            debug!(
+                "  Unmapping {:#X} - {:#X} for custom MMIO handlers",
+                start, stop
+            );
+            emu.mem_unmap(start, stop - start)
+                .map_err(|e| format!("failed to unmap {:#X} - {:#X}: {:?}", start, stop, e))?;
+        }
+    }
+
+    debug!("  Mapping custom MMIO region {:#X} - {:#X}", base, end);
+    let mut read = read;
+    let mut write = write;
+    emu.mmio_map(
+        base,
+        len,
+        Some(
+            move |uc: &mut Unicorn<'_, mmio::EmulatorState>, offset, size| match &mut read {
+                Some(read) => read(uc, offset, size),
+                None => mmio::generic::read_handler(uc, base + offset, size),
+            },
+        ),
+        Some(
+            move |uc: &mut Unicorn<'_, mmio::EmulatorState>, offset, size, value| {
+                mmio::record_write(uc, base + offset, size, value);
+                match &mut write {
+                    Some(write) => write(uc, offset, size, value),
+                    None => mmio::generic::write_handler(uc, base + offset, size, value),
+                }
+            },
+        ),
+    )
+    .map_err(|e| format!("failed to map custom MMIO region: {:?}", e))
+}
+
 /// Set up memory map for ARM9
 pub fn setup_arm9_memory(
     emu: &mut Unicorn<mmio::EmulatorState>,
@@ -39,7 +289,7 @@ pub fn setup_arm9_memory(
     axi_wram: &mut [u8],
     vram: &mut [u8],
     arm9_private_wram: &mut [u8],
-) {
+) -> Result<(), String> {
     // Shared memory regions
     debug!(
         "  Mapping shared FCRAM at {:#X} ({}MB)",
@@ -53,7 +303,7 @@ pub fn setup_arm9_memory(
             Prot::ALL,
             fcram.as_mut_ptr() as _,
         )
-        .expect("failed to map FCRAM");
+        .map_err(|e| format!("failed to map FCRAM: {:?}", e))?;
     }
 
     debug!(
@@ -68,7 +318,7 @@ pub fn setup_arm9_memory(
             Prot::ALL,
             axi_wram.as_mut_ptr() as _,
         )
-        .expect("failed to map AXI WRAM");
+        .map_err(|e| format!("failed to map AXI WRAM: {:?}", e))?;
     }
 
     debug!(
@@ -83,7 +333,7 @@ pub fn setup_arm9_memory(
             Prot::ALL,
             vram.as_mut_ptr() as _,
         )
-        .expect("failed to map VRAM");
+        .map_err(|e| format!("failed to map VRAM: {:?}", e))?;
     }
 
     // ARM9-specific internal memory
@@ -93,7 +343,7 @@ pub fn setup_arm9_memory(
         ARM9_ITCM_SIZE / (1024 * 1024)
     );
     emu.mem_map(ARM9_ITCM_BASE as u64, ARM9_ITCM_SIZE as u64, Prot::ALL)
-        .expect("failed to map ARM9 internal memory");
+        .map_err(|e| format!("failed to map ARM9 internal memory: {:?}", e))?;
 
     // ARM9-specific private WRAM
     debug!(
@@ -108,64 +358,25 @@ pub fn setup_arm9_memory(
             Prot::ALL,
             arm9_private_wram.as_mut_ptr() as _,
         )
-        .expect("failed to map ARM9 private WRAM");
+        .map_err(|e| format!("failed to map ARM9 private WRAM: {:?}", e))?;
     }
 
-    // Generic MMIO regions (split around VRAM and SDMMC)
-    debug!(
-        "  Mapping generic MMIO region {:#X} - {:#X}",
-        MMIO_REGION1_BASE, SDMMC_MMIO_BASE
+    // Region 1 MMIO (split around the devices with dedicated handlers), including
+    // the ARM9-only devices
+    let mut devices = shared_mmio_devices();
+    devices.insert(
+        0,
+        MmioDevice {
+            name: "CFG9",
+            base: CFG9_MMIO_BASE,
+            end: CFG9_MMIO_END,
+            handlers: Some((mmio::cfg9::read_handler, mmio::cfg9::write_handler)),
+        },
     );
-    emu.mmio_map(
-        MMIO_REGION1_BASE as u64,
-        (SDMMC_MMIO_BASE - MMIO_REGION1_BASE) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map generic MMIO region");
+    map_mmio_region(emu, MMIO_REGION1_BASE, MMIO_REGION1_END, &devices)?;
 
-    debug!(
-        "  Mapping SDMMC MMIO region {:#X} - {:#X}",
-        SDMMC_MMIO_BASE, SDMMC_MMIO_END
-    );
-    emu.mmio_map(
-        SDMMC_MMIO_BASE as u64,
-        (SDMMC_MMIO_END - SDMMC_MMIO_BASE) as u64,
-        Some(mmio::sdmmc::read_handler),
-        Some(mmio::sdmmc::write_handler),
-    )
-    .expect("failed to map SDMMC MMIO region");
-
-    debug!(
-        "  Intentionally leaving {:#X} - {:#X} unmapped (unused region)",
-        SDMMC_MMIO_END,
-        SDMMC_MMIO_END + 0x1000
-    );
-
-    debug!(
-        "  Mapping generic MMIO region {:#X} - {:#X}",
-        SDMMC_MMIO_END + 0x1000,
-        MMIO_REGION1_END
-    );
-    emu.mmio_map(
-        (SDMMC_MMIO_END + 0x1000) as u64,
-        (MMIO_REGION1_END - (SDMMC_MMIO_END + 0x1000)) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map generic MMIO region");
-
-    debug!(
-        "  Mapping generic MMIO region {:#X} - {:#X}",
-        MMIO_REGION2_BASE, MMIO_REGION2_END
-    );
-    emu.mmio_map(
-        MMIO_REGION2_BASE as u64,
-        (MMIO_REGION2_END - MMIO_REGION2_BASE) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map MMIO region");
+    // Region 2 MMIO (after VRAM)
+    map_generic_mmio(emu, MMIO_REGION2_BASE, MMIO_REGION2_END)
 }
 
 /// Set up memory map for ARM11
@@ -174,7 +385,7 @@ pub fn setup_arm11_memory(
     fcram: &mut [u8],
     axi_wram: &mut [u8],
     vram: &mut [u8],
-) {
+) -> Result<(), String> {
     // Shared memory regions
     debug!(
         "  Mapping shared FCRAM at {:#X} ({}MB)",
@@ -188,7 +399,7 @@ pub fn setup_arm11_memory(
             Prot::ALL,
             fcram.as_mut_ptr() as _,
         )
-        .expect("failed to map FCRAM");
+        .map_err(|e| format!("failed to map FCRAM: {:?}", e))?;
     }
 
     debug!(
@@ -203,7 +414,7 @@ pub fn setup_arm11_memory(
             Prot::ALL,
             axi_wram.as_mut_ptr() as _,
         )
-        .expect("failed to map AXI WRAM");
+        .map_err(|e| format!("failed to map AXI WRAM: {:?}", e))?;
     }
 
     debug!(
@@ -218,115 +429,210 @@ pub fn setup_arm11_memory(
             Prot::ALL,
             vram.as_mut_ptr() as _,
         )
-        .expect("failed to map VRAM");
+        .map_err(|e| format!("failed to map VRAM: {:?}", e))?;
     }
 
-    // MMIO regions with separate handlers
-    debug!(
-        "  Mapping generic MMIO region {:#X} - {:#X}",
-        MMIO_REGION1_BASE, SDMMC_MMIO_BASE
-    );
-    emu.mmio_map(
-        MMIO_REGION1_BASE as u64,
-        (SDMMC_MMIO_BASE - MMIO_REGION1_BASE) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map generic MMIO region");
+    // MMIO regions with separate handlers, including the ARM11-only devices
+    let mut devices = shared_mmio_devices();
+    devices.extend(arm11_mmio_devices());
+    devices.sort_by_key(|device| device.base);
+    map_mmio_region(emu, MMIO_REGION1_BASE, ARM11_MMIO_SPLIT, &devices)?;
 
-    debug!(
-        "  Mapping SDMMC MMIO region {:#X} - {:#X}",
-        SDMMC_MMIO_BASE, SDMMC_MMIO_END
-    );
-    emu.mmio_map(
-        SDMMC_MMIO_BASE as u64,
-        (SDMMC_MMIO_END - SDMMC_MMIO_BASE) as u64,
-        Some(mmio::sdmmc::read_handler),
-        Some(mmio::sdmmc::write_handler),
-    )
-    .expect("failed to map SDMMC MMIO region");
+    // Region 2 MMIO (after VRAM)
+    map_generic_mmio(emu, MMIO_REGION2_BASE, MMIO_REGION2_END)
+}
 
-    debug!(
-        "  Intentionally leaving {:#X} - {:#X} unmapped (unused region)",
-        SDMMC_MMIO_END,
-        SDMMC_MMIO_END + 0x1000
-    );
+/// Record writes to memory shared between ARM9 and ARM11
+///
+/// Both cores map FCRAM, AXI WRAM, and VRAM onto the same backing buffers, but each
+/// Unicorn instance keeps its own translation cache. The recorded range lets the
+/// scheduler invalidate stale translated code on the other core.
+pub fn track_shared_writes(emu: &mut Unicorn<mmio::EmulatorState>) -> Result<(), uc_error> {
+    let shared_regions = [
+        (FCRAM_BASE, FCRAM_SIZE),
+        (AXI_WRAM_BASE, AXI_WRAM_SIZE),
+        (VRAM_BASE, VRAM_SIZE),
+    ];
 
-    debug!(
-        "  Mapping generic MMIO region {:#X} - {:#X}",
-        SDMMC_MMIO_END + 0x1000,
-        GPU_MMIO_BASE
-    );
-    emu.mmio_map(
-        (SDMMC_MMIO_END + 0x1000) as u64,
-        (GPU_MMIO_BASE - (SDMMC_MMIO_END + 0x1000)) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map generic MMIO region");
+    for (base, size) in shared_regions {
+        debug!(
+            "  Tracking shared writes to {:#X} - {:#X}",
+            base,
+            base as u64 + size as u64
+        );
+        emu.add_mem_hook(
+            HookType::MEM_WRITE,
+            base as u64,
+            base as u64 + size as u64 - 1,
+            |uc, _mem_type, addr, size, _value| {
+                uc.get_data_mut().record_shared_write(addr, size);
+                true
+            },
+        )?;
+    }
 
-    debug!(
-        "  Mapping GPU MMIO region {:#X} - {:#X} (ARM11 only)",
-        GPU_MMIO_BASE, GPU_MMIO_END
-    );
-    emu.mmio_map(
-        GPU_MMIO_BASE as u64,
-        (GPU_MMIO_END - GPU_MMIO_BASE) as u64,
-        Some(mmio::gpu::read_handler),
-        Some(mmio::gpu::write_handler),
-    )
-    .expect("failed to map GPU MMIO region");
+    Ok(())
+}
 
-    debug!(
-        "  Mapping remaining MMIO region {:#X} - {:#X}",
-        GPU_MMIO_END, ARM11_MMIO_SPLIT
-    );
-    emu.mmio_map(
-        GPU_MMIO_END as u64,
-        (ARM11_MMIO_SPLIT - GPU_MMIO_END) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map remaining MMIO region");
+/// Whether `region` is RAM or ROM rather than MMIO
+///
+/// RAM and the bootrom are mapped with execute permission; MMIO handlers never are.
+fn is_memory(region: &MemRegion) -> bool {
+    region.perms & Prot::EXEC == Prot::EXEC
+}
 
-    debug!(
-        "  Mapping final MMIO region {:#X} - {:#X}",
-        MMIO_REGION2_BASE, MMIO_REGION2_END
-    );
-    emu.mmio_map(
-        MMIO_REGION2_BASE as u64,
-        (MMIO_REGION2_END - MMIO_REGION2_BASE) as u64,
-        Some(mmio::generic::read_handler),
-        Some(mmio::generic::write_handler),
-    )
-    .expect("failed to map final MMIO region");
+/// Executable parts of this core's memory map, as inclusive (begin, end) ranges
+/// sorted by address
+///
+/// These are the RAM and ROM regions (see [`is_memory`]), limited to the `allowed`
+/// ranges (start, end exclusive) if any are given. Empty allowed ranges allow
+/// nothing.
+fn executable_ranges(regions: &[MemRegion], allowed: &[(u32, u32)]) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<_> = regions
+        .iter()
+        .filter(|region| is_memory(region))
+        .flat_map(|region| {
+            if allowed.is_empty() {
+                return vec![(region.begin, region.end)];
+            }
+            allowed
+                .iter()
+                .filter(|&&(start, end)| start < end)
+                .map(|&(start, end)| {
+                    (
+                        region.begin.max(start as u64),
+                        region.end.min(end as u64 - 1),
+                    )
+                })
+                .filter(|(begin, end)| begin <= end)
+                .collect()
+        })
+        .collect();
+    ranges.sort();
+    ranges
+}
+
+/// Whether an instruction at `addr` may be executed: it lies in memory mapped
+/// executable and, if `allowed` ranges are given, within one of them
+pub fn is_executable(regions: &[MemRegion], allowed: &[(u32, u32)], addr: u64) -> bool {
+    executable_ranges(regions, allowed)
+        .iter()
+        .any(|&(begin, end)| begin <= addr && addr <= end)
+}
+
+/// Parts of the 32-bit address space outside executable memory (see
+/// [`is_executable`]), as inclusive (begin, end) ranges for placing hooks
+pub fn non_executable_ranges(regions: &[MemRegion], allowed: &[(u32, u32)]) -> Vec<(u64, u64)> {
+    let mut gaps = Vec::new();
+    let mut cursor = 0u64;
+    for (begin, end) in executable_ranges(regions, allowed) {
+        if cursor < begin {
+            gaps.push((cursor, begin - 1));
+        }
+        cursor = cursor.max(end + 1);
+    }
+    if cursor <= u32::MAX as u64 {
+        gaps.push((cursor, u32::MAX as u64));
+    }
+    gaps
+}
+
+/// Check if `[start, end)` lies entirely within RAM or ROM mapped on this core
+fn is_mapped(regions: &[MemRegion], start: u64, end: u64) -> bool {
+    let mut addr = start;
+    while addr < end {
+        match regions
+            .iter()
+            .filter(|region| is_memory(region))
+            .find(|region| region.begin <= addr && addr <= region.end)
+        {
+            Some(region) => addr = region.end + 1,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// The first MMIO region on this core that overlaps `[start, end)`, if any
+fn overlapping_mmio(regions: &[MemRegion], start: u64, end: u64) -> Option<&MemRegion> {
+    regions
+        .iter()
+        .filter(|region| !is_memory(region))
+        .find(|region| region.begin < end && start <= region.end)
 }
 
-/// Check if an address is in ARM9-specific memory
-pub fn is_arm9_memory(addr: u32) -> bool {
-    // ARM9 internal memory
-    (ARM9_ITCM_BASE..(ARM9_ITCM_BASE + ARM9_ITCM_SIZE as u32)).contains(&addr)
+/// Whether section `index` should be loaded on the core with memory map `regions`:
+/// its whole address range is RAM or ROM there
+///
+/// Fails if the section overlaps MMIO, which no core can load data into.
+fn section_loads_on(
+    regions: &[MemRegion],
+    index: usize,
+    section: &FirmSectionHeader,
+) -> Result<bool, String> {
+    let start = section.load_address as u64;
+    let end = start + section.size as u64;
+    if let Some(mmio) = overlapping_mmio(regions, start, end) {
+        return Err(format!(
+            "section {} ({:#X}-{:#X}) overlaps MMIO at {:#X}-{:#X}",
+            index, start, end, mmio.begin, mmio.end
+        ));
+    }
+    Ok(is_mapped(regions, start, end))
+}
+
+/// Report FIRM sections whose load address ranges overlap
+///
+/// Each overlapping pair is logged as a warning, since the later section overwrites
+/// part of the earlier one. With `reject` set, the first overlap is an error instead.
+pub fn check_section_overlaps(firm: &FirmHeader, reject: bool) -> Result<(), String> {
+    for (i, j) in firm.overlapping_sections() {
+        let (a, b) = (&firm.sections[i], &firm.sections[j]);
+        let message = format!(
+            "sections {} ({:#X}-{:#X}) and {} ({:#X}-{:#X}) overlap",
+            i,
+            a.load_address,
+            a.load_address as u64 + a.size as u64,
+            j,
+            b.load_address,
+            b.load_address as u64 + b.size as u64
+        );
+        if reject {
+            return Err(message);
+        }
+        warn!(
+            "FIRM {}; section {} overwrites part of section {}",
+            message, j, i
+        );
+    }
+    Ok(())
 }
 
 /// Load FIRM sections into emulator
+///
+/// A section is loaded if its whole address range is RAM or ROM on this core. Sections
+/// in shared memory (FCRAM, AXI WRAM, VRAM) are written through both cores, which is
+/// harmless since both map the same backing buffers. Returns the indices of the
+/// sections that were loaded, so the caller can report sections no core maps.
+///
+/// Fails if a section overlaps MMIO on this core, or its data extends past the end
+/// of `firm_data`.
 pub fn load_sections(
     emu: &mut Unicorn<mmio::EmulatorState>,
-    sections: &[FirmSectionHeader],
+    firm: &FirmHeader,
     firm_data: &[u8],
-    is_arm9: bool,
-) {
-    for (i, section) in sections.iter().enumerate() {
-        if section.size == 0 {
-            continue;
-        }
+    core: Core,
+) -> Result<Vec<usize>, String> {
+    let regions = emu.mem_regions().unwrap_or_default();
 
+    let mut loaded = Vec::new();
+    for (i, section) in firm.loadable_sections() {
         let addr = section.load_address;
 
-        // Skip ARM9-specific sections if this is ARM11, and vice versa
-        if is_arm9_memory(addr) != is_arm9 {
+        if !section_loads_on(&regions, i, section)? {
             debug!(
-                "  Section {}: addr={:#X}, size={:#X} - skipping (wrong processor)",
-                i, addr, section.size
+                "  Section {}: addr={:#X}, size={:#X} - skipping (not mapped on {:?})",
+                i, addr, section.size, core
             );
             continue;
         }
@@ -336,12 +642,171 @@ pub fn load_sections(
             i, addr, section.size, section.offset
         );
 
+        let data = firm_data.get(section.file_range()).ok_or_else(|| {
+            format!(
+                "section {} data ({:#X} bytes at offset {:#X}) runs past the end of the {:#X} byte FIRM",
+                i,
+                section.size,
+                section.offset,
+                firm_data.len()
+            )
+        })?;
+
         // Copy section data - let Unicorn figure out which backing memory it goes to
-        let section_start = section.offset as usize;
-        let section_end = section_start + section.size as usize;
-        let section_data = &firm_data[section_start..section_end];
+        emu.mem_write(addr as u64, data)
+            .map_err(|e| format!("failed to write section {} data: {:?}", i, e))?;
+        loaded.push(i);
+    }
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(base: u32, size: u64, perms: Prot) -> MemRegion {
+        MemRegion {
+            begin: base as u64,
+            end: base as u64 + size - 1,
+            perms,
+        }
+    }
+
+    /// RAM and MMIO regions as `mem_regions` reports them for `core`, sorted by address
+    fn memory_map(core: Core) -> Vec<MemRegion> {
+        let mmio = Prot::READ | Prot::WRITE;
+        let mut regions = vec![
+            region(FCRAM_BASE, FCRAM_SIZE as u64, Prot::ALL),
+            region(AXI_WRAM_BASE, AXI_WRAM_SIZE as u64, Prot::ALL),
+            region(VRAM_BASE, VRAM_SIZE as u64, Prot::ALL),
+            region(
+                MMIO_REGION2_BASE,
+                (MMIO_REGION2_END - MMIO_REGION2_BASE) as u64,
+                mmio,
+            ),
+        ];
+        match core {
+            Core::Arm9 => regions.extend([
+                region(
+                    ARM9_PRIVATE_WRAM_BASE,
+                    ARM9_PRIVATE_WRAM_SIZE as u64,
+                    Prot::ALL,
+                ),
+                region(ARM9_ITCM_BASE, ARM9_ITCM_SIZE as u64, Prot::ALL),
+                region(
+                    MMIO_REGION1_BASE,
+                    (MMIO_REGION1_END - MMIO_REGION1_BASE) as u64,
+                    mmio,
+                ),
+            ]),
+            Core::Arm11 => regions.push(region(
+                MMIO_REGION1_BASE,
+                (ARM11_MMIO_SPLIT - MMIO_REGION1_BASE) as u64,
+                mmio,
+            )),
+        }
+        regions.sort_by_key(|region| region.begin);
+        regions
+    }
+
+    fn section(load_address: u32, size: u32) -> FirmSectionHeader {
+        FirmSectionHeader {
+            offset: 0x200,
+            load_address,
+            size,
+            copy_method: 2,
+            hash: [0; 32],
+        }
+    }
+
+    #[test]
+    fn arm9_fcram_section_loads_on_both_cores() {
+        let section = section(FCRAM_BASE + 0x10_0000, 0x1000);
+        assert_eq!(
+            section_loads_on(&memory_map(Core::Arm9), 0, &section),
+            Ok(true)
+        );
+        assert_eq!(
+            section_loads_on(&memory_map(Core::Arm11), 0, &section),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn arm9_private_sections_load_on_arm9_only() {
+        for section in [
+            section(ARM9_ITCM_BASE, 0x1000),
+            section(ARM9_PRIVATE_WRAM_BASE, 0x100),
+        ] {
+            assert_eq!(
+                section_loads_on(&memory_map(Core::Arm9), 0, &section),
+                Ok(true)
+            );
+            assert_eq!(
+                section_loads_on(&memory_map(Core::Arm11), 0, &section),
+                Ok(false)
+            );
+        }
+    }
+
+    #[test]
+    fn section_spanning_adjacent_ram_regions_is_mapped() {
+        // AXI WRAM ends where FCRAM begins
+        let section = section(FCRAM_BASE - 0x100, 0x200);
+        assert_eq!(
+            section_loads_on(&memory_map(Core::Arm11), 0, &section),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn unmapped_section_is_skipped() {
+        let section = section(0x0000_1000, 0x100);
+        assert_eq!(
+            section_loads_on(&memory_map(Core::Arm9), 0, &section),
+            Ok(false)
+        );
+        assert_eq!(
+            section_loads_on(&memory_map(Core::Arm11), 0, &section),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn section_overlapping_mmio_is_rejected() {
+        // Entirely inside MMIO
+        let inside = section(MMIO_REGION1_BASE + 0x1000, 0x100);
+        // Running off the end of VRAM into MMIO region 2
+        let straddling = section(MMIO_REGION2_BASE - 0x100, 0x200);
+        for section in [inside, straddling] {
+            for core in [Core::Arm9, Core::Arm11] {
+                let result = section_loads_on(&memory_map(core), 3, &section);
+                assert!(
+                    result.as_ref().is_err_and(|e| e.contains("overlaps MMIO")),
+                    "{:?} on {:?}: {:?}",
+                    section,
+                    core,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_exec_regions_allow_nothing() {
+        let regions = memory_map(Core::Arm9);
+        let base = ARM9_PRIVATE_WRAM_BASE;
+        for allowed in [[(base, base)], [(0, 0)]] {
+            assert!(!is_executable(&regions, &allowed, base as u64));
+            assert_eq!(
+                non_executable_ranges(&regions, &allowed),
+                vec![(0, u32::MAX as u64)]
+            );
+        }
 
-        emu.mem_write(addr as u64, section_data)
-            .expect("failed to write section data");
+        // Alongside a real range, an empty one changes nothing
+        let allowed = [(base, base + 0x100), (0, 0)];
+        assert!(is_executable(&regions, &allowed, base as u64 + 0xFC));
+        assert!(!is_executable(&regions, &allowed, base as u64 + 0x100));
     }
 }
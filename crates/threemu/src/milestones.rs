@@ -0,0 +1,103 @@
+//! Boot milestones for a quick "how far did boot get?" view.
+//!
+//! With `--verbose-boot`, the first time each recognizable boot step happens (FIRM
+//! parsed, sections loaded, a core starting, the first SDMMC command, the first
+//! framebuffer, the first VBlank) it is logged as a single `info` line, and the
+//! milestones never reached are listed when the run ends. Milestones are reported
+//! from wherever they happen (emulator setup, MMIO handlers, the frame loop), so the
+//! tracker is shared by both cores and safe to use from any of them.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use tracing::info;
+
+/// A recognizable step of the boot process, in the order boot normally reaches them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    FirmParsed,
+    SectionsLoaded,
+    Arm9Started,
+    Arm11Started,
+    FirstSdmmcCommand,
+    FramebufferConfigured,
+    FirstVBlank,
+}
+
+impl Milestone {
+    const ALL: [Milestone; 7] = [
+        Milestone::FirmParsed,
+        Milestone::SectionsLoaded,
+        Milestone::Arm9Started,
+        Milestone::Arm11Started,
+        Milestone::FirstSdmmcCommand,
+        Milestone::FramebufferConfigured,
+        Milestone::FirstVBlank,
+    ];
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Milestone::FirmParsed => "FIRM parsed",
+            Milestone::SectionsLoaded => "sections loaded",
+            Milestone::Arm9Started => "ARM9 started",
+            Milestone::Arm11Started => "ARM11 started",
+            Milestone::FirstSdmmcCommand => "first SDMMC command",
+            Milestone::FramebufferConfigured => "framebuffer configured",
+            Milestone::FirstVBlank => "first VBlank",
+        }
+    }
+}
+
+/// Milestones reached so far, each reported once
+#[derive(Debug, Default)]
+pub struct BootMilestones {
+    /// Whether milestones are logged at all
+    enabled: bool,
+    /// Bitmask of reached milestones
+    reached: AtomicU32,
+}
+
+impl BootMilestones {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            reached: AtomicU32::new(0),
+        }
+    }
+
+    /// Log `milestone` the first time it is reached, followed by `detail()`
+    ///
+    /// `detail` is only evaluated when the milestone is logged.
+    pub fn reach(&self, milestone: Milestone, detail: impl FnOnce() -> String) {
+        if !self.enabled || self.has_reached(milestone) {
+            return;
+        }
+        if self.reached.fetch_or(milestone.bit(), Ordering::Relaxed) & milestone.bit() == 0 {
+            info!("[boot] {}: {}", milestone.description(), detail());
+        }
+    }
+
+    /// Whether `milestone` has been reached (always false while disabled)
+    pub fn has_reached(&self, milestone: Milestone) -> bool {
+        self.reached.load(Ordering::Relaxed) & milestone.bit() != 0
+    }
+
+    /// Log the milestones that were never reached
+    pub fn report(&self) {
+        if !self.enabled {
+            return;
+        }
+        let missing: Vec<_> = Milestone::ALL
+            .into_iter()
+            .filter(|&milestone| !self.has_reached(milestone))
+            .map(Milestone::description)
+            .collect();
+        if missing.is_empty() {
+            info!("[boot] all milestones reached");
+        } else {
+            info!("[boot] not reached: {}", missing.join(", "));
+        }
+    }
+}
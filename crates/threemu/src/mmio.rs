@@ -7,33 +7,202 @@
 //! # Memory Map
 //! According to [3DBrew IO Registers](https://www.3dbrew.org/wiki/IO_Registers):
 //! - `0x10000000-0x10400000`: Generic MMIO (both ARM9 and ARM11)
+//! - `0x10000000-0x10001000`: CFG9 (ARM9 only)
+//! - `0x10140000-0x10142000`: CONFIG11 (ARM11 only)
+//! - `0x10161000`: I2C bus 0 (both ARM9 and ARM11)
+//! - `0x10144000`, `0x10148000`: I2C buses 1 and 2 (ARM11 only)
+//! - `0x10146000-0x10147000`: HID (both ARM9 and ARM11)
+//! - `0x10203000-0x10204000`: DSP (ARM11 only)
 //! - `0x10400000-0x10500000`: GPU registers (ARM11 only)
 //! - `0x10500000-0x18000000`: Additional MMIO regions
+//! - `0x17E00000-0x17E02000`: MPCore private region, including the GIC (ARM11 only)
 //! - `0x18000000-0x18600000`: VRAM (6MB, both ARM9 and ARM11)
 //! - `0x18600000-0x1FF80000`: More MMIO regions
 
-use std::path::PathBuf;
+use crate::cpu_types::Core;
+use crate::milestones::BootMilestones;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use tracing::error;
+use unicorn_engine::{RegisterARM, Unicorn};
 
+pub mod access;
+pub mod cfg9;
+pub mod config11;
+pub mod dsp;
 pub mod generic;
+pub mod gic;
 pub mod gpu;
+pub mod hid;
+pub mod i2c;
+pub mod replay;
 pub mod sdmmc;
+pub mod unimplemented;
 
 // Re-export types for convenience
+pub use cfg9::Cfg9State;
+pub use config11::Config11State;
+pub use dsp::DspState;
+pub use generic::{MmioAccess, MmioLogger};
+pub use gic::GicState;
 pub use gpu::{GpuState, PixelFormat};
+pub use hid::{Button, HidState};
+pub use i2c::I2cState;
+pub use replay::{MmioReplay, MmioWrite, MmioWriteLog};
 pub use sdmmc::SdmmcState;
+pub use unimplemented::{UnimplementedAccess, UnimplementedMmio};
 
-/// Shared emulator state accessible from MMIO callbacks and main loop
+/// Per-core emulator state accessible from MMIO callbacks and main loop
+///
+/// Each core owns its own `EmulatorState`, and handlers can tell which core they're
+/// serving from [`EmulatorState::core`]. Peripherals that are reachable from both
+/// cores (such as SDMMC, I2C bus 0, and HID) are held behind a shared handle so that
+/// ARM9 and ARM11 observe the same device state. CONFIG11 and I2C buses 1 and 2 are
+/// only mapped on ARM11.
 #[derive(Debug)]
 pub struct EmulatorState {
+    /// Core this state belongs to, set once at construction
+    pub core: Core,
+
+    pub cfg9: Cfg9State,
+    pub gic: GicState,
     pub gpu: GpuState,
-    pub sdmmc: SdmmcState,
+    pub dsp: DspState,
+    pub sdmmc: Arc<Mutex<SdmmcState>>,
+    pub i2c: Arc<Mutex<I2cState>>,
+    pub config11: Arc<Mutex<Config11State>>,
+    pub hid: Arc<Mutex<HidState>>,
+
+    /// Range of shared memory (start, end) written by this core since the other
+    /// core's translation cache was last synchronized
+    pub shared_writes: Option<(u64, u64)>,
+
+    /// Devices with dedicated MMIO handlers mapped on this core, recorded by the
+    /// memory setup
+    pub mmio_devices: Vec<&'static str>,
+
+    /// Log of generic MMIO accesses, when enabled
+    pub mmio_log: Option<MmioLogger>,
+
+    /// Log of every MMIO write, shared by both cores, when recording or verifying
+    pub mmio_writes: Option<Arc<Mutex<MmioWriteLog>>>,
+
+    /// Instructions this core has executed, counted only while `mmio_writes` is set
+    pub instruction_count: u64,
+
+    /// Generic MMIO accesses made by this core (all of which are unimplemented)
+    pub unimplemented: UnimplementedMmio,
+
+    /// Cycles spent in bootrom waits since the scheduler last charged them to this
+    /// core's cycle clock (they don't count as executed instructions)
+    pub waited_cycles: u64,
+
+    /// Boot progress tracker, shared by both cores
+    pub milestones: Arc<BootMilestones>,
+
+    /// Set while the host (e.g. a debugger) reads memory through this core, so
+    /// device handlers can skip the side effects of guest reads
+    pub host_access: AtomicBool,
 }
 
 impl EmulatorState {
-    pub fn new(sd_card_path: Option<PathBuf>) -> Self {
+    pub fn new(
+        core: Core,
+        sdmmc: Arc<Mutex<SdmmcState>>,
+        i2c: Arc<Mutex<I2cState>>,
+        config11: Arc<Mutex<Config11State>>,
+        hid: Arc<Mutex<HidState>>,
+    ) -> Self {
         Self {
+            core,
+            cfg9: Cfg9State::new(),
+            gic: GicState::new(),
             gpu: GpuState::new(),
-            sdmmc: SdmmcState::new(sd_card_path),
+            dsp: DspState::new(),
+            sdmmc,
+            i2c,
+            config11,
+            hid,
+            shared_writes: None,
+            mmio_devices: Vec::new(),
+            mmio_log: None,
+            mmio_writes: None,
+            instruction_count: 0,
+            unimplemented: UnimplementedMmio::default(),
+            waited_cycles: 0,
+            milestones: Arc::default(),
+            host_access: AtomicBool::new(false),
         }
     }
+
+    /// Extend the pending shared write range to cover `addr..addr + size`
+    pub fn record_shared_write(&mut self, addr: u64, size: usize) {
+        let end = addr + size as u64;
+        self.shared_writes = Some(match self.shared_writes {
+            Some((start, prev_end)) => (start.min(addr), prev_end.max(end)),
+            None => (addr, end),
+        });
+    }
+}
+
+/// PC of the instruction making the current MMIO access, formatted for span fields
+pub fn access_pc(uc: &Unicorn<'_, EmulatorState>) -> String {
+    match uc.reg_read(RegisterARM::PC) {
+        Ok(pc) => format!("{:#X}", pc),
+        Err(_) => "?".to_string(),
+    }
+}
+
+/// Add a write to the MMIO write log, if one is kept
+///
+/// `addr` is the absolute address written.
+pub fn record_write(uc: &Unicorn<'_, EmulatorState>, addr: u64, size: usize, value: u64) {
+    let state = uc.get_data();
+    let Some(log) = &state.mmio_writes else {
+        return;
+    };
+    let pc = uc.reg_read(RegisterARM::PC).unwrap_or(0) as u32;
+    log.lock()
+        .expect("MMIO write log lock poisoned")
+        .record(MmioWrite {
+            core: state.core,
+            instruction: state.instruction_count,
+            pc,
+            addr: addr as u32,
+            size,
+            value,
+        });
+}
+
+/// Read handler for an MMIO range mapped at runtime with `EmulatorCore::map_mmio`
+///
+/// Receives the offset from the start of the range and the access size, and returns
+/// the value read.
+pub type CustomReadHandler = Box<dyn FnMut(&mut Unicorn<'_, EmulatorState>, u64, usize) -> u64>;
+
+/// Write handler for an MMIO range mapped at runtime with `EmulatorCore::map_mmio`
+///
+/// Receives the offset from the start of the range, the access size, and the value
+/// written.
+pub type CustomWriteHandler = Box<dyn FnMut(&mut Unicorn<'_, EmulatorState>, u64, usize, u64)>;
+
+/// Flag an access to `device` from a core it isn't wired to on hardware
+///
+/// `cores` are the cores the device is reachable from, as declared by its handler
+/// module rather than taken from the memory map, so an access from any other core
+/// means the memory setup mapped the device where it doesn't belong (e.g. an
+/// ARM11-only device reachable from ARM9). It's reported as an error rather than
+/// handled.
+pub fn check_device_core(uc: &Unicorn<'_, EmulatorState>, device: &str, cores: &[Core]) {
+    let state = uc.get_data();
+    if !cores.contains(&state.core) {
+        error!(
+            "{} register access from {:?} at PC {}, but {} is only reachable from {:?}",
+            device,
+            state.core,
+            access_pc(uc),
+            device,
+            cores
+        );
+    }
 }
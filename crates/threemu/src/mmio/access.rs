@@ -0,0 +1,101 @@
+//! Splitting MMIO accesses into per-register pieces.
+//!
+//! CPU accesses don't have to match the width of the registers they touch: a byte
+//! write can land in the upper half of a 16-bit register, and a word access can span
+//! two of them. [`split_access`] breaks an access into the parts that fall into each
+//! register so device handlers can apply them one register at a time.
+
+/// The part of an MMIO access that lands in a single register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterSlice {
+    /// Offset of the register
+    pub offset: u32,
+    /// Register width in bytes
+    pub width: u32,
+    /// First byte of the register covered by the access
+    pub start: u32,
+    /// Number of register bytes covered by the access
+    pub len: u32,
+    /// Byte position of the covered bytes within the access value
+    pub value_pos: u32,
+}
+
+impl RegisterSlice {
+    /// Whether the access covers the whole register
+    pub fn is_full(&self) -> bool {
+        self.start == 0 && self.len == self.width
+    }
+
+    /// Mask of the covered bits, in register position
+    fn mask(&self) -> u32 {
+        (u32::MAX >> (32 - self.len * 8)) << (self.start * 8)
+    }
+
+    /// Bytes of `access_value` that land in this register, in the low bits
+    pub fn bits(&self, access_value: u32) -> u32 {
+        (access_value >> (self.value_pos * 8)) & (u32::MAX >> (32 - self.len * 8))
+    }
+
+    /// Replace the covered bytes of `current` with those from `access_value`
+    pub fn merge(&self, current: u32, access_value: u32) -> u32 {
+        (current & !self.mask()) | (self.bits(access_value) << (self.start * 8))
+    }
+
+    /// Move the covered bytes of `register_value` to their place in the access value
+    pub fn extract(&self, register_value: u32) -> u32 {
+        ((register_value & self.mask()) >> (self.start * 8)) << (self.value_pos * 8)
+    }
+}
+
+/// Split an access of `size` bytes at `offset` into the registers it touches
+///
+/// `register_width` gives the width in bytes (a power of two) of the register
+/// containing a given offset. Registers are assumed to be naturally aligned.
+pub fn split_access(
+    offset: u32,
+    size: usize,
+    register_width: impl Fn(u32) -> u32,
+) -> impl Iterator<Item = RegisterSlice> {
+    let end = offset + size as u32;
+    let mut pos = offset;
+    std::iter::from_fn(move || {
+        if pos >= end {
+            return None;
+        }
+        let width = register_width(pos);
+        let register = pos & !(width - 1);
+        let len = (register + width).min(end) - pos;
+        let slice = RegisterSlice {
+            offset: register,
+            width,
+            start: pos - register,
+            len,
+            value_pos: pos - offset,
+        };
+        pos += len;
+        Some(slice)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accesses_split_at_register_boundaries() {
+        let slices: Vec<_> = split_access(0x05, 4, |_| 2).collect();
+        let covered: Vec<_> = slices
+            .iter()
+            .map(|s| (s.offset, s.start, s.len, s.value_pos))
+            .collect();
+        assert_eq!(covered, [(0x04, 1, 1, 0), (0x06, 0, 2, 1), (0x08, 0, 1, 3)]);
+
+        // The middle register gets bytes 1-2 of the value, the others one byte each
+        assert_eq!(slices[0].merge(0x1111, 0xAABBCCDD), 0xDD11);
+        assert_eq!(slices[1].bits(0xAABBCCDD), 0xBBCC);
+        assert_eq!(slices[2].merge(0x1111, 0xAABBCCDD), 0x11AA);
+        assert!(slices[1].is_full() && !slices[0].is_full());
+        assert_eq!(slices[0].extract(0x12_34), 0x12);
+        assert_eq!(slices[2].extract(0x56_78), 0x78 << 24);
+    }
+}
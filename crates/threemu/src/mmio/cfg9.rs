@@ -0,0 +1,144 @@
+//! CFG9 register handling for 3DS emulation.
+//!
+//! CFG9 (0x10000000-0x10001000, ARM9 only) holds the ARM9's system configuration:
+//! bootrom/OTP protection, ARM11 reset, gamecard and SD/MMC controller routing, and
+//! the New 3DS extended ARM9 memory switch. Boot code writes these early and some
+//! of it reads the values back, so the block is modeled as a register file that
+//! returns what was written, with a few exceptions:
+//!
+//! - `SYSPROT9`/`SYSPROT11` bits can be set but never cleared again.
+//! - `MPCORECFG` is read-only and reports an Old 3DS.
+//! - `EXTMEMCNT9` is stored, but the extended memory is never mapped, as on an
+//!   Old 3DS.
+//!
+//! # References
+//! - [CONFIG9 Registers](https://www.3dbrew.org/wiki/CONFIG9_Registers)
+
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::mmio::cfg9::{BASE, END, extmemcnt9, registers as hw_regs};
+use tracing::{debug, instrument, trace};
+use unicorn_engine::Unicorn;
+
+/// Cores the CFG9 registers are reachable from
+const CORES: &[Core] = &[Core::Arm9];
+
+/// Size of the CFG9 register block
+const SIZE: usize = (END - BASE) as usize;
+
+/// CFG9 register file
+#[derive(Debug)]
+pub struct Cfg9State {
+    /// Backing store for all registers (little-endian)
+    regs: Box<[u8; SIZE]>,
+}
+
+impl Cfg9State {
+    #[expect(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            regs: Box::new([0u8; SIZE]),
+        }
+    }
+
+    /// Handle a write to a CFG9 register
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        trace!(
+            "CFG9 register write: offset={:#X}, size={}, value={:#X}",
+            offset, size, value
+        );
+
+        let start = offset as usize;
+        let Some(bytes) = self.regs.get_mut(start..start + size) else {
+            return;
+        };
+        for (i, (byte, new)) in bytes.iter_mut().zip(value.to_le_bytes()).enumerate() {
+            let register = offset + i as u32;
+            *byte = match register {
+                hw_regs::SYSPROT9 | hw_regs::SYSPROT11 => {
+                    if new & !*byte != 0 {
+                        debug!(
+                            "CFG9 protection {:#X}: {:#X} -> {:#X}",
+                            register,
+                            *byte,
+                            *byte | new
+                        );
+                    }
+                    *byte | new
+                }
+                r if (hw_regs::MPCORECFG..hw_regs::MPCORECFG + 4).contains(&r) => {
+                    trace!("Ignoring write to read-only CFG9 register {:#X}", r);
+                    *byte
+                }
+                _ => new,
+            };
+        }
+
+        if (offset..offset + size as u32).contains(&hw_regs::EXTMEMCNT9) {
+            debug!(
+                "CFG9 ARM9 extended memory {} (not present on Old 3DS, ignored)",
+                if self.word(hw_regs::EXTMEMCNT9) & extmemcnt9::ENABLE != 0 {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+        }
+    }
+
+    /// Handle a read from a CFG9 register
+    pub fn read(&self, offset: u32, size: usize) -> u32 {
+        trace!("CFG9 register read: offset={:#X}, size={}", offset, size);
+
+        let start = offset as usize;
+        let Some(bytes) = self.regs.get(start..start + size) else {
+            return 0;
+        };
+        let mut value = [0u8; 4];
+        value[..size].copy_from_slice(bytes);
+        u32::from_le_bytes(value)
+    }
+
+    /// Current value of the 32-bit register at `offset`
+    fn word(&self, offset: u32) -> u32 {
+        self.read(offset, 4)
+    }
+}
+
+// ============================================================================
+// Unicorn MMIO Adapters
+// ============================================================================
+
+/// MMIO read handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses to the u32
+/// offsets expected by the CFG9 handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+    super::check_device_core(uc, "CFG9", CORES);
+    uc.get_data().cfg9.read(addr as u32, size) as u64
+}
+
+/// MMIO write handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
+/// types expected by the CFG9 handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn write_handler(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+) {
+    super::check_device_core(uc, "CFG9", CORES);
+    uc.get_data_mut()
+        .cfg9
+        .write(addr as u32, size, value as u32);
+}
@@ -0,0 +1,231 @@
+//! CONFIG11 register handling for 3DS emulation.
+//!
+//! CONFIG11 (0x10140000-0x10142000) holds system configuration registers, including
+//! the MPCore boot control used to release the other ARM11 cores from reset. The
+//! block is only mapped on ARM11; the boot handshake runs between the MPCore cores,
+//! which all see the same register file.
+//!
+//! `MPCORE_BOOTCNT` (0x1310, one byte per core) is the only register with special
+//! behavior: once bit 0 (release) has been written for a core, reads report bit 4
+//! (running).
+//!
+//! # Shared WRAM Banks
+//! The 512KB of shared (DSP) WRAM at 0x1FF00000 is split into a code half
+//! (0x1FF00000-0x1FF40000) and a data half (0x1FF40000-0x1FF80000), each made of
+//! eight 32KB blocks. `SHAREDWRAM_32K_CODE` (0x00-0x07) and `SHAREDWRAM_32K_DATA`
+//! (0x08-0x0F) hold one byte per block:
+//!
+//! - bit 0: master, 0 = ARM9/ARM11, 1 = DSP
+//! - bits 2-4: slot (0-7) the block appears at within its half
+//! - bit 7: enable
+//!
+//! The values read back as written, and changes are logged decoded (see
+//! [`SharedWramBlock`]), but the mapping itself isn't applied: the shared WRAM range
+//! is generic MMIO on both cores regardless of the configuration.
+//!
+//! # References
+//! - [CONFIG11 Registers](https://www.3dbrew.org/wiki/CONFIG11_Registers)
+
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::mmio::config11::{BASE, END, bootcnt, registers as hw_regs, sharedwram};
+use tracing::{debug, instrument, trace};
+use unicorn_engine::Unicorn;
+
+/// Cores the CONFIG11 registers are reachable from
+const CORES: &[Core] = &[Core::Arm11];
+
+/// Size of the CONFIG11 register block
+const SIZE: usize = (END - BASE) as usize;
+
+/// Number of MPCore cores with a boot control byte
+const NUM_MPCORE_CORES: u32 = 4;
+
+/// Half of shared WRAM a mapping byte belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharedWramHalf {
+    Code,
+    Data,
+}
+
+/// Decoded mapping of one 32KB shared WRAM block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedWramBlock {
+    pub half: SharedWramHalf,
+    /// Block number within its half (0-7)
+    pub block: u32,
+    /// Mapped to the DSP rather than the ARM9/ARM11
+    pub dsp: bool,
+    /// Slot within its half the block appears at (0-7)
+    pub slot: u32,
+    pub enabled: bool,
+}
+
+impl SharedWramBlock {
+    /// Decode the mapping byte of `block` in `half`
+    pub fn decode(half: SharedWramHalf, block: u32, value: u8) -> Self {
+        Self {
+            half,
+            block,
+            dsp: value & sharedwram::MASTER_DSP != 0,
+            slot: ((value >> sharedwram::SLOT_SHIFT) & sharedwram::SLOT_MASK) as u32,
+            enabled: value & sharedwram::ENABLE != 0,
+        }
+    }
+
+    /// Address the block appears at when enabled
+    pub fn address(&self) -> u32 {
+        let base = match self.half {
+            SharedWramHalf::Code => sharedwram::CODE_BASE,
+            SharedWramHalf::Data => sharedwram::DATA_BASE,
+        };
+        base + self.slot * sharedwram::BLOCK_SIZE
+    }
+}
+
+/// CONFIG11 register file, reachable from ARM11 only
+#[derive(Debug)]
+pub struct Config11State {
+    /// Backing store for all registers (little-endian)
+    regs: Box<[u8; SIZE]>,
+}
+
+impl Config11State {
+    #[expect(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            regs: Box::new([0u8; SIZE]),
+        }
+    }
+
+    /// Handle a write to a CONFIG11 register
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        trace!(
+            "CONFIG11 register write: offset={:#X}, size={}, value={:#X}",
+            offset, size, value
+        );
+
+        let start = offset as usize;
+        let Some(bytes) = self.regs.get_mut(start..start + size) else {
+            return;
+        };
+        bytes.copy_from_slice(&value.to_le_bytes()[..size]);
+
+        for (half, index) in Self::shared_wram_blocks(offset, size) {
+            let block = self.shared_wram_block(half, index);
+            debug!(
+                "CONFIG11 shared WRAM {:?} block {}: {} at {:#X} ({})",
+                block.half,
+                block.block,
+                if block.enabled { "mapped" } else { "unmapped" },
+                block.address(),
+                if block.dsp { "DSP" } else { "ARM9/ARM11" }
+            );
+        }
+
+        for core in Self::bootcnt_cores(offset, size) {
+            debug!(
+                "CONFIG11 MPCore core {} boot control: {:#X}",
+                core,
+                self.regs[(hw_regs::MPCORE_BOOTCNT + core) as usize]
+            );
+        }
+    }
+
+    /// Handle a read from a CONFIG11 register
+    pub fn read(&self, offset: u32, size: usize) -> u32 {
+        trace!(
+            "CONFIG11 register read: offset={:#X}, size={}",
+            offset, size
+        );
+
+        let start = offset as usize;
+        let Some(bytes) = self.regs.get(start..start + size) else {
+            return 0;
+        };
+        let mut value = [0u8; 4];
+        value[..size].copy_from_slice(bytes);
+
+        // Released cores report that they are running
+        for core in Self::bootcnt_cores(offset, size) {
+            let index = (hw_regs::MPCORE_BOOTCNT + core - offset) as usize;
+            if value[index] & bootcnt::RELEASE != 0 {
+                value[index] |= bootcnt::RUNNING;
+            }
+        }
+
+        u32::from_le_bytes(value)
+    }
+
+    /// Current mapping of shared WRAM `block` (0-7) in `half`
+    pub fn shared_wram_block(&self, half: SharedWramHalf, block: u32) -> SharedWramBlock {
+        let base = match half {
+            SharedWramHalf::Code => hw_regs::SHAREDWRAM_32K_CODE,
+            SharedWramHalf::Data => hw_regs::SHAREDWRAM_32K_DATA,
+        };
+        SharedWramBlock::decode(half, block, self.regs[(base + block) as usize])
+    }
+
+    /// Shared WRAM blocks whose mapping byte lies inside `offset..offset + size`
+    fn shared_wram_blocks(offset: u32, size: usize) -> impl Iterator<Item = (SharedWramHalf, u32)> {
+        [
+            (SharedWramHalf::Code, hw_regs::SHAREDWRAM_32K_CODE),
+            (SharedWramHalf::Data, hw_regs::SHAREDWRAM_32K_DATA),
+        ]
+        .into_iter()
+        .flat_map(|(half, base)| (0..sharedwram::BLOCKS).map(move |block| (half, base, block)))
+        .filter(move |(_, base, block)| (offset..offset + size as u32).contains(&(base + block)))
+        .map(|(half, _, block)| (half, block))
+    }
+
+    /// MPCore cores whose boot control byte lies inside `offset..offset + size`
+    fn bootcnt_cores(offset: u32, size: usize) -> impl Iterator<Item = u32> {
+        (0..NUM_MPCORE_CORES).filter(move |core| {
+            (offset..offset + size as u32).contains(&(hw_regs::MPCORE_BOOTCNT + core))
+        })
+    }
+}
+
+// ============================================================================
+// Unicorn MMIO Adapters
+// ============================================================================
+
+/// MMIO read handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses to the u32
+/// offsets expected by the CONFIG11 handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+    super::check_device_core(uc, "CONFIG11", CORES);
+    uc.get_data()
+        .config11
+        .lock()
+        .expect("CONFIG11 state lock poisoned")
+        .read(addr as u32, size) as u64
+}
+
+/// MMIO write handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
+/// types expected by the CONFIG11 handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn write_handler(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+) {
+    super::check_device_core(uc, "CONFIG11", CORES);
+    uc.get_data()
+        .config11
+        .lock()
+        .expect("CONFIG11 state lock poisoned")
+        .write(addr as u32, size, value as u32);
+}
@@ -0,0 +1,242 @@
+//! DSP register handling for 3DS emulation.
+//!
+//! The DSP register block (0x10203000-0x10204000, ARM11 only) is how the ARM11
+//! loads, resets, and talks to the Teak DSP. No DSP core is emulated; this module
+//! only models enough of the handshake for firmware waiting on DSP initialization
+//! to carry on:
+//!
+//! - Clearing `PCFG` bit 0 releases the DSP from reset. `PSTS` bit 2 reports the
+//!   reset state, and the emulated DSP "boots" immediately on release.
+//! - On boot the DSP writes 1 to each reply register (`REP0`-`REP2`), flagging them
+//!   as unread in `PSTS` bits 10-12. Reading a reply register clears its flag.
+//! - Commands written to `CMD0`-`CMD2` are consumed at once, so `PSTS` bits 13-15
+//!   (command not yet read by the DSP) never stay set.
+//! - `SEM` holds DSP-to-ARM11 semaphore flags, cleared through `PCLEAR`. `PSTS`
+//!   bit 9 is set while an unmasked `SEM` bit is set.
+//!
+//! Accesses to the data FIFO and transfer registers are recorded as unimplemented.
+//!
+//! # References
+//! - [DSP Registers](https://www.3dbrew.org/wiki/DSP_Registers)
+
+use super::UnimplementedMmio;
+use super::access::split_access;
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::mmio::dsp::{pcfg, psts, registers as hw_regs};
+use tracing::{debug, instrument, trace};
+use unicorn_engine::Unicorn;
+
+/// Cores the DSP registers are reachable from
+const CORES: &[Core] = &[Core::Arm11];
+
+/// Value the DSP writes to each reply register once it has booted
+const BOOT_REPLY: u16 = 1;
+
+/// DSP register state as seen by the ARM11
+#[derive(Debug)]
+pub struct DspState {
+    pub pcfg: u16,
+    pub psem: u16,
+    pub pmask: u16,
+    pub sem: u16,
+    pub cmd: [u16; hw_regs::NUM_CHANNELS as usize],
+    pub rep: [u16; hw_regs::NUM_CHANNELS as usize],
+
+    /// Reply registers the ARM11 hasn't read since the DSP wrote them (bit n = `REPn`)
+    rep_unread: u16,
+
+    /// Accesses to registers this emulation doesn't implement
+    pub unimplemented: UnimplementedMmio,
+}
+
+impl DspState {
+    #[expect(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            // The DSP comes out of power-on held in reset
+            pcfg: pcfg::RESET,
+            psem: 0,
+            pmask: 0,
+            sem: 0,
+            cmd: [0; hw_regs::NUM_CHANNELS as usize],
+            rep: [0; hw_regs::NUM_CHANNELS as usize],
+            rep_unread: 0,
+            unimplemented: UnimplementedMmio::default(),
+        }
+    }
+
+    /// Whether the DSP is held in reset
+    pub fn in_reset(&self) -> bool {
+        self.pcfg & pcfg::RESET != 0
+    }
+
+    /// Handle a write of `size` bytes at `offset`
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        for slice in split_access(offset, size, |_| 2) {
+            let value = if slice.is_full() {
+                slice.bits(value)
+            } else {
+                slice.merge(self.register_value(slice.offset).unwrap_or(0) as u32, value)
+            };
+            self.write_register(slice.offset, value as u16);
+        }
+    }
+
+    /// Handle a read of `size` bytes at `offset`
+    pub fn read(&mut self, offset: u32, size: usize) -> u32 {
+        split_access(offset, size, |_| 2).fold(0, |value, slice| {
+            value | slice.extract(self.read_register(slice.offset) as u32)
+        })
+    }
+
+    /// Command/reply channel of a `CMDn` or `REPn` register at `offset`
+    fn channel(offset: u32, first: u32) -> Option<usize> {
+        let delta = offset.checked_sub(first)?;
+        let index = delta / hw_regs::CHANNEL_STRIDE;
+        (delta % hw_regs::CHANNEL_STRIDE == 0 && index < hw_regs::NUM_CHANNELS)
+            .then_some(index as usize)
+    }
+
+    fn write_register(&mut self, offset: u32, value: u16) {
+        trace!(
+            "DSP register write: offset={:#X}, value={:#X}",
+            offset, value
+        );
+
+        match offset {
+            hw_regs::PCFG => {
+                let was_in_reset = self.in_reset();
+                self.pcfg = value;
+                match (was_in_reset, self.in_reset()) {
+                    (true, false) => self.boot(),
+                    (false, true) => {
+                        debug!("DSP held in reset");
+                        self.rep_unread = 0;
+                        self.sem = 0;
+                    }
+                    _ => {}
+                }
+            }
+            hw_regs::PSEM => {
+                self.psem = value;
+                debug!("DSP ARM11-to-DSP semaphore: {:#X}", value);
+            }
+            hw_regs::PMASK => self.pmask = value,
+            hw_regs::PCLEAR => self.sem &= !value,
+            hw_regs::PSTS | hw_regs::SEM => {
+                trace!("Ignoring write to read-only DSP register {:#X}", offset);
+            }
+            _ => {
+                if let Some(channel) = Self::channel(offset, hw_regs::CMD0) {
+                    // The DSP picks up commands immediately
+                    self.cmd[channel] = value;
+                    debug!("DSP CMD{}: {:#X}", channel, value);
+                } else {
+                    debug!(
+                        "Unimplemented DSP register write: offset={:#X}, value={:#X}",
+                        offset, value
+                    );
+                    self.unimplemented.record("DSP", offset, true);
+                }
+            }
+        }
+    }
+
+    fn read_register(&mut self, offset: u32) -> u16 {
+        if let Some(channel) = Self::channel(offset, hw_regs::REP0) {
+            self.rep_unread &= !(1 << channel);
+            trace!("DSP REP{} read: {:#X}", channel, self.rep[channel]);
+            return self.rep[channel];
+        }
+
+        let value = self.register_value(offset).unwrap_or_else(|| {
+            debug!("Unimplemented DSP register read: offset={:#X}", offset);
+            self.unimplemented.record("DSP", offset, false);
+            0
+        });
+        trace!(
+            "DSP register read: offset={:#X}, value={:#X}",
+            offset, value
+        );
+        value
+    }
+
+    /// Current value of a register, without side effects
+    fn register_value(&self, offset: u32) -> Option<u16> {
+        let value = match offset {
+            hw_regs::PCFG => self.pcfg,
+            hw_regs::PSTS => self.status(),
+            hw_regs::PSEM => self.psem,
+            hw_regs::PMASK => self.pmask,
+            hw_regs::PCLEAR => 0,
+            hw_regs::SEM => self.sem,
+            _ => {
+                if let Some(channel) = Self::channel(offset, hw_regs::CMD0) {
+                    self.cmd[channel]
+                } else if let Some(channel) = Self::channel(offset, hw_regs::REP0) {
+                    self.rep[channel]
+                } else {
+                    return None;
+                }
+            }
+        };
+        Some(value)
+    }
+
+    /// Compose the `PSTS` register
+    fn status(&self) -> u16 {
+        let mut status = psts::WRITE_FIFO_EMPTY | (self.rep_unread << psts::REP_NEW_SHIFT);
+        if self.in_reset() {
+            status |= psts::PERIPHERAL_RESET;
+        }
+        if self.sem & !self.pmask != 0 {
+            status |= psts::SEM_IRQ;
+        }
+        status
+    }
+
+    /// Bring the DSP out of reset, posting the replies its firmware sends once
+    /// initialized
+    fn boot(&mut self) {
+        debug!("DSP released from reset, reporting ready");
+        self.rep = [BOOT_REPLY; hw_regs::NUM_CHANNELS as usize];
+        self.rep_unread = (1 << hw_regs::NUM_CHANNELS) - 1;
+    }
+}
+
+// ============================================================================
+// Unicorn MMIO Adapters
+// ============================================================================
+
+/// MMIO read handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses to the u32
+/// offsets expected by the DSP handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+    super::check_device_core(uc, "DSP", CORES);
+    uc.get_data_mut().dsp.read(addr as u32, size) as u64
+}
+
+/// MMIO write handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
+/// types expected by the DSP handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn write_handler(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+) {
+    super::check_device_core(uc, "DSP", CORES);
+    uc.get_data_mut().dsp.write(addr as u32, size, value as u32);
+}
@@ -5,17 +5,113 @@
 //!
 //! In a full emulator, these would be replaced with specific handlers for each
 //! hardware component (timers, DMA, interrupts, etc.).
+//!
+//! Accesses that fall inside the range configured on [`MmioLogger`] are recorded
+//! along with the PC of the accessing instruction, to help reverse-engineer which
+//! code touches an unimplemented peripheral.
+
+use tracing::{info, instrument, trace};
+use unicorn_engine::{RegisterARM, Unicorn};
+
+/// A single recorded MMIO access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmioAccess {
+    /// PC of the instruction performing the access
+    pub pc: u32,
+    /// Absolute address accessed
+    pub addr: u32,
+    /// Access size in bytes
+    pub size: usize,
+    /// Value read or written
+    pub value: u64,
+    pub is_write: bool,
+}
+
+/// Log of generic MMIO accesses within an address range
+#[derive(Debug, Clone)]
+pub struct MmioLogger {
+    /// Logged address range (start, end), end exclusive
+    pub range: (u32, u32),
+    pub entries: Vec<MmioAccess>,
+}
+
+impl MmioLogger {
+    pub fn new(range: (u32, u32)) -> Self {
+        Self {
+            range,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Record an access if it falls inside the logged range
+    pub fn record(&mut self, access: MmioAccess) {
+        if (self.range.0..self.range.1).contains(&access.addr) {
+            self.entries.push(access);
+        }
+    }
 
-use tracing::{instrument, trace};
-use unicorn_engine::Unicorn;
+    /// Print all recorded accesses, prefixed with `label`
+    pub fn dump(&self, label: &str) {
+        info!(
+            "{} MMIO log ({:#X} - {:#X}): {} accesses",
+            label,
+            self.range.0,
+            self.range.1,
+            self.entries.len()
+        );
+        for access in &self.entries {
+            info!(
+                "  pc={:#010X} {} addr={:#010X} size={} value={:#X}",
+                access.pc,
+                if access.is_write { "W" } else { "R" },
+                access.addr,
+                access.size,
+                access.value
+            );
+        }
+    }
+}
+
+/// Record an access as unimplemented, and on the core's MMIO logger if one is configured
+fn log_access(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+    is_write: bool,
+) {
+    uc.get_data_mut()
+        .unimplemented
+        .record("generic", addr as u32, is_write);
+
+    if uc.get_data().mmio_log.is_none() {
+        return;
+    }
+    let pc = uc.reg_read(RegisterARM::PC).unwrap_or(0) as u32;
+    if let Some(logger) = uc.get_data_mut().mmio_log.as_mut() {
+        logger.record(MmioAccess {
+            pc,
+            addr: addr as u32,
+            size,
+            value,
+            is_write,
+        });
+    }
+}
 
 /// Generic MMIO read handler - returns zero
 ///
 /// This is a placeholder for unimplemented MMIO regions.
 /// Real hardware would return specific values based on the register.
-#[instrument(level = "trace", skip(_uc))]
-pub fn read_handler(_uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+/// `addr` is the absolute address of the access.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
     trace!("Generic MMIO read: addr={:#X}, size={}", addr, size);
+    log_access(uc, addr, size, 0, false);
     0
 }
 
@@ -23,9 +119,14 @@ pub fn read_handler(_uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size
 ///
 /// This is a placeholder for unimplemented MMIO regions.
 /// Real hardware would perform specific actions based on the register.
-#[instrument(level = "trace", skip(_uc))]
+/// `addr` is the absolute address of the access.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
 pub fn write_handler(
-    _uc: &mut Unicorn<'_, super::EmulatorState>,
+    uc: &mut Unicorn<'_, super::EmulatorState>,
     addr: u64,
     size: usize,
     value: u64,
@@ -34,5 +135,6 @@ pub fn write_handler(
         "Generic MMIO write: addr={:#X}, size={}, value={:#X}",
         addr, size, value
     );
+    log_access(uc, addr, size, value, true);
     // Ignore writes
 }
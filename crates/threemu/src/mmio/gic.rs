@@ -0,0 +1,323 @@
+//! MPCore GIC (Generic Interrupt Controller) handling for 3DS emulation.
+//!
+//! This module implements a minimal version of the ARM11 MPCore interrupt
+//! controller. The MPCore private region is mapped at 0x17E00000-0x17E02000 and
+//! contains the GIC CPU interface (0x100) and distributor (0x1000).
+//!
+//! Only a single CPU interface is modeled. Interrupts are raised by other devices
+//! through [`GicState::raise_interrupt`], become visible through the acknowledge
+//! register once enabled, and are retired by writing their ID to the end of
+//! interrupt register.
+//!
+//! # References
+//! - [ARM11 Interrupts](https://www.3dbrew.org/wiki/ARM11_Interrupts)
+//! - [MPCore Private Memory Region](https://developer.arm.com/documentation/ddi0360/f/mpcore-private-memory-region)
+
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::mmio::gic::{NUM_INTERRUPTS, SPURIOUS_INTERRUPT_ID, registers as hw_regs};
+use tracing::{debug, instrument, trace};
+use unicorn_engine::Unicorn;
+
+/// Cores the GIC (MPCore private region) is reachable from
+const CORES: &[Core] = &[Core::Arm11];
+
+/// Number of 32-bit words needed to hold one bit per interrupt
+const BITMAP_WORDS: usize = NUM_INTERRUPTS / 32;
+
+/// Priority value reported when no interrupt is active (lowest possible priority)
+const IDLE_PRIORITY: u32 = 0xFF;
+
+/// GIC state tracking distributor and CPU interface registers
+#[derive(Debug)]
+pub struct GicState {
+    // CPU interface
+    pub cpu_control: u32,
+    pub priority_mask: u32,
+    pub binary_point: u32,
+
+    // Distributor
+    pub dist_control: u32,
+    pub enabled: [u32; BITMAP_WORDS],
+    pub pending: [u32; BITMAP_WORDS],
+    pub active: [u32; BITMAP_WORDS],
+    pub priority: [u8; NUM_INTERRUPTS],
+    pub target: [u8; NUM_INTERRUPTS],
+    pub config: [u32; NUM_INTERRUPTS / 16],
+}
+
+impl GicState {
+    #[expect(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            cpu_control: 0,
+            priority_mask: 0,
+            binary_point: 0,
+            dist_control: 0,
+            enabled: [0; BITMAP_WORDS],
+            pending: [0; BITMAP_WORDS],
+            active: [0; BITMAP_WORDS],
+            priority: [0; NUM_INTERRUPTS],
+            target: [0; NUM_INTERRUPTS],
+            config: [0; NUM_INTERRUPTS / 16],
+        }
+    }
+
+    /// Mark an interrupt as pending
+    ///
+    /// This is the entry point used by other devices to signal the ARM11.
+    pub fn raise_interrupt(&mut self, id: u32) {
+        if id as usize >= NUM_INTERRUPTS {
+            debug!("Ignoring out of range GIC interrupt raise: {}", id);
+            return;
+        }
+        trace!("GIC interrupt raised: {}", id);
+        Self::set_bit(&mut self.pending, id);
+    }
+
+    /// Check whether an interrupt would currently be delivered to the CPU
+    pub fn irq_asserted(&self) -> bool {
+        self.highest_pending().is_some()
+    }
+
+    /// Handle a write to a GIC register
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        trace!(
+            "GIC register write: offset={:#X}, value={:#X}",
+            offset, value
+        );
+
+        match offset {
+            hw_regs::CPU_CONTROL => {
+                self.cpu_control = value & 1;
+                debug!("GIC CPU interface control: {:#X}", self.cpu_control);
+            }
+            hw_regs::CPU_PRIORITY_MASK => {
+                self.priority_mask = value & 0xFF;
+                debug!("GIC priority mask: {:#X}", self.priority_mask);
+            }
+            hw_regs::CPU_BINARY_POINT => {
+                self.binary_point = value & 0x7;
+            }
+            hw_regs::CPU_END_OF_INTERRUPT => {
+                let id = value & 0x3FF;
+                if (id as usize) < NUM_INTERRUPTS {
+                    trace!("GIC end of interrupt: {}", id);
+                    Self::clear_bit(&mut self.active, id);
+                }
+            }
+            hw_regs::DIST_CONTROL => {
+                self.dist_control = value & 1;
+                debug!("GIC distributor control: {:#X}", self.dist_control);
+            }
+            hw_regs::DIST_SOFTWARE_INTERRUPT => {
+                // Target list filtering is ignored since only one CPU interface is modeled
+                self.raise_interrupt(value & 0x3FF);
+            }
+            _ => self.write_banked(offset, size, value),
+        }
+    }
+
+    /// Handle a read from a GIC register
+    ///
+    /// Reading the acknowledge register has side effects, so this takes `&mut self`.
+    pub fn read(&mut self, offset: u32, size: usize) -> u32 {
+        trace!("GIC register read: offset={:#X}", offset);
+
+        match offset {
+            hw_regs::CPU_CONTROL => self.cpu_control,
+            hw_regs::CPU_PRIORITY_MASK => self.priority_mask,
+            hw_regs::CPU_BINARY_POINT => self.binary_point,
+            hw_regs::CPU_ACKNOWLEDGE => self.acknowledge(),
+            hw_regs::CPU_RUNNING_PRIORITY => self.running_priority(),
+            hw_regs::CPU_HIGHEST_PENDING => self.highest_pending().unwrap_or(SPURIOUS_INTERRUPT_ID),
+            hw_regs::DIST_CONTROL => self.dist_control,
+            hw_regs::DIST_TYPE => (BITMAP_WORDS as u32) - 1,
+            _ => self.read_banked(offset, size),
+        }
+    }
+
+    /// Handle a write to one of the per-interrupt distributor register banks
+    fn write_banked(&mut self, offset: u32, size: usize, value: u32) {
+        if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_SET_ENABLE) {
+            self.enabled[word] |= value;
+        } else if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_CLEAR_ENABLE) {
+            self.enabled[word] &= !value;
+        } else if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_SET_PENDING) {
+            self.pending[word] |= value;
+        } else if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_CLEAR_PENDING) {
+            self.pending[word] &= !value;
+        } else if let Some(index) = Self::byte_index(offset, hw_regs::DIST_PRIORITY) {
+            Self::write_bytes(&mut self.priority, index, size, value);
+        } else if let Some(index) = Self::byte_index(offset, hw_regs::DIST_TARGET) {
+            Self::write_bytes(&mut self.target, index, size, value);
+        } else if let Some(word) = Self::config_word(offset) {
+            self.config[word] = value;
+        } else {
+            // SCU and private timers share this region but aren't modeled yet
+            debug!(
+                "Unhandled MPCore private register write: offset={:#X}, value={:#X}",
+                offset, value
+            );
+        }
+    }
+
+    /// Handle a read from one of the per-interrupt distributor register banks
+    fn read_banked(&self, offset: u32, size: usize) -> u32 {
+        if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_SET_ENABLE)
+            .or_else(|| Self::bitmap_word(offset, hw_regs::DIST_CLEAR_ENABLE))
+        {
+            self.enabled[word]
+        } else if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_SET_PENDING)
+            .or_else(|| Self::bitmap_word(offset, hw_regs::DIST_CLEAR_PENDING))
+        {
+            self.pending[word]
+        } else if let Some(word) = Self::bitmap_word(offset, hw_regs::DIST_ACTIVE) {
+            self.active[word]
+        } else if let Some(index) = Self::byte_index(offset, hw_regs::DIST_PRIORITY) {
+            Self::read_bytes(&self.priority, index, size)
+        } else if let Some(index) = Self::byte_index(offset, hw_regs::DIST_TARGET) {
+            Self::read_bytes(&self.target, index, size)
+        } else if let Some(word) = Self::config_word(offset) {
+            self.config[word]
+        } else {
+            debug!(
+                "Unhandled MPCore private register read: offset={:#X}",
+                offset
+            );
+            0
+        }
+    }
+
+    // ========================================================================
+    // Interrupt selection
+    // ========================================================================
+
+    /// Find the highest priority interrupt that is pending, enabled, and unmasked
+    fn highest_pending(&self) -> Option<u32> {
+        if self.cpu_control & 1 == 0 || self.dist_control & 1 == 0 {
+            return None;
+        }
+
+        (0..NUM_INTERRUPTS as u32)
+            .filter(|&id| {
+                Self::test_bit(&self.pending, id)
+                    && Self::test_bit(&self.enabled, id)
+                    && !Self::test_bit(&self.active, id)
+                    && (self.priority[id as usize] as u32) < self.priority_mask
+            })
+            // Lower priority values win, ties go to the lowest ID
+            .min_by_key(|&id| self.priority[id as usize])
+    }
+
+    /// Acknowledge the highest priority pending interrupt, moving it to active
+    fn acknowledge(&mut self) -> u32 {
+        match self.highest_pending() {
+            Some(id) => {
+                Self::clear_bit(&mut self.pending, id);
+                Self::set_bit(&mut self.active, id);
+                trace!("GIC interrupt acknowledged: {}", id);
+                id
+            }
+            None => SPURIOUS_INTERRUPT_ID,
+        }
+    }
+
+    /// Priority of the highest priority active interrupt
+    fn running_priority(&self) -> u32 {
+        (0..NUM_INTERRUPTS as u32)
+            .filter(|&id| Self::test_bit(&self.active, id))
+            .map(|id| self.priority[id as usize] as u32)
+            .min()
+            .unwrap_or(IDLE_PRIORITY)
+    }
+
+    // ========================================================================
+    // Register layout helpers
+    // ========================================================================
+
+    /// Map an offset inside a one-bit-per-interrupt register bank to its word index
+    fn bitmap_word(offset: u32, base: u32) -> Option<usize> {
+        (base..base + BITMAP_WORDS as u32 * 4)
+            .contains(&offset)
+            .then(|| ((offset - base) / 4) as usize)
+    }
+
+    /// Map an offset inside a one-byte-per-interrupt register bank to its byte index
+    fn byte_index(offset: u32, base: u32) -> Option<usize> {
+        (base..base + NUM_INTERRUPTS as u32)
+            .contains(&offset)
+            .then(|| (offset - base) as usize)
+    }
+
+    /// Map an offset inside the two-bits-per-interrupt configuration bank to its word index
+    fn config_word(offset: u32) -> Option<usize> {
+        (hw_regs::DIST_CONFIG..hw_regs::DIST_CONFIG + (NUM_INTERRUPTS / 16) as u32 * 4)
+            .contains(&offset)
+            .then(|| ((offset - hw_regs::DIST_CONFIG) / 4) as usize)
+    }
+
+    fn write_bytes(bank: &mut [u8], index: usize, size: usize, value: u32) {
+        for (i, byte) in value.to_le_bytes().iter().take(size).enumerate() {
+            if let Some(slot) = bank.get_mut(index + i) {
+                *slot = *byte;
+            }
+        }
+    }
+
+    fn read_bytes(bank: &[u8], index: usize, size: usize) -> u32 {
+        let mut bytes = [0u8; 4];
+        for (i, byte) in bytes.iter_mut().take(size).enumerate() {
+            *byte = bank.get(index + i).copied().unwrap_or(0);
+        }
+        u32::from_le_bytes(bytes)
+    }
+
+    fn test_bit(bitmap: &[u32; BITMAP_WORDS], id: u32) -> bool {
+        bitmap[(id / 32) as usize] & (1 << (id % 32)) != 0
+    }
+
+    fn set_bit(bitmap: &mut [u32; BITMAP_WORDS], id: u32) {
+        bitmap[(id / 32) as usize] |= 1 << (id % 32);
+    }
+
+    fn clear_bit(bitmap: &mut [u32; BITMAP_WORDS], id: u32) {
+        bitmap[(id / 32) as usize] &= !(1 << (id % 32));
+    }
+}
+
+// ============================================================================
+// Unicorn MMIO Adapters
+// ============================================================================
+
+/// MMIO read handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses to the u32
+/// offsets expected by the GIC handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+    super::check_device_core(uc, "GIC", CORES);
+    uc.get_data_mut().gic.read(addr as u32, size) as u64
+}
+
+/// MMIO write handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
+/// types expected by the GIC handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn write_handler(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+) {
+    super::check_device_core(uc, "GIC", CORES);
+    uc.get_data_mut().gic.write(addr as u32, size, value as u32);
+}
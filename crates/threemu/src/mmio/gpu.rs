@@ -11,11 +11,31 @@
 //! The 3DS framebuffers have an unusual orientation: pixels are stored left-to-right
 //! (as if the screen is rotated 90° clockwise). This means for a 400×240 screen, the
 //! framebuffer is actually stored as 240 columns of 400 pixels each.
+//!
+//! # Scanout Timing
+//! Each PDC exposes a VCount register that firmware polls to sync to scanout. The
+//! scanline position advances by a slice of the frame after every ARM11 quantum (see
+//! [`GpuState::advance_scanout`]), and by one line on each VCount read so that
+//! polling loops always observe progress.
+//!
+//! # Scanout Enable
+//! A screen only shows its framebuffer once firmware sets the enable bit in its PDC
+//! control register; until then renderers draw it black, even if a framebuffer
+//! address is already programmed.
 
-use oxidiz3ds_hw::mmio::gpu::registers as hw_regs;
+use super::UnimplementedMmio;
+use super::access::split_access;
+use crate::cpu_types::Core;
+use crate::milestones::Milestone;
+use crate::scheduler::QUANTUMS_PER_FRAME;
+use oxidiz3ds_hw::mmio::gpu::{DEFAULT_VTOTAL, pdc_control, registers as hw_regs};
+use oxidiz3ds_hw::specs::display;
 use tracing::{debug, instrument, trace, warn};
 use unicorn_engine::Unicorn;
 
+/// Cores the GPU registers are reachable from
+const CORES: &[Core] = &[Core::Arm11];
+
 /// Pixel format for framebuffers.
 ///
 /// These correspond to the values in bits 0-2 of the format register.
@@ -50,6 +70,31 @@ impl From<u32> for PixelFormat {
     }
 }
 
+impl PixelFormat {
+    /// Framebuffer bytes per pixel, or `None` for an unknown format
+    ///
+    /// ```
+    /// use threemu::PixelFormat;
+    ///
+    /// assert_eq!(PixelFormat::Rgba8.bytes_per_pixel(), Some(4));
+    /// assert_eq!(PixelFormat::Rgb8.bytes_per_pixel(), Some(3));
+    /// assert_eq!(PixelFormat::Rgb565.bytes_per_pixel(), Some(2));
+    /// assert_eq!(PixelFormat::Rgb5A1.bytes_per_pixel(), Some(2));
+    /// assert_eq!(PixelFormat::Rgba4.bytes_per_pixel(), Some(2));
+    /// assert_eq!(PixelFormat::Unknown.bytes_per_pixel(), None);
+    /// ```
+    pub fn bytes_per_pixel(&self) -> Option<usize> {
+        match self {
+            PixelFormat::Rgba8 => Some(display::BYTES_PER_PIXEL_RGBA8),
+            PixelFormat::Rgb8 => Some(display::BYTES_PER_PIXEL_RGB8),
+            PixelFormat::Rgb565 | PixelFormat::Rgb5A1 | PixelFormat::Rgba4 => {
+                Some(display::BYTES_PER_PIXEL_16BIT)
+            }
+            PixelFormat::Unknown => None,
+        }
+    }
+}
+
 /// GPU state tracking framebuffer configuration
 #[derive(Debug)]
 pub struct GpuState {
@@ -58,11 +103,27 @@ pub struct GpuState {
     pub top_right_addr: u32,
     pub top_format: PixelFormat,
     pub top_stride: u32,
+    /// Framebuffer select (bit 0: 0 = A/left, 1 = B/right)
+    pub top_select: u32,
+    /// PDC0 control (bit 0: scanout enabled)
+    pub top_control: u32,
+    pub top_vtotal: u32,
+    pub top_vcount: u32,
 
     // Bottom screen
     pub bottom_addr: u32,
+    pub bottom_addr_b: u32,
     pub bottom_format: PixelFormat,
     pub bottom_stride: u32,
+    /// Framebuffer select (bit 0: 0 = A, 1 = B)
+    pub bottom_select: u32,
+    /// PDC1 control (bit 0: scanout enabled)
+    pub bottom_control: u32,
+    pub bottom_vtotal: u32,
+    pub bottom_vcount: u32,
+
+    /// Accesses to registers this emulation doesn't implement
+    pub unimplemented: UnimplementedMmio,
 }
 
 impl GpuState {
@@ -73,14 +134,122 @@ impl GpuState {
             top_right_addr: 0,
             top_format: PixelFormat::Unknown,
             top_stride: 0,
+            top_select: 0,
+            top_control: 0,
+            top_vtotal: DEFAULT_VTOTAL,
+            top_vcount: 0,
             bottom_addr: 0,
+            bottom_addr_b: 0,
             bottom_format: PixelFormat::Unknown,
             bottom_stride: 0,
+            bottom_select: 0,
+            bottom_control: 0,
+            bottom_vtotal: DEFAULT_VTOTAL,
+            bottom_vcount: 0,
+            unimplemented: UnimplementedMmio::default(),
+        }
+    }
+
+    /// Top screen framebuffer currently selected for scanout
+    ///
+    /// Falls back to framebuffer A if B is selected but was never programmed.
+    pub fn active_top_addr(&self) -> u32 {
+        if self.top_select & 1 != 0 && self.top_right_addr != 0 {
+            self.top_right_addr
+        } else {
+            self.top_left_addr
+        }
+    }
+
+    /// Bottom screen framebuffer currently selected for scanout
+    ///
+    /// Falls back to framebuffer A if B is selected but was never programmed.
+    pub fn active_bottom_addr(&self) -> u32 {
+        if self.bottom_select & 1 != 0 && self.bottom_addr_b != 0 {
+            self.bottom_addr_b
+        } else {
+            self.bottom_addr
         }
     }
 
-    /// Handle a write to a GPU register
-    pub fn write(&mut self, offset: u32, _size: usize, value: u32) {
+    /// Whether PDC0 is scanning the top screen's framebuffer out
+    pub fn top_enabled(&self) -> bool {
+        self.top_control & pdc_control::ENABLE != 0
+    }
+
+    /// Whether PDC1 is scanning the bottom screen's framebuffer out
+    pub fn bottom_enabled(&self) -> bool {
+        self.bottom_control & pdc_control::ENABLE != 0
+    }
+
+    /// Human-readable dump of the framebuffer registers, one line per screen
+    pub fn dump(&self) -> String {
+        let select = |select: u32| if select & 1 != 0 { 'B' } else { 'A' };
+        format!(
+            "Top: A={:#010X} B={:#010X} select={} (active {:#010X}) format={:?} stride={} vcount={}/{} enabled={}\n\
+             Bottom: A={:#010X} B={:#010X} select={} (active {:#010X}) format={:?} stride={} vcount={}/{} enabled={}",
+            self.top_left_addr,
+            self.top_right_addr,
+            select(self.top_select),
+            self.active_top_addr(),
+            self.top_format,
+            self.top_stride,
+            self.top_vcount,
+            self.top_vtotal,
+            self.top_enabled(),
+            self.bottom_addr,
+            self.bottom_addr_b,
+            select(self.bottom_select),
+            self.active_bottom_addr(),
+            self.bottom_format,
+            self.bottom_stride,
+            self.bottom_vcount,
+            self.bottom_vtotal,
+            self.bottom_enabled(),
+        )
+    }
+
+    /// Advance both screens' scanline position by one quantum's share of a frame
+    pub fn advance_scanout(&mut self) {
+        self.top_vcount = Self::advance_vcount(
+            self.top_vcount,
+            self.top_vtotal,
+            self.top_vtotal.div_ceil(QUANTUMS_PER_FRAME as u32),
+        );
+        self.bottom_vcount = Self::advance_vcount(
+            self.bottom_vcount,
+            self.bottom_vtotal,
+            self.bottom_vtotal.div_ceil(QUANTUMS_PER_FRAME as u32),
+        );
+    }
+
+    fn advance_vcount(vcount: u32, vtotal: u32, lines: u32) -> u32 {
+        (vcount + lines) % vtotal.max(1)
+    }
+
+    /// Handle a write of `size` bytes at `offset`
+    ///
+    /// Byte and halfword writes only change the bytes they cover.
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        for slice in split_access(offset, size, |_| 4) {
+            let value = if slice.is_full() {
+                value
+            } else {
+                slice.merge(self.register_value(slice.offset).unwrap_or(0), value)
+            };
+            self.write_register(slice.offset, value);
+        }
+    }
+
+    /// Handle a read of `size` bytes at `offset`
+    pub fn read(&mut self, offset: u32, size: usize) -> u32 {
+        split_access(offset, size, |_| 4).fold(0, |value, slice| {
+            value | slice.extract(self.read_register(slice.offset))
+        })
+    }
+
+    /// Handle a write to a single GPU register
+    fn write_register(&mut self, offset: u32, value: u32) {
         trace!(
             "GPU register write: offset={:#X}, value={:#X}",
             offset, value
@@ -103,6 +272,26 @@ impl GpuState {
                 self.top_stride = value;
                 debug!("Top screen stride: {:#X}", self.top_stride);
             }
+            hw_regs::FRAMEBUFFER_TOP_SELECT => {
+                self.top_select = value;
+                trace!("Top screen framebuffer select: {}", value & 1);
+            }
+            hw_regs::PDC0_CONTROL => {
+                self.top_control = value;
+                debug!(
+                    "Top screen PDC control: {:#X} (scanout {})",
+                    value,
+                    if self.top_enabled() {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                );
+            }
+            hw_regs::PDC0_VTOTAL => {
+                self.top_vtotal = value & 0xFFF;
+                debug!("Top screen VTotal: {}", self.top_vtotal);
+            }
             hw_regs::FRAMEBUFFER_BOTTOM_LEFT => {
                 self.bottom_addr = value;
                 debug!("Bottom screen framebuffer: {:#X}", self.bottom_addr);
@@ -115,21 +304,71 @@ impl GpuState {
                 self.bottom_stride = value;
                 debug!("Bottom screen stride: {:#X}", self.bottom_stride);
             }
+            hw_regs::FRAMEBUFFER_BOTTOM_B => {
+                self.bottom_addr_b = value;
+                debug!("Bottom screen framebuffer B: {:#X}", self.bottom_addr_b);
+            }
+            hw_regs::FRAMEBUFFER_BOTTOM_SELECT => {
+                self.bottom_select = value;
+                trace!("Bottom screen framebuffer select: {}", value & 1);
+            }
+            hw_regs::PDC1_CONTROL => {
+                self.bottom_control = value;
+                debug!(
+                    "Bottom screen PDC control: {:#X} (scanout {})",
+                    value,
+                    if self.bottom_enabled() {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                );
+            }
+            hw_regs::PDC1_VTOTAL => {
+                self.bottom_vtotal = value & 0xFFF;
+                debug!("Bottom screen VTotal: {}", self.bottom_vtotal);
+            }
+            hw_regs::PDC0_VCOUNT | hw_regs::PDC1_VCOUNT => {
+                trace!("Ignoring write to read-only VCount: {:#X}", offset);
+            }
             _ => {
                 // Unknown register - log at warn level
                 warn!(
                     "Unknown GPU register write: offset={:#X}, value={:#X}",
                     offset, value
                 );
+                self.unimplemented.record("GPU", offset, true);
             }
         }
     }
 
-    /// Handle a read from a GPU register
-    pub fn read(&self, offset: u32, _size: usize) -> u32 {
+    /// Handle a read from a single GPU register
+    fn read_register(&mut self, offset: u32) -> u32 {
         trace!("GPU register read: offset={:#X}", offset);
 
+        let Some(value) = self.register_value(offset) else {
+            warn!("Unknown GPU register read: offset={:#X}", offset);
+            self.unimplemented.record("GPU", offset, false);
+            return 0;
+        };
+
+        // Each VCount read sees the beam move on by a line
         match offset {
+            hw_regs::PDC0_VCOUNT => {
+                self.top_vcount = Self::advance_vcount(self.top_vcount, self.top_vtotal, 1);
+            }
+            hw_regs::PDC1_VCOUNT => {
+                self.bottom_vcount =
+                    Self::advance_vcount(self.bottom_vcount, self.bottom_vtotal, 1);
+            }
+            _ => {}
+        }
+        value
+    }
+
+    /// Current value of the register at `offset`, without read side effects
+    fn register_value(&self, offset: u32) -> Option<u32> {
+        let value = match offset {
             hw_regs::FRAMEBUFFER_TOP_LEFT => self.top_left_addr,
             hw_regs::FRAMEBUFFER_TOP_RIGHT => self.top_right_addr,
             hw_regs::FRAMEBUFFER_TOP_FORMAT => self.top_format as u32,
@@ -137,11 +376,18 @@ impl GpuState {
             hw_regs::FRAMEBUFFER_BOTTOM_LEFT => self.bottom_addr,
             hw_regs::FRAMEBUFFER_BOTTOM_FORMAT => self.bottom_format as u32,
             hw_regs::FRAMEBUFFER_BOTTOM_STRIDE => self.bottom_stride,
-            _ => {
-                warn!("Unknown GPU register read: offset={:#X}", offset);
-                0
-            }
-        }
+            hw_regs::FRAMEBUFFER_BOTTOM_B => self.bottom_addr_b,
+            hw_regs::FRAMEBUFFER_TOP_SELECT => self.top_select,
+            hw_regs::FRAMEBUFFER_BOTTOM_SELECT => self.bottom_select,
+            hw_regs::PDC0_CONTROL => self.top_control,
+            hw_regs::PDC1_CONTROL => self.bottom_control,
+            hw_regs::PDC0_VTOTAL => self.top_vtotal,
+            hw_regs::PDC1_VTOTAL => self.bottom_vtotal,
+            hw_regs::PDC0_VCOUNT => self.top_vcount,
+            hw_regs::PDC1_VCOUNT => self.bottom_vcount,
+            _ => return None,
+        };
+        Some(value)
     }
 }
 
@@ -153,8 +399,13 @@ impl GpuState {
 ///
 /// This is a thin adapter that converts Unicorn's u64 addresses to the u32
 /// offsets expected by the GPU handler.
-#[instrument(level = "trace", skip(uc))]
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
 pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+    super::check_device_core(uc, "GPU", CORES);
     uc.get_data_mut().gpu.read(addr as u32, size) as u64
 }
 
@@ -162,12 +413,44 @@ pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size:
 ///
 /// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
 /// types expected by the GPU handler.
-#[instrument(level = "trace", skip(uc))]
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
 pub fn write_handler(
     uc: &mut Unicorn<'_, super::EmulatorState>,
     addr: u64,
     size: usize,
     value: u64,
 ) {
-    uc.get_data_mut().gpu.write(addr as u32, size, value as u32);
+    super::check_device_core(uc, "GPU", CORES);
+    let state = uc.get_data_mut();
+    state.gpu.write(addr as u32, size, value as u32);
+
+    let (top, bottom) = (state.gpu.active_top_addr(), state.gpu.active_bottom_addr());
+    if top != 0 || bottom != 0 {
+        state
+            .milestones
+            .reach(Milestone::FramebufferConfigured, || {
+                format!("top {:#X}, bottom {:#X}", top, bottom)
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_word_writes_merge_into_the_register() {
+        let mut gpu = GpuState::new();
+        gpu.write(hw_regs::FRAMEBUFFER_TOP_LEFT, 4, 0x1830_0000);
+        gpu.write(hw_regs::FRAMEBUFFER_TOP_LEFT + 1, 1, 0x46);
+        assert_eq!(gpu.top_left_addr, 0x1830_4600);
+        gpu.write(hw_regs::FRAMEBUFFER_TOP_LEFT + 2, 2, 0x1840);
+        assert_eq!(gpu.top_left_addr, 0x1840_4600);
+        assert_eq!(gpu.read(hw_regs::FRAMEBUFFER_TOP_LEFT + 2, 2), 0x1840);
+        assert_eq!(gpu.read(hw_regs::FRAMEBUFFER_TOP_LEFT + 1, 1), 0x46);
+    }
 }
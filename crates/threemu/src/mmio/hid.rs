@@ -0,0 +1,217 @@
+//! HID register handling for 3DS emulation.
+//!
+//! The HID block (0x10146000-0x10147000, both ARM9 and ARM11) reports the state of
+//! the face buttons, D-pad, and shoulder buttons through the 16-bit `PAD` register.
+//! Hardware reports buttons active-low: a bit reads as 0 while its button is held.
+//!
+//! # Button Layout
+//! | Bit | Button | Bit | Button |
+//! |-----|--------|-----|--------|
+//! | 0   | A      | 6   | Up     |
+//! | 1   | B      | 7   | Down   |
+//! | 2   | Select | 8   | R      |
+//! | 3   | Start  | 9   | L      |
+//! | 4   | Right  | 10  | X      |
+//! | 5   | Left   | 11  | Y      |
+//!
+//! Bits 12-15 are unused and read as 0. The circle pad, touch screen, and ZL/ZR
+//! aren't reported here.
+//!
+//! The held buttons are a fixed mask taken from the configuration, so boot-time
+//! button checks can be exercised headlessly. `PADCNT` reads back as written, but no
+//! button interrupts are raised.
+//!
+//! # References
+//! - [HID Registers](https://www.3dbrew.org/wiki/HID_Registers)
+
+use super::UnimplementedMmio;
+use super::access::split_access;
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::mmio::hid::{buttons, registers as hw_regs};
+use tracing::{debug, instrument, trace};
+use unicorn_engine::Unicorn;
+
+/// Cores the HID registers are reachable from
+const CORES: &[Core] = &[Core::Arm9, Core::Arm11];
+
+/// A button reported by the `PAD` register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Button {
+    A,
+    B,
+    Select,
+    Start,
+    Right,
+    Left,
+    Up,
+    Down,
+    R,
+    L,
+    X,
+    Y,
+}
+
+impl Button {
+    /// Bit of the button in `PAD`
+    pub fn mask(self) -> u16 {
+        match self {
+            Button::A => buttons::A,
+            Button::B => buttons::B,
+            Button::Select => buttons::SELECT,
+            Button::Start => buttons::START,
+            Button::Right => buttons::RIGHT,
+            Button::Left => buttons::LEFT,
+            Button::Up => buttons::UP,
+            Button::Down => buttons::DOWN,
+            Button::R => buttons::R,
+            Button::L => buttons::L,
+            Button::X => buttons::X,
+            Button::Y => buttons::Y,
+        }
+    }
+}
+
+/// HID register state shared between ARM9 and ARM11
+#[derive(Debug)]
+pub struct HidState {
+    /// Buttons currently held (bit set = pressed, same layout as `PAD`)
+    pub held_buttons: u16,
+    pub padcnt: u16,
+
+    /// Accesses to registers this emulation doesn't implement
+    pub unimplemented: UnimplementedMmio,
+}
+
+impl HidState {
+    /// Create the HID state with `held_buttons` held for the whole run
+    ///
+    /// ```
+    /// use oxidiz3ds_hw::mmio::hid::{buttons, registers};
+    /// use threemu::mmio::HidState;
+    ///
+    /// let mut hid = HidState::new(buttons::SELECT);
+    /// let pad = hid.read(registers::PAD, 2) as u16;
+    /// assert_eq!(pad & buttons::SELECT, 0);
+    /// assert_eq!(pad, buttons::ALL & !buttons::SELECT);
+    /// ```
+    pub fn new(held_buttons: u16) -> Self {
+        Self {
+            held_buttons,
+            padcnt: 0,
+            unimplemented: UnimplementedMmio::default(),
+        }
+    }
+
+    /// Value of `PAD`: every button bit set except those held
+    pub fn pad(&self) -> u16 {
+        buttons::ALL & !self.held_buttons
+    }
+
+    /// Handle a write of `size` bytes at `offset`
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        for slice in split_access(offset, size, |_| 2) {
+            let value = if slice.is_full() {
+                slice.bits(value)
+            } else {
+                slice.merge(self.register_value(slice.offset).unwrap_or(0) as u32, value)
+            };
+            self.write_register(slice.offset, value as u16);
+        }
+    }
+
+    /// Handle a read of `size` bytes at `offset`
+    pub fn read(&mut self, offset: u32, size: usize) -> u32 {
+        split_access(offset, size, |_| 2).fold(0, |value, slice| {
+            value | slice.extract(self.read_register(slice.offset) as u32)
+        })
+    }
+
+    fn write_register(&mut self, offset: u32, value: u16) {
+        trace!(
+            "HID register write: offset={:#X}, value={:#X}",
+            offset, value
+        );
+
+        match offset {
+            hw_regs::PAD => trace!("Ignoring write to read-only HID PAD register"),
+            hw_regs::PADCNT => {
+                self.padcnt = value;
+                debug!("HID PADCNT: {:#X}", value);
+            }
+            _ => {
+                debug!(
+                    "Unimplemented HID register write: offset={:#X}, value={:#X}",
+                    offset, value
+                );
+                self.unimplemented.record("HID", offset, true);
+            }
+        }
+    }
+
+    fn read_register(&mut self, offset: u32) -> u16 {
+        let value = self.register_value(offset).unwrap_or_else(|| {
+            debug!("Unimplemented HID register read: offset={:#X}", offset);
+            self.unimplemented.record("HID", offset, false);
+            0
+        });
+        trace!(
+            "HID register read: offset={:#X}, value={:#X}",
+            offset, value
+        );
+        value
+    }
+
+    /// Current value of a register, without side effects
+    fn register_value(&self, offset: u32) -> Option<u16> {
+        match offset {
+            hw_regs::PAD => Some(self.pad()),
+            hw_regs::PADCNT => Some(self.padcnt),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// Unicorn MMIO Adapters
+// ============================================================================
+
+/// MMIO read handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses to the u32
+/// offsets expected by the HID handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
+    super::check_device_core(uc, "HID", CORES);
+    uc.get_data()
+        .hid
+        .lock()
+        .expect("HID state lock poisoned")
+        .read(addr as u32, size) as u64
+}
+
+/// MMIO write handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
+/// types expected by the HID handler.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn write_handler(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+) {
+    super::check_device_core(uc, "HID", CORES);
+    uc.get_data()
+        .hid
+        .lock()
+        .expect("HID state lock poisoned")
+        .write(addr as u32, size, value as u32);
+}
@@ -0,0 +1,329 @@
+//! I2C MMIO register handling for 3DS emulation.
+//!
+//! This module implements the three I2C bus controllers and a small table of
+//! devices attached to them. Each bus is mapped at its own 4KB page (see
+//! [`oxidiz3ds_hw::mmio::i2c::BUS_BASES`]).
+//!
+//! Transfers complete immediately: writing the control register with the enable bit
+//! set performs the byte transfer and clears the busy bit before the driver can
+//! observe it. A transfer starts with a device address byte (start bit set), is
+//! followed by a register index byte for writes, and then data bytes. Reads after
+//! a repeated start return consecutive registers.
+//!
+//! Currently only the MCU is attached, providing the RTC and power button status.
+//!
+//! # References
+//! - [I2C Registers](https://www.3dbrew.org/wiki/I2C_Registers)
+//! - [MCU Services](https://www.3dbrew.org/wiki/MCU_Services)
+
+use crate::cpu_types::Core;
+use oxidiz3ds_hw::mmio::i2c::{cnt, devices, mcu as mcu_regs, registers as hw_regs};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument, trace, warn};
+use unicorn_engine::Unicorn;
+
+/// Number of I2C buses
+const NUM_BUSES: usize = 3;
+
+/// Controller register state for a single bus
+#[derive(Debug, Default)]
+pub struct I2cBus {
+    pub data: u8,
+    pub cnt: u8,
+    pub cntex: u16,
+    pub scl: u16,
+
+    /// Device address selected by the last start condition
+    device: Option<u8>,
+    /// Next written byte selects the device register rather than carrying data
+    register_pending: bool,
+    /// Current device register index (auto-increments)
+    register: u8,
+}
+
+/// MCU (microcontroller) device state
+#[derive(Debug)]
+pub struct McuState {
+    /// Fixed RTC time in seconds since the Unix epoch (host clock when `None`)
+    pub rtc_epoch_secs: Option<u64>,
+    /// Pending interrupt status bits (cleared when read)
+    pub interrupt_status: u32,
+    /// Backing store for registers without special behavior
+    regs: [u8; 0x100],
+}
+
+impl McuState {
+    pub fn new(rtc_epoch_secs: Option<u64>) -> Self {
+        let mut regs = [0u8; 0x100];
+        regs[mcu_regs::VERSION_HIGH as usize] = 0x02;
+        regs[mcu_regs::VERSION_LOW as usize] = 0x25;
+        regs[mcu_regs::BATTERY_PERCENT as usize] = 100;
+        regs[mcu_regs::POWER_STATUS as usize] = mcu_regs::POWER_STATUS_SHELL_OPEN;
+
+        Self {
+            rtc_epoch_secs,
+            interrupt_status: 0,
+            regs,
+        }
+    }
+
+    /// Signal a power button press to the emulated software
+    pub fn press_power_button(&mut self) {
+        self.interrupt_status |= mcu_regs::IRQ_POWER_PRESS;
+    }
+
+    fn read(&mut self, register: u8) -> u8 {
+        let value = match register {
+            mcu_regs::INTERRUPT_STATUS..=0x13 => {
+                let shift = (register - mcu_regs::INTERRUPT_STATUS) * 8;
+                let byte = (self.interrupt_status >> shift) as u8;
+                self.interrupt_status &= !(0xFF << shift);
+                byte
+            }
+            mcu_regs::RTC_TIME..=0x36 => self.rtc_bcd()[(register - mcu_regs::RTC_TIME) as usize],
+            _ => self.regs[register as usize],
+        };
+        trace!("MCU register read: {:#X} = {:#X}", register, value);
+        value
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            mcu_regs::POWER_CONTROL => {
+                debug!("MCU power control write: {:#X}", value);
+            }
+            _ => {
+                trace!("MCU register write: {:#X} = {:#X}", register, value);
+            }
+        }
+        self.regs[register as usize] = value;
+    }
+
+    /// Current RTC time as BCD bytes (second, minute, hour, weekday, day, month, year)
+    fn rtc_bcd(&self) -> [u8; 7] {
+        let secs = self.rtc_epoch_secs.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+        let days = secs / 86_400;
+        let time_of_day = secs % 86_400;
+        let (year, month, day) = civil_from_days(days);
+        // 1970-01-01 was a Thursday (Sunday = 0)
+        let weekday = (days + 4) % 7;
+
+        [
+            to_bcd(time_of_day % 60),
+            to_bcd(time_of_day / 60 % 60),
+            to_bcd(time_of_day / 3600),
+            to_bcd(weekday),
+            to_bcd(day),
+            to_bcd(month),
+            to_bcd(year.saturating_sub(2000) % 100),
+        ]
+    }
+}
+
+/// I2C controller state for all buses and the attached devices
+#[derive(Debug)]
+pub struct I2cState {
+    pub buses: [I2cBus; NUM_BUSES],
+    pub mcu: McuState,
+}
+
+impl I2cState {
+    pub fn new(rtc_epoch_secs: Option<u64>) -> Self {
+        Self {
+            buses: Default::default(),
+            mcu: McuState::new(rtc_epoch_secs),
+        }
+    }
+
+    /// Handle a write to an I2C register on `bus`
+    pub fn write(&mut self, bus: usize, offset: u32, _size: usize, value: u32) {
+        trace!(
+            "I2C{} register write: offset={:#X}, value={:#X}",
+            bus, offset, value
+        );
+
+        match offset {
+            hw_regs::DATA => self.buses[bus].data = value as u8,
+            hw_regs::CNT => self.control(bus, value as u8),
+            hw_regs::CNTEX => self.buses[bus].cntex = value as u16,
+            hw_regs::SCL => self.buses[bus].scl = value as u16,
+            _ => {
+                warn!(
+                    "Unknown I2C{} register write: offset={:#X}, value={:#X}",
+                    bus, offset, value
+                );
+            }
+        }
+    }
+
+    /// Handle a read from an I2C register on `bus`
+    pub fn read(&mut self, bus: usize, offset: u32, _size: usize) -> u32 {
+        trace!("I2C{} register read: offset={:#X}", bus, offset);
+
+        match offset {
+            hw_regs::DATA => self.buses[bus].data as u32,
+            hw_regs::CNT => self.buses[bus].cnt as u32,
+            hw_regs::CNTEX => self.buses[bus].cntex as u32,
+            hw_regs::SCL => self.buses[bus].scl as u32,
+            _ => {
+                warn!("Unknown I2C{} register read: offset={:#X}", bus, offset);
+                0
+            }
+        }
+    }
+
+    /// Handle a control register write, performing a byte transfer if enabled
+    fn control(&mut self, bus: usize, value: u8) {
+        if value & cnt::ENABLE == 0 {
+            self.buses[bus].cnt = value;
+            return;
+        }
+
+        let acked = if value & cnt::START != 0 {
+            // Address byte: bit 0 selects the direction of the following transfers
+            let addr = self.buses[bus].data & 0xFE;
+            let present = self.device_present(bus, addr);
+            debug!(
+                "I2C{} start: device={:#X} read={} present={}",
+                bus,
+                addr,
+                self.buses[bus].data & 1 != 0,
+                present
+            );
+            self.buses[bus].device = present.then_some(addr);
+            self.buses[bus].register_pending = self.buses[bus].data & 1 == 0;
+            present
+        } else if let Some(addr) = self.buses[bus].device {
+            if value & cnt::READ != 0 {
+                let register = self.buses[bus].register;
+                self.buses[bus].data = self.device_read(bus, addr, register);
+                self.buses[bus].register = register.wrapping_add(1);
+            } else if self.buses[bus].register_pending {
+                self.buses[bus].register = self.buses[bus].data;
+                self.buses[bus].register_pending = false;
+            } else {
+                let register = self.buses[bus].register;
+                let data = self.buses[bus].data;
+                self.device_write(bus, addr, register, data);
+                self.buses[bus].register = register.wrapping_add(1);
+            }
+            true
+        } else {
+            false
+        };
+
+        if value & cnt::STOP != 0 {
+            self.buses[bus].register_pending = false;
+        }
+
+        // Transfer finished: clear busy and report the acknowledge state
+        let mut result = value & !cnt::ENABLE;
+        if acked {
+            result |= cnt::ACK;
+        } else {
+            result &= !cnt::ACK;
+        }
+        self.buses[bus].cnt = result;
+    }
+
+    fn device_present(&self, bus: usize, addr: u8) -> bool {
+        bus == devices::MCU_BUS && addr == devices::MCU_ADDR
+    }
+
+    fn device_read(&mut self, bus: usize, addr: u8, register: u8) -> u8 {
+        match (bus, addr) {
+            (devices::MCU_BUS, devices::MCU_ADDR) => self.mcu.read(register),
+            _ => 0xFF,
+        }
+    }
+
+    fn device_write(&mut self, bus: usize, addr: u8, register: u8, value: u8) {
+        if let (devices::MCU_BUS, devices::MCU_ADDR) = (bus, addr) {
+            self.mcu.write(register, value);
+        }
+    }
+}
+
+/// Convert a value below 100 to packed BCD
+fn to_bcd(value: u64) -> u8 {
+    (((value / 10) << 4) | (value % 10)) as u8
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) civil date
+///
+/// Reference: <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+// ============================================================================
+// Unicorn MMIO Adapters
+// ============================================================================
+
+/// Cores bus `bus` is reachable from: bus 0 is shared, buses 1 and 2 are ARM11-only
+fn bus_cores(bus: usize) -> &'static [Core] {
+    match bus {
+        0 => &[Core::Arm9, Core::Arm11],
+        _ => &[Core::Arm11],
+    }
+}
+
+/// MMIO read handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses to the u32
+/// offsets expected by the I2C handler. `BUS` selects the bus controller.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn read_handler<const BUS: usize>(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+) -> u64 {
+    super::check_device_core(uc, "I2C", bus_cores(BUS));
+    uc.get_data()
+        .i2c
+        .lock()
+        .expect("I2C state lock poisoned")
+        .read(BUS, addr as u32, size) as u64
+}
+
+/// MMIO write handler function (for use with Unicorn)
+///
+/// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
+/// types expected by the I2C handler. `BUS` selects the bus controller.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
+pub fn write_handler<const BUS: usize>(
+    uc: &mut Unicorn<'_, super::EmulatorState>,
+    addr: u64,
+    size: usize,
+    value: u64,
+) {
+    super::check_device_core(uc, "I2C", bus_cores(BUS));
+    uc.get_data()
+        .i2c
+        .lock()
+        .expect("I2C state lock poisoned")
+        .write(BUS, addr as u32, size, value as u32);
+}
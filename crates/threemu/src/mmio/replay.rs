@@ -0,0 +1,210 @@
+//! Recording and replay verification of MMIO writes.
+//!
+//! With recording enabled, every MMIO write made by either core (to implemented
+//! devices and generic MMIO alike) is logged as one line:
+//!
+//! ```text
+//! core,instruction,pc,addr,size,value
+//! arm11,1234,0x1FF80010,0x10400030,4,0x1
+//! ```
+//!
+//! where `instruction` is the number of instructions the core had executed when it
+//! made the write. Verifying a later run against such a log compares its writes with
+//! the recorded ones in order and reports the first difference. Since the scheduler
+//! interleaves the cores deterministically, a difference means something
+//! non-deterministic (e.g. the host clock behind the RTC) leaked into the run.
+
+use crate::args::parse_hex_or_dec;
+use crate::cpu_types::Core;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::{error, info};
+
+/// What to do with the MMIO writes made during a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MmioReplay {
+    /// Write them to this file when the run ends
+    Record(PathBuf),
+    /// Check them against a log previously recorded to this file
+    Verify(PathBuf),
+}
+
+/// A single MMIO write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmioWrite {
+    pub core: Core,
+    /// Instructions the core had executed, including the one making the write
+    pub instruction: u64,
+    /// PC of the instruction making the write
+    pub pc: u32,
+    /// Absolute address written
+    pub addr: u32,
+    /// Access size in bytes
+    pub size: usize,
+    pub value: u64,
+}
+
+impl fmt::Display for MmioWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let core = match self.core {
+            Core::Arm9 => "arm9",
+            Core::Arm11 => "arm11",
+        };
+        write!(
+            f,
+            "{},{},{:#X},{:#X},{},{:#X}",
+            core, self.instruction, self.pc, self.addr, self.size, self.value
+        )
+    }
+}
+
+impl FromStr for MmioWrite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.trim().split(',').collect();
+        let [core, instruction, pc, addr, size, value] = fields[..] else {
+            return Err(format!("expected 6 comma-separated fields, got {:?}", s));
+        };
+        let number = |field: &str| {
+            parse_hex_or_dec(field).map_err(|e| format!("invalid number {:?}: {}", field, e))
+        };
+        Ok(Self {
+            core: core.parse()?,
+            instruction: number(instruction)?,
+            pc: number(pc)? as u32,
+            addr: number(addr)? as u32,
+            size: number(size)? as usize,
+            value: number(value)?,
+        })
+    }
+}
+
+/// MMIO write log shared by both cores' MMIO handlers
+#[derive(Debug)]
+pub struct MmioWriteLog {
+    mode: MmioReplay,
+    /// Writes recorded so far (recording), or the writes expected (verifying)
+    writes: Vec<MmioWrite>,
+    /// Writes seen so far while verifying
+    seen: usize,
+    /// First write that differed from the log: its index, the write expected there
+    /// (`None` past the end of the log), and the write actually made
+    mismatch: Option<(usize, Option<MmioWrite>, MmioWrite)>,
+}
+
+impl MmioWriteLog {
+    /// Start recording, or load the log to verify against
+    pub fn new(mode: MmioReplay) -> Result<Self, String> {
+        let writes = match &mode {
+            MmioReplay::Record(_) => Vec::new(),
+            MmioReplay::Verify(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read MMIO write log {:?}: {}", path, e))?;
+                let writes = text
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(i, line)| {
+                        line.parse()
+                            .map_err(|e| format!("{:?} line {}: {}", path, i + 1, e))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                info!(
+                    "Verifying MMIO writes against {:?} ({} writes)",
+                    path,
+                    writes.len()
+                );
+                writes
+            }
+        };
+        Ok(Self {
+            mode,
+            writes,
+            seen: 0,
+            mismatch: None,
+        })
+    }
+
+    /// Record a write, or check it against the log
+    ///
+    /// Only the first difference is reported: once the runs diverge, later writes
+    /// are unlikely to line up.
+    pub fn record(&mut self, write: MmioWrite) {
+        if matches!(self.mode, MmioReplay::Record(_)) {
+            self.writes.push(write);
+            return;
+        }
+
+        let index = self.seen;
+        self.seen += 1;
+        let expected = self.writes.get(index).copied();
+        if self.mismatch.is_none() && expected != Some(write) {
+            match expected {
+                Some(expected) => error!(
+                    "MMIO write {} differs from the log: expected {}, got {}",
+                    index, expected, write
+                ),
+                None => error!(
+                    "MMIO write {} is past the end of the log: got {}",
+                    index, write
+                ),
+            }
+            self.mismatch = Some((index, expected, write));
+        }
+    }
+
+    /// First write that differed from the log, if verifying
+    pub fn mismatch(&self) -> Option<(usize, Option<MmioWrite>, MmioWrite)> {
+        self.mismatch
+    }
+
+    /// Write out the recorded log, or report the verification result
+    ///
+    /// Verification fails if a write differed from the log or the run made fewer
+    /// writes than were recorded.
+    pub fn finish(&self) -> Result<(), String> {
+        match &self.mode {
+            MmioReplay::Record(path) => {
+                let write_log = || -> std::io::Result<()> {
+                    let mut writer = BufWriter::new(File::create(path)?);
+                    for write in &self.writes {
+                        writeln!(writer, "{}", write)?;
+                    }
+                    writer.flush()
+                };
+                write_log()
+                    .map_err(|e| format!("Failed to write MMIO write log {:?}: {}", path, e))?;
+                info!("Recorded {} MMIO writes to {:?}", self.writes.len(), path);
+                Ok(())
+            }
+            MmioReplay::Verify(path) => {
+                if let Some((index, expected, actual)) = self.mismatch {
+                    return Err(match expected {
+                        Some(expected) => format!(
+                            "MMIO write {} differs from {:?}: expected {}, got {}",
+                            index, path, expected, actual
+                        ),
+                        None => format!(
+                            "MMIO write {} ({}) is past the end of {:?}",
+                            index, actual, path
+                        ),
+                    });
+                }
+                if self.seen < self.writes.len() {
+                    return Err(format!(
+                        "Run made {} MMIO writes, but {:?} has {}",
+                        self.seen,
+                        path,
+                        self.writes.len()
+                    ));
+                }
+                info!("All {} MMIO writes match {:?}", self.seen, path);
+                Ok(())
+            }
+        }
+    }
+}
@@ -7,55 +7,82 @@
 //! - [EMMC Registers](https://www.3dbrew.org/wiki/EMMC_Registers)
 //! - [SD/MMC/SDIO Registers](https://dsibrew.org/wiki/SD/MMC/SDIO_Registers)
 
-use std::io::{Read, Seek, SeekFrom, Write};
+use super::UnimplementedMmio;
+use super::access::split_access;
+use crate::cpu_types::Core;
+use crate::milestones::Milestone;
+use oxidiz3ds_hw::mmio::sdmmc::{cmd_flags, registers as reg, status as stat0};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use tracing::{debug, instrument, trace, warn};
 use unicorn_engine::Unicorn;
 
-/// SDMMC register offsets (relative to base)
-mod reg {
-    pub const CMD: u32 = 0x000;
-    pub const PORTSEL: u32 = 0x002;
-    pub const CMDARG0: u32 = 0x004;
-    pub const CMDARG1: u32 = 0x006;
-    pub const STOP: u32 = 0x008;
-    pub const BLKCOUNT: u32 = 0x00a;
-    pub const RESP0: u32 = 0x00c;
-    pub const RESP1: u32 = 0x00e;
-    pub const RESP2: u32 = 0x010;
-    pub const RESP3: u32 = 0x012;
-    pub const RESP4: u32 = 0x014;
-    pub const RESP5: u32 = 0x016;
-    pub const RESP6: u32 = 0x018;
-    pub const RESP7: u32 = 0x01a;
-    pub const STATUS0: u32 = 0x01c;
-    pub const STATUS1: u32 = 0x01e;
-    pub const IRQ_MASK0: u32 = 0x020;
-    pub const IRQ_MASK1: u32 = 0x022;
-    pub const CLKCTL: u32 = 0x024;
-    pub const BLKLEN: u32 = 0x026;
-    pub const OPT: u32 = 0x028;
-    pub const ERROR_DETAIL_STATUS0: u32 = 0x02c;
-    pub const ERROR_DETAIL_STATUS1: u32 = 0x02e;
-    pub const FIFO: u32 = 0x030;
-    pub const DATA_CTL: u32 = 0x0d8;
-    pub const RESET: u32 = 0x0e0;
-    pub const DATA32_IRQ: u32 = 0x100;
-    pub const DATA32_BLK_LEN: u32 = 0x104;
-    pub const DATA32_BLK_COUNT: u32 = 0x108;
-    pub const DATA32_FIFO: u32 = 0x10c;
-}
-
-// Status flag constants
-const TMIO_STAT0_CMDRESPEND: u16 = 0x0001;
-const TMIO_STAT0_DATAEND: u16 = 0x0004;
-const TMIO_STAT0_CARD_INSERTED: u16 = 1 << 5;
-const TMIO_STAT0_WRPROTECT: u16 = 1 << 7;
+/// Cores the SDMMC registers are reachable from
+const CORES: &[Core] = &[Core::Arm9, Core::Arm11];
 
+// DAT0 line level: low while the card signals busy after an R1b command
+const TMIO_STAT1_DAT0: u16 = 0x0080;
+const TMIO_STAT1_DATATIMEOUT: u16 = 0x0008;
+const TMIO_STAT1_CMDTIMEOUT: u16 = 0x0040;
 const TMIO_STAT1_RXRDY: u16 = 0x0100;
 const TMIO_STAT1_TXRQ: u16 = 0x0200;
 const TMIO_STAT1_CMD_BUSY: u16 = 0x4000;
 
+// ERROR_DETAIL_STATUS0 bits: CRC and end bit errors
+const TMIO_ERR0_READ_DATA_CRC: u16 = 1 << 10;
+const TMIO_ERR0_CRC_STATUS_TOKEN: u16 = 1 << 11;
+
+// ERROR_DETAIL_STATUS1 bits: timeouts
+const TMIO_ERR1_CMD_RESP_TIMEOUT: u16 = 1 << 0;
+const TMIO_ERR1_READ_DATA_TIMEOUT: u16 = 1 << 2;
+
+// STOP bit 8: issue CMD12 automatically once the block count is reached
+const TMIO_STOP_AUTO_CMD12: u16 = 0x0100;
+
+// CLKCTL bit 8: card clock enabled (commands don't reach the card while it's off)
+const TMIO_CLKCTL_SDCLK_ON: u16 = 0x0100;
+
+// OPT bit 15: 1 = controller drives a 1-bit data bus, 0 = 4-bit
+const TMIO_OPT_BUS_WIDTH_1BIT: u16 = 0x8000;
+
+// RESET bit 0: 0 = controller held in reset, 1 = reset released
+const TMIO_RESET_RELEASE: u16 = 0x0001;
+
+// DATA_CTL bit selecting the 32-bit FIFO (REG_DATA32_FIFO) instead of the 16-bit one
+const TMIO_DATACTL_FIFO32: u16 = 0x0002;
+
+// DATA32_IRQ readiness bits (read-only, only reported while the 32-bit FIFO is selected)
+const TMIO32_STAT_RXRDY: u16 = 0x0100;
+const TMIO32_STAT_BUSY: u16 = 0x0200;
+
+// DATA32_IRQ control bits: FIFO clear (write-only) and the readiness IRQ enables.
+// This controller has no separate FIFO control register: 0x034 (called FIFO_CTL in
+// older register maps) is SDIO_CARD_IRQ_CTL, and drivers flush the FIFO by writing
+// bit 10 here (3dbrew's REG_SDDATACTL32 "clear FIFO"; the TMIO driver's
+// TMIO_MASK_CLEAR_FIFO), which reads back as 0.
+const TMIO32_CLEAR_FIFO: u16 = 0x0400;
+const TMIO32_IRQ_RXRDY_ENABLE: u16 = 0x0800;
+const TMIO32_IRQ_TXRQ_ENABLE: u16 = 0x1000;
+
+// R1 card status error bits, reported by CMD13 and cleared once reported
+const R1_OUT_OF_RANGE: u32 = 1 << 31;
+const R1_ILLEGAL_COMMAND: u32 = 1 << 22;
+const R1_ERROR: u32 = 1 << 19;
+
+// OCR bit 31: card power-up (initialization) complete; clear while the card is busy
+const OCR_POWER_UP_DONE: u32 = 1 << 31;
+
+/// Relative card address published by CMD3 (the same for the SD card and NAND)
+const CARD_RCA: u32 = 0x0001;
+
+/// Default number of ACMD41 polls answered as busy before the card reports ready
+pub const DEFAULT_OP_COND_BUSY_POLLS: u32 = 2;
+
+/// Default number of busy polls (STATUS1 reads or CMD13s) the card stays busy for
+/// after an R1b command or a write
+pub const DEFAULT_BUSY_POLLS: u32 = 2;
+
 // MMC card states (stored in STATUS1 bits 9-12, also returned in R1 response)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
@@ -70,6 +97,18 @@ enum MmcState {
     Program = 7,
 }
 
+/// Storage an SD card image can be read from and written to: a file, or an
+/// in-memory `Cursor<Vec<u8>>`
+pub trait ReadWriteSeek: Read + Write + Seek + Send {}
+
+impl<T: Read + Write + Seek + Send> ReadWriteSeek for T {}
+
+impl std::fmt::Debug for dyn ReadWriteSeek {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<SD card image>")
+    }
+}
+
 /// SDMMC state tracking controller registers and internal emulation state
 #[derive(Debug)]
 pub struct SdmmcState {
@@ -106,6 +145,9 @@ pub struct SdmmcState {
     // Data transfer registers (16-bit mode)
     pub fifo: u16, // 0x030: REG_FIFO
 
+    // SDIO interrupt control (latched only, no SDIO card is emulated)
+    pub sdio_card_irq_ctl: u16, // 0x034: REG_SDIO_CARD_IRQ_CTL
+
     // Data control registers
     pub data_ctl: u16, // 0x0D8: REG_DATA_CTL
 
@@ -118,29 +160,86 @@ pub struct SdmmcState {
     pub data32_blk_count: u16, // 0x108: REG_DATA32_BLK_COUNT
     pub data32_fifo: u32,      // 0x10C: REG_DATA32_FIFO
 
+    // ========================================================================
+    // CONFIGURATION
+    // ========================================================================
+    /// Number of ACMD41 polls answered with OCR bit 31 clear (still initializing)
+    /// before the card reports power-up complete, to exercise driver retry loops
+    pub op_cond_busy_polls: u32,
+
+    /// Number of busy polls (STATUS1 reads or CMD13s) the card holds DAT0 low for
+    /// after an R1b response or while programming written data; 0 never signals busy
+    pub busy_polls: u32,
+
+    /// Fail reads that start past the end of the SD card image: the card answers
+    /// with OUT_OF_RANGE and sends no data, so the read times out. Otherwise such
+    /// reads return zeros and the error is only reported by the next CMD13.
+    pub strict_sd_reads: bool,
+
     // ========================================================================
     // INTERNAL STATE - Emulation bookkeeping (not directly mapped to registers)
     // ========================================================================
+    /// ACMD41 polls answered since the card last entered the idle state
+    op_cond_polls: u32,
+
     /// Next command should be interpreted as ACMD (set by CMD55)
     app_command_next: bool,
 
+    /// 32-bit FIFO selected via DATA_CTL (otherwise the 16-bit FIFO is active)
+    fifo32_mode: bool,
+
+    /// Response type of the command being executed (one of `cmd_flags::RESP_*`)
+    response_type: u16,
+
+    /// SD card switched to high-speed access mode via CMD6
+    high_speed: bool,
+
+    /// Data bus width (1 or 4 lines) selected on the SD card via ACMD6
+    sd_bus_width: u8,
+
+    /// Card is holding DAT0 low after an R1b response or while programming
+    /// written data
+    card_busy: bool,
+
+    /// Busy polls left before the card releases DAT0
+    busy_polls_left: u32,
+
+    /// R1 error bits (`R1_*`) raised since the last CMD13
+    card_errors: u32,
+
     /// Current data transfer buffer (for FIFO reads/writes)
     transfer_buffer: Vec<u8>,
 
     /// Current position within transfer_buffer
     transfer_pos: usize,
 
+    /// Number of blocks in the current transfer, latched from the block count register
+    /// of the FIFO width selected when it started (BLKCOUNT or DATA32_BLK_COUNT)
+    transfer_blocks: u16,
+
     /// Number of blocks remaining in multi-block transfer
     transfer_blocks_remaining: u16,
 
     /// Starting address for current transfer operation
     transfer_start_addr: u32,
 
-    /// SD card backing file handle
-    sd_file: Option<std::fs::File>,
+    /// Port (PORTSEL value) the current transfer was started on, so a PORTSEL write
+    /// mid-transfer doesn't redirect the remaining blocks to another device
+    transfer_port: u16,
+
+    /// SD card image backing the SD port
+    sd_image: Option<Box<dyn ReadWriteSeek>>,
+
+    /// Capacity of the SD card in 512-byte blocks, from the image size
+    sd_blocks: u64,
+
+    /// Accesses to registers this emulation doesn't implement
+    pub unimplemented: UnimplementedMmio,
 }
 
 impl SdmmcState {
+    /// Create a controller with the SD card image file at `sd_card_path` attached,
+    /// opened for reading and writing
     pub fn new(sd_card_path: Option<PathBuf>) -> Self {
         // Open SD card file if path provided
         let sd_file = sd_card_path.and_then(|path| {
@@ -160,6 +259,25 @@ impl SdmmcState {
             }
         });
 
+        Self::with_sd_image(sd_file.map(|file| Box::new(file) as Box<dyn ReadWriteSeek>))
+    }
+
+    /// Create a controller with an SD card image held in memory
+    ///
+    /// Writes by the emulated software change `data` in place, so they are seen by
+    /// later reads, but nothing is persisted.
+    pub fn from_sd_card_data(data: Vec<u8>) -> Self {
+        debug!("Using in-memory SD card image ({} bytes)", data.len());
+        Self::with_sd_image(Some(Box::new(Cursor::new(data))))
+    }
+
+    /// Create a controller with `sd_image` (if any) backing the SD port
+    pub fn with_sd_image(mut sd_image: Option<Box<dyn ReadWriteSeek>>) -> Self {
+        let sd_blocks = sd_image
+            .as_mut()
+            .and_then(|image| image.seek(SeekFrom::End(0)).ok())
+            .map_or(0, |len| len / 512);
+
         Self {
             // Register state
             cmd: 0,
@@ -179,6 +297,7 @@ impl SdmmcState {
             error_detail_status0: 0,
             error_detail_status1: 0,
             fifo: 0,
+            sdio_card_irq_ctl: 0,
             data_ctl: 0,
             reset: 0,
             data32_irq: 0,
@@ -186,18 +305,177 @@ impl SdmmcState {
             data32_blk_count: 0,
             data32_fifo: 0,
 
+            // Configuration
+            op_cond_busy_polls: DEFAULT_OP_COND_BUSY_POLLS,
+            busy_polls: DEFAULT_BUSY_POLLS,
+            strict_sd_reads: false,
+
             // Internal state
+            op_cond_polls: 0,
             app_command_next: false,
+            fifo32_mode: false,
+            response_type: cmd_flags::RESP_AUTO,
+            card_busy: false,
+            busy_polls_left: 0,
+            high_speed: false,
+            sd_bus_width: 1,
+            card_errors: 0,
             transfer_buffer: Vec::new(),
             transfer_pos: 0,
+            transfer_blocks: 0,
             transfer_blocks_remaining: 0,
             transfer_start_addr: 0,
-            sd_file,
+            transfer_port: 0,
+            sd_image,
+            sd_blocks,
+            unimplemented: UnimplementedMmio::default(),
         }
     }
 
-    /// Handle a write to an SDMMC register
-    pub fn write(&mut self, offset: u32, _size: usize, value: u32) {
+    /// Human-readable dump of the controller registers and card state, one
+    /// register group per line
+    pub fn dump(&self) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        [
+            format!(
+                "CMD={:#06X} (CMD{}) PORTSEL={:#06X} CMDARG={:#010X} STOP={:#06X} BLKCOUNT={}",
+                self.cmd,
+                self.cmd & 0x3F,
+                self.portsel,
+                (self.cmdarg1 as u32) << 16 | self.cmdarg0 as u32,
+                self.stop,
+                self.blkcount
+            ),
+            format!(
+                "STATUS0={:#06X} (cmd_resp_end={}, data_end={}) STATUS1={:#06X} (cmd_busy={}, cmd_timeout={}, rxrdy={}, txrq={})",
+                self.status0,
+                self.status0 & stat0::CMD_RESP_END != 0,
+                self.status0 & stat0::DATA_END != 0,
+                self.status1,
+                self.status1 & TMIO_STAT1_CMD_BUSY != 0,
+                self.status1 & TMIO_STAT1_CMDTIMEOUT != 0,
+                self.status1 & TMIO_STAT1_RXRDY != 0,
+                self.status1 & TMIO_STAT1_TXRQ != 0
+            ),
+            format!(
+                "IRQ_MASK0={:#06X} IRQ_MASK1={:#06X} ERROR_DETAIL0={:#06X} ERROR_DETAIL1={:#06X}",
+                self.irq_mask0,
+                self.irq_mask1,
+                self.error_detail_status0,
+                self.error_detail_status1
+            ),
+            format!(
+                "CLKCTL={:#06X} (clock {}) BLKLEN={} OPT={:#06X} ({}-bit bus) DATA_CTL={:#06X} ({}-bit FIFO) RESET={:#06X} ({})",
+                self.clkctl,
+                on_off(self.clkctl & TMIO_CLKCTL_SDCLK_ON != 0),
+                self.blklen,
+                self.opt,
+                if self.opt & TMIO_OPT_BUS_WIDTH_1BIT != 0 { 1 } else { 4 },
+                self.data_ctl,
+                if self.fifo32_mode { 32 } else { 16 },
+                self.reset,
+                if self.reset & TMIO_RESET_RELEASE != 0 {
+                    "released"
+                } else {
+                    "held"
+                }
+            ),
+            format!(
+                "DATA32_IRQ={:#06X} (rxrdy_irq={}, txrq_irq={}) DATA32_BLK_LEN={} DATA32_BLK_COUNT={}",
+                self.data32_irq,
+                on_off(self.data32_irq & TMIO32_IRQ_RXRDY_ENABLE != 0),
+                on_off(self.data32_irq & TMIO32_IRQ_TXRQ_ENABLE != 0),
+                self.data32_blk_len,
+                self.data32_blk_count
+            ),
+            format!(
+                "Card: state={:?} bus_width={} high_speed={} busy={} R1 errors={:#010X}",
+                self.get_state(),
+                self.sd_bus_width,
+                self.high_speed,
+                self.card_busy,
+                self.card_errors
+            ),
+            format!(
+                "Transfer: port={:#06X} start={:#X} blocks_remaining={}/{} buffer={}/{}",
+                self.transfer_port,
+                self.transfer_start_addr,
+                self.transfer_blocks_remaining,
+                self.transfer_blocks,
+                self.transfer_pos,
+                self.transfer_buffer.len()
+            ),
+        ]
+        .join("\n")
+    }
+
+    /// Handle a write of `size` bytes at `offset`
+    ///
+    /// Accesses narrower than a register only change the bytes they cover, and wider
+    /// ones are applied to each register they span in address order. The FIFO ports
+    /// take exactly the bytes written to them.
+    pub fn write(&mut self, offset: u32, size: usize, value: u32) {
+        for slice in split_access(offset, size, Self::register_width) {
+            let value = if slice.is_full() || Self::is_fifo(slice.offset) {
+                slice.bits(value)
+            } else {
+                let current = match slice.offset {
+                    // Status registers are acknowledged by writing 0, so 1 leaves a bit set
+                    reg::STATUS0 | reg::STATUS1 => 0xFFFF,
+                    _ => self.read_register(slice.offset, slice.width as usize),
+                };
+                slice.merge(current, value)
+            };
+            self.write_register(slice.offset, slice.len as usize, value);
+        }
+    }
+
+    /// Handle a read of `size` bytes at `offset`
+    ///
+    /// Each register the access covers is read once; the FIFO ports supply exactly
+    /// the number of bytes read from them. A word read of the response block returns
+    /// two adjacent RESP halfwords, lowest address in the low bits.
+    ///
+    /// This is the guest's view: it drains the FIFOs and counts STATUS1 reads as
+    /// busy polls. Use [`Self::peek`] to inspect registers without side effects.
+    pub fn read(&mut self, offset: u32, size: usize) -> u32 {
+        split_access(offset, size, Self::register_width).fold(0, |value, slice| {
+            let register = if Self::is_fifo(slice.offset) {
+                self.read_register(slice.offset, slice.len as usize) << (slice.start * 8)
+            } else {
+                self.read_register(slice.offset, slice.width as usize)
+            };
+            value | slice.extract(register)
+        })
+    }
+
+    /// Read `size` bytes at `offset` without side effects, for debuggers and dumps
+    ///
+    /// Registers read as the guest would see them, except that the FIFO ports read
+    /// as zero (reading them would consume data) and STATUS1 reads don't count as
+    /// busy polls.
+    pub fn peek(&self, offset: u32, size: usize) -> u32 {
+        split_access(offset, size, Self::register_width).fold(0, |value, slice| {
+            value | slice.extract(self.register_value(slice.offset).unwrap_or(0))
+        })
+    }
+
+    /// Width in bytes of the register containing `offset`
+    fn register_width(offset: u32) -> u32 {
+        if offset & !3 == reg::DATA32_FIFO {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// Whether the register at `offset` is a FIFO data port
+    fn is_fifo(offset: u32) -> bool {
+        matches!(offset, reg::FIFO | reg::DATA32_FIFO)
+    }
+
+    /// Handle a write to a single SDMMC register (`width` bytes, only used by the FIFOs)
+    fn write_register(&mut self, offset: u32, width: usize, value: u32) {
         trace!(
             "SDMMC register write: offset={:#X}, value={:#X}",
             offset, value
@@ -214,14 +492,24 @@ impl SdmmcState {
                     self.cmd, cmd, arg
                 );
 
+                // Without the card clock the command never reaches the card and times out
+                if self.clkctl & TMIO_CLKCTL_SDCLK_ON == 0 {
+                    warn!("SDMMC CMD{} issued with the card clock disabled", cmd);
+                    self.status1 |= TMIO_STAT1_CMDTIMEOUT;
+                    self.error_detail_status1 |= TMIO_ERR1_CMD_RESP_TIMEOUT;
+                    return;
+                }
+
                 // Set CMD_BUSY to indicate command is being processed
                 self.status1 |= TMIO_STAT1_CMD_BUSY;
 
                 // Execute command (will clear CMD_BUSY when done)
                 if self.app_command_next {
                     self.app_command_next = false;
+                    self.response_type = self.resolve_response_type(cmd, true);
                     self.execute_acmd(cmd, arg);
                 } else {
+                    self.response_type = self.resolve_response_type(cmd, false);
                     self.execute_cmd(cmd, arg);
                 }
             }
@@ -232,6 +520,12 @@ impl SdmmcState {
                     self.portsel,
                     if self.portsel == 0 { "SD card" } else { "NAND" }
                 );
+                if self.transfer_blocks_remaining > 0 && self.portsel != self.transfer_port {
+                    debug!(
+                        "SDMMC port switched mid-transfer, {} blocks remain on port {}",
+                        self.transfer_blocks_remaining, self.transfer_port
+                    );
+                }
             }
             reg::CMDARG0 => {
                 self.cmdarg0 = value as u16;
@@ -327,22 +621,49 @@ impl SdmmcState {
             }
             reg::FIFO => {
                 self.fifo = value as u16;
-                trace!("SDMMC FIFO write: {:#X}", self.fifo);
+                if self.fifo32_mode {
+                    warn!("SDMMC 16-bit FIFO write while 32-bit FIFO is selected");
+                } else {
+                    self.write_fifo(value, width);
+                }
+            }
+            reg::SDIO_CARD_IRQ_CTL => {
+                self.sdio_card_irq_ctl = value as u16;
+                debug!("SDMMC SDIO card IRQ control: {:#X}", self.sdio_card_irq_ctl);
             }
             reg::DATA_CTL => {
                 self.data_ctl = value as u16;
-                debug!("SDMMC data control: {:#X}", self.data_ctl);
+                self.fifo32_mode = self.data_ctl & TMIO_DATACTL_FIFO32 != 0;
+                debug!(
+                    "SDMMC data control: {:#X} (32-bit FIFO: {})",
+                    self.data_ctl, self.fifo32_mode
+                );
             }
             reg::RESET => {
                 self.reset = value as u16;
                 debug!("SDMMC reset: {:#X}", self.reset);
+                // Bit 0 is active-low: clearing it holds the controller in reset
+                if self.reset & TMIO_RESET_RELEASE == 0 {
+                    self.soft_reset();
+                }
             }
             reg::DATA32_IRQ => {
-                self.data32_irq = value as u16;
-                // Firmware writes to this register using sdmmc_mask16 to clear bits 0x800/0x1000
-                // These appear to be control/acknowledgement bits, but STATUS1 flags are cleared
-                // separately via writes to REG_STATUS1
-                debug!("SDMMC data32 IRQ: {:#X}", self.data32_irq);
+                // Bits 11/12 enable the RXRDY/TXRQ interrupts rather than acknowledging
+                // anything: sdmmc.c sets 0x800 and clears 0x1000 before a read, and the
+                // reverse before a write. The readiness bits are computed on read, so
+                // writes to them are dropped, and the FIFO clear bit isn't latched.
+                let value = value as u16;
+                self.data32_irq =
+                    value & !(TMIO32_STAT_RXRDY | TMIO32_STAT_BUSY | TMIO32_CLEAR_FIFO);
+                if value & TMIO32_CLEAR_FIFO != 0 {
+                    self.clear_fifo();
+                }
+                debug!(
+                    "SDMMC data32 IRQ: {:#X} (RXRDY IRQ: {}, TXRQ IRQ: {})",
+                    self.data32_irq,
+                    self.data32_irq & TMIO32_IRQ_RXRDY_ENABLE != 0,
+                    self.data32_irq & TMIO32_IRQ_TXRQ_ENABLE != 0
+                );
             }
             reg::DATA32_BLK_LEN => {
                 self.data32_blk_len = value as u16;
@@ -354,7 +675,11 @@ impl SdmmcState {
             }
             reg::DATA32_FIFO => {
                 self.data32_fifo = value;
-                self.write_fifo32(value);
+                if self.fifo32_mode {
+                    self.write_fifo(value, width);
+                } else {
+                    warn!("SDMMC 32-bit FIFO write while 16-bit FIFO is selected");
+                }
             }
             _ => {
                 // Unknown register - log at warn level
@@ -362,15 +687,51 @@ impl SdmmcState {
                     "Unknown SDMMC register write: offset={:#X}, value={:#X}",
                     offset, value
                 );
+                self.unimplemented.record("SDMMC", offset, true);
             }
         }
     }
 
-    /// Handle a read from an SDMMC register
-    pub fn read(&mut self, offset: u32, _size: usize) -> u32 {
+    /// Handle a read from a single SDMMC register (`width` bytes, only used by the FIFOs)
+    fn read_register(&mut self, offset: u32, width: usize) -> u32 {
         trace!("SDMMC register read: offset={:#X}", offset);
 
         match offset {
+            reg::FIFO => {
+                if self.fifo32_mode {
+                    warn!("SDMMC 16-bit FIFO read while 32-bit FIFO is selected");
+                    0
+                } else {
+                    self.read_fifo(width)
+                }
+            }
+            reg::DATA32_FIFO => {
+                if self.fifo32_mode {
+                    self.read_fifo(width)
+                } else {
+                    warn!("SDMMC 32-bit FIFO read while 16-bit FIFO is selected");
+                    0
+                }
+            }
+            reg::STATUS1 => {
+                let status = self.register_value(offset).unwrap_or(0);
+                self.poll_busy();
+                status
+            }
+            _ => self.register_value(offset).unwrap_or_else(|| {
+                warn!("Unknown SDMMC register read: offset={:#X}", offset);
+                self.unimplemented.record("SDMMC", offset, false);
+                0
+            }),
+        }
+    }
+
+    /// Current value of a register that reads without side effects, or `None` for
+    /// an unknown register
+    ///
+    /// The FIFO ports, whose reads consume data, read as zero here.
+    fn register_value(&self, offset: u32) -> Option<u32> {
+        let value = match offset {
             reg::CMD => self.cmd as u32,
             reg::PORTSEL => self.portsel as u32,
             reg::CMDARG0 => self.cmdarg0 as u32,
@@ -388,17 +749,23 @@ impl SdmmcState {
             reg::STATUS0 => {
                 // Add card inserted and write protect bits
                 let mut status = self.status0;
-                status |= TMIO_STAT0_CARD_INSERTED; // Card always inserted
-                status |= TMIO_STAT0_WRPROTECT; // Not write-protected
+                status |= stat0::CARD_INSERTED; // Card always inserted
+                status |= stat0::WRITE_PROTECT; // Not write-protected
                 trace!(
                     "STATUS0 read: {:#X} (CMDRESPEND={} DATAEND={})",
                     status,
-                    status & TMIO_STAT0_CMDRESPEND != 0,
-                    status & TMIO_STAT0_DATAEND != 0
+                    status & stat0::CMD_RESP_END != 0,
+                    status & stat0::DATA_END != 0
                 );
                 status as u32
             }
-            reg::STATUS1 => self.status1 as u32,
+            reg::STATUS1 => {
+                let mut status = self.status1;
+                if !self.card_busy {
+                    status |= TMIO_STAT1_DAT0;
+                }
+                status as u32
+            }
             reg::IRQ_MASK0 => self.irq_mask0 as u32,
             reg::IRQ_MASK1 => self.irq_mask1 as u32,
             reg::CLKCTL => self.clkctl as u32,
@@ -406,37 +773,96 @@ impl SdmmcState {
             reg::OPT => self.opt as u32,
             reg::ERROR_DETAIL_STATUS0 => self.error_detail_status0 as u32,
             reg::ERROR_DETAIL_STATUS1 => self.error_detail_status1 as u32,
-            reg::FIFO => self.fifo as u32,
+            reg::FIFO | reg::DATA32_FIFO => 0,
+            reg::SDIO_CARD_IRQ_CTL => self.sdio_card_irq_ctl as u32,
             reg::DATA_CTL => self.data_ctl as u32,
             reg::RESET => self.reset as u32,
             reg::DATA32_IRQ => {
-                // REG_DATACTL32 - bits 8-9 reflect RXRDY/TXRQ status
+                // REG_DATACTL32 - bits 8-9 reflect RXRDY/TXRQ status, but only while the
+                // 32-bit FIFO is selected. In 16-bit mode drivers poll STATUS1 instead.
+                // Readiness follows the FIFO fill level against the watermark, so a driver
+                // that sees it can always move a whole burst.
                 let mut val = self.data32_irq;
-                if self.status1 & TMIO_STAT1_RXRDY != 0 {
-                    val |= 0x100; // Bit 8: read buffer ready (set when data available)
-                }
-                // Bit 9 has INVERTED semantics: clear when ready to transmit
-                // Firmware checks !(ctl32 & 0x200) for write readiness (sdmmc.c:157)
-                if self.status1 & TMIO_STAT1_TXRQ == 0 {
-                    val |= 0x200; // Bit 9: transmit buffer full (clear = ready to write)
+                if self.fifo32_mode {
+                    let watermark = self.fifo32_watermark();
+                    let remaining = self.transfer_buffer.len().saturating_sub(self.transfer_pos);
+                    if self.status1 & TMIO_STAT1_RXRDY != 0 && remaining >= watermark {
+                        val |= TMIO32_STAT_RXRDY; // Bit 8: read buffer ready (set when data available)
+                    }
+                    // Bit 9 has INVERTED semantics: clear when ready to transmit
+                    // Firmware checks !(ctl32 & 0x200) for write readiness (sdmmc.c:157)
+                    if self.status1 & TMIO_STAT1_TXRQ == 0 || remaining < watermark {
+                        val |= TMIO32_STAT_BUSY; // Bit 9: transmit buffer full (clear = ready to write)
+                    }
                 }
                 debug!(
                     "DATA32_IRQ/DATACTL32 read: {:#X} (RXRDY bit set: {})",
                     val,
-                    val & 0x100 != 0
+                    val & TMIO32_STAT_RXRDY != 0
                 );
                 val as u32
             }
             reg::DATA32_BLK_LEN => self.data32_blk_len as u32,
             reg::DATA32_BLK_COUNT => self.data32_blk_count as u32,
-            reg::DATA32_FIFO => self.read_fifo32(),
-            _ => {
-                warn!("Unknown SDMMC register read: offset={:#X}", offset);
-                0
-            }
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Fill level (in bytes) at which the 32-bit FIFO reports read/write readiness
+    ///
+    /// This is one burst of `DATA32_BLK_LEN` bytes: for reads, that much unread data
+    /// must be buffered; for writes, that much space must be free. It is capped at the
+    /// current block size, and a block length of 0 means one whole block.
+    fn fifo32_watermark(&self) -> usize {
+        let block_len = self.transfer_buffer.len();
+        match self.data32_blk_len as usize {
+            0 => block_len,
+            burst => burst.min(block_len),
         }
     }
 
+    /// Discard the data queued in the FIFO (DATA32_IRQ bit 10)
+    ///
+    /// The FIFO is modeled as a window onto the current block's buffer, so clearing
+    /// it drops the block and the readiness flags: a read in progress delivers no
+    /// more data and a write has nothing left to send. Drivers clear the FIFO before
+    /// starting a transfer or while recovering from an error, so the transfer itself
+    /// is left to be stopped with CMD12 or a reset.
+    fn clear_fifo(&mut self) {
+        debug!(
+            "SDMMC FIFO clear (discarding {} of {} bytes)",
+            self.transfer_buffer.len().saturating_sub(self.transfer_pos),
+            self.transfer_buffer.len()
+        );
+        self.transfer_buffer.clear();
+        self.transfer_pos = 0;
+        self.status1 &= !(TMIO_STAT1_RXRDY | TMIO_STAT1_TXRQ);
+    }
+
+    /// Reset the controller to its power-on state
+    ///
+    /// Clears status flags, drops any in-flight transfer, and returns the card
+    /// state machine to Idle. Configuration registers are left untouched.
+    fn soft_reset(&mut self) {
+        debug!("SDMMC soft reset");
+        self.status0 = 0;
+        self.status1 = 0;
+        self.transfer_buffer.clear();
+        self.transfer_pos = 0;
+        self.transfer_blocks_remaining = 0;
+        self.app_command_next = false;
+        self.card_busy = false;
+        self.busy_polls_left = 0;
+        self.high_speed = false;
+        self.sd_bus_width = 1;
+        self.card_errors = 0;
+        self.error_detail_status0 = 0;
+        self.error_detail_status1 = 0;
+        self.op_cond_polls = 0;
+        self.set_state(MmcState::Idle);
+    }
+
     // ========================================================================
     // Helper methods for command execution
     // ========================================================================
@@ -474,12 +900,84 @@ impl SdmmcState {
         r1
     }
 
+    /// Determine the response type for a command from the REG_CMD response bits
+    ///
+    /// In auto mode the controller picks the type from the command index. CMD0 is
+    /// treated as R1 there: drivers issuing it in auto mode still wait for
+    /// CMDRESPEND before moving on.
+    fn resolve_response_type(&self, cmd: u8, app: bool) -> u16 {
+        let explicit = self.cmd & cmd_flags::RESP_MASK;
+        if explicit != cmd_flags::RESP_AUTO {
+            return explicit;
+        }
+
+        match (app, cmd) {
+            (false, 2 | 9 | 10) => cmd_flags::RESP_R2,
+            (false, 1) | (true, 41) => cmd_flags::RESP_R3,
+            (false, 7 | 12 | 28 | 29 | 38) => cmd_flags::RESP_R1B,
+            // eMMC SWITCH signals busy; SD SWITCH_FUNC answers with R1 plus a data block
+            (false, 6) if self.nand_selected() => cmd_flags::RESP_R1B,
+            _ => cmd_flags::RESP_R1,
+        }
+    }
+
     /// Mark command as completed
+    ///
+    /// CMDRESPEND is only asserted for commands that expect a response. R1b
+    /// commands additionally hold the card busy for a few polls.
     fn command_end(&mut self) {
         // Clear CMD_BUSY flag in STATUS1
         self.status1 &= !TMIO_STAT1_CMD_BUSY;
-        // Set CMDRESPEND flag in STATUS0
-        self.status0 |= TMIO_STAT0_CMDRESPEND;
+
+        match self.response_type {
+            cmd_flags::RESP_NONE => {
+                trace!("SDMMC command without response completed");
+            }
+            cmd_flags::RESP_R1B => {
+                self.status0 |= stat0::CMD_RESP_END;
+                self.start_busy(false);
+            }
+            _ => {
+                self.status0 |= stat0::CMD_RESP_END;
+            }
+        }
+    }
+
+    /// Hold DAT0 low for the next `busy_polls` polls, moving the card to the Program
+    /// state meanwhile if `program` is set (written data being committed)
+    ///
+    /// Hardware stays busy for as long as the flash takes, which drivers can only
+    /// wait out by polling DAT0 or CMD13; a fixed number of polls models that delay
+    /// without tying it to emulated time.
+    fn start_busy(&mut self, program: bool) {
+        if self.busy_polls == 0 {
+            if program {
+                self.set_state(MmcState::Transfer);
+            }
+            return;
+        }
+        self.card_busy = true;
+        self.busy_polls_left = self.busy_polls;
+        if program {
+            self.set_state(MmcState::Program);
+        }
+        trace!("SDMMC card busy for {} polls", self.busy_polls);
+    }
+
+    /// Count a poll of the busy state, releasing DAT0 (and finishing programming)
+    /// once enough polls have seen the card busy
+    fn poll_busy(&mut self) {
+        if !self.card_busy {
+            return;
+        }
+        self.busy_polls_left = self.busy_polls_left.saturating_sub(1);
+        if self.busy_polls_left == 0 {
+            trace!("SDMMC card busy released");
+            self.card_busy = false;
+            if self.get_state() == MmcState::Program {
+                self.set_state(MmcState::Transfer);
+            }
+        }
     }
 
     /// Get full 32-bit argument from CMDARG0 and CMDARG1
@@ -487,6 +985,14 @@ impl SdmmcState {
         (self.cmdarg1 as u32) << 16 | self.cmdarg0 as u32
     }
 
+    /// The full response as held in RESP0-7, RESP0 in the low bits
+    pub fn response(&self) -> u128 {
+        self.resp
+            .iter()
+            .rev()
+            .fold(0, |acc, &half| (acc << 16) | half as u128)
+    }
+
     /// Write 128-bit response (4x u32) to RESP0-7 registers
     fn set_response_128(&mut self, resp: &[u32; 4]) {
         for (i, r) in resp.iter().enumerate() {
@@ -501,6 +1007,47 @@ impl SdmmcState {
         self.resp[1] = (resp >> 16) as u16;
     }
 
+    /// Block count and length for the next transfer, taken from the register set
+    /// that matches the FIFO width selected in DATA_CTL
+    ///
+    /// The block registers keep their values once a transfer ends, so a non-zero
+    /// DATA32_BLK_LEN left by a 32-bit transfer doesn't make the next transfer 32-bit:
+    /// only DATA_CTL chooses the width.
+    fn transfer_params(&self) -> (u16, usize) {
+        if self.fifo32_mode {
+            (self.data32_blk_count, self.data32_blk_len as usize)
+        } else {
+            (self.blkcount, self.blklen as usize)
+        }
+    }
+
+    /// Data bus width (1 or 4 lines) the controller is configured for in OPT
+    fn controller_bus_width(&self) -> u8 {
+        if self.opt & TMIO_OPT_BUS_WIDTH_1BIT != 0 {
+            1
+        } else {
+            4
+        }
+    }
+
+    /// Warn if a data transfer to the SD card starts while the controller and card
+    /// disagree on the bus width, which on hardware garbles the data
+    ///
+    /// Only the SD port is checked; eMMC bus width is set through EXT_CSD, which
+    /// isn't tracked.
+    fn check_bus_width(&self, cmd: u8) {
+        if self.nand_selected() {
+            return;
+        }
+        let controller = self.controller_bus_width();
+        if controller != self.sd_bus_width {
+            warn!(
+                "SDMMC CMD{} data transfer with the card in {}-bit mode but OPT set for {}-bit (OPT={:#X})",
+                cmd, self.sd_bus_width, controller, self.opt
+            );
+        }
+    }
+
     /// Check if NAND is currently selected (portsel == 1)
     fn nand_selected(&self) -> bool {
         self.portsel == 1
@@ -519,7 +1066,8 @@ impl SdmmcState {
             1 => self.cmd1_send_op_cond(),
             2 => self.cmd2_all_send_cid(),
             3 => self.cmd3_send_relative_addr(arg),
-            7 => self.cmd7_select_card(),
+            6 => self.cmd6_switch_func(arg),
+            7 => self.cmd7_select_card(arg),
             8 => self.cmd8_send_if_cond(),
             9 => self.cmd9_send_csd(),
             10 => self.cmd10_send_cid(),
@@ -530,7 +1078,10 @@ impl SdmmcState {
             25 => self.cmd25_write_multiple_block(arg),
             55 => self.cmd55_app_cmd(),
             _ => {
+                // A real card wouldn't respond; only the error detail records that,
+                // so bring-up isn't blocked on the missing command
                 warn!("Unimplemented SDMMC CMD{}", cmd);
+                self.error_detail_status1 |= TMIO_ERR1_CMD_RESP_TIMEOUT;
                 self.command_end();
             }
         }
@@ -548,6 +1099,7 @@ impl SdmmcState {
             51 => self.acmd51_send_scr(),
             _ => {
                 warn!("Unimplemented SDMMC ACMD{}", cmd);
+                self.error_detail_status1 |= TMIO_ERR1_CMD_RESP_TIMEOUT;
                 self.command_end();
             }
         }
@@ -560,6 +1112,8 @@ impl SdmmcState {
     /// CMD0: GO_IDLE_STATE - Reset card to idle state
     fn cmd0_go_idle_state(&mut self) {
         self.set_state(MmcState::Idle);
+        self.sd_bus_width = 1;
+        self.op_cond_polls = 0;
         self.set_response_32(1 << 9); // Card ready bit
         self.command_end();
     }
@@ -591,7 +1145,7 @@ impl SdmmcState {
 
     /// CMD3: SEND_RELATIVE_ADDR - Get/set relative card address
     fn cmd3_send_relative_addr(&mut self, _arg: u32) {
-        let rca = 0x00010000u32; // Relative card address
+        let rca = CARD_RCA << 16; // Relative card address
         let status = self.get_r1_response();
         self.set_response_32(rca | status);
         self.command_end();
@@ -601,10 +1155,99 @@ impl SdmmcState {
         }
     }
 
+    /// CMD6: SWITCH_FUNC (SD) / SWITCH (eMMC) - Query or switch card functions
+    ///
+    /// For SD cards the argument selects one function per group (4 bits each, group 1
+    /// in bits 0-3, 0xF = keep current) and bit 31 chooses between checking (0) and
+    /// switching (1). The card answers with a 512-bit status block on the data line,
+    /// sent MSB first:
+    ///
+    /// | Bits    | Bytes | Field                                              |
+    /// |---------|-------|----------------------------------------------------|
+    /// | 511:496 | 0-1   | Maximum current consumption (mA)                   |
+    /// | 495:400 | 2-13  | Supported functions, groups 6 down to 1 (16b each) |
+    /// | 399:376 | 14-16 | Selected function, groups 6 down to 1 (4b each)    |
+    /// | 375:368 | 17    | Data structure version                             |
+    /// | 367:272 | 18-29 | Busy status, groups 6 down to 1 (16b each)         |
+    /// | 271:0   | 30-63 | Reserved                                           |
+    ///
+    /// Only group 1 (access mode) has a non-default function: 1 = high speed. eMMC
+    /// SWITCH writes EXT_CSD instead and transfers no data, so it only responds.
+    fn cmd6_switch_func(&mut self, arg: u32) {
+        self.set_response_32(self.get_r1_response());
+
+        if self.nand_selected() {
+            debug!("SDMMC eMMC SWITCH: arg={:#X}", arg);
+            self.command_end();
+            return;
+        }
+
+        let switch = arg & (1 << 31) != 0;
+        let access_mode = arg & 0xF;
+        let selected = match access_mode {
+            0xF => self.high_speed as u8,
+            0 | 1 => access_mode as u8,
+            _ => 0xF, // Unsupported function
+        };
+        if switch && selected != 0xF {
+            self.high_speed = selected == 1;
+        }
+        debug!(
+            "SDMMC SWITCH_FUNC: arg={:#X} ({}), access mode {} -> high speed: {}",
+            arg,
+            if switch { "switch" } else { "check" },
+            access_mode,
+            self.high_speed
+        );
+
+        let mut status = [0u8; 64];
+        status[0..2].copy_from_slice(&200u16.to_be_bytes()); // 200mA
+        // Groups 6-2 only support their default function
+        for group in 0..5 {
+            status[2 + group * 2..4 + group * 2].copy_from_slice(&0x8001u16.to_be_bytes());
+        }
+        // Group 1: default and high-speed access modes
+        status[12..14].copy_from_slice(&0x8003u16.to_be_bytes());
+        status[16] = selected;
+        status[17] = 1;
+
+        self.command_end();
+        self.start_register_read(status.to_vec());
+    }
+
     /// CMD7: SELECT_CARD - Select/deselect card
-    fn cmd7_select_card(&mut self) {
+    ///
+    /// The argument's upper halfword is the RCA of the card to select. Selecting this
+    /// card moves it from Standby to Transfer; any other RCA (normally 0) deselects
+    /// it, returning it to Standby.
+    fn cmd7_select_card(&mut self, arg: u32) {
+        // The response reports the state the command was received in
         self.set_response_32(self.get_r1_response());
         self.command_end();
+
+        let rca = arg >> 16;
+        let state = self.get_state();
+        if rca == CARD_RCA {
+            if state == MmcState::Standby {
+                self.set_state(MmcState::Transfer);
+            }
+        } else if matches!(
+            state,
+            MmcState::Transfer | MmcState::Data | MmcState::Receive | MmcState::Program
+        ) {
+            self.set_state(MmcState::Standby);
+        }
+        debug!(
+            "SDMMC {} card (RCA={:#X}): {:?} -> {:?}",
+            if rca == CARD_RCA {
+                "select"
+            } else {
+                "deselect"
+            },
+            rca,
+            state,
+            self.get_state()
+        );
     }
 
     /// CMD8: SEND_IF_COND - Send interface condition
@@ -630,24 +1273,54 @@ impl SdmmcState {
     }
 
     /// CMD12: STOP_TRANSMISSION - Stop multi-block read/write
+    ///
+    /// Outside of a transfer (e.g. in Idle or Standby) CMD12 is illegal: the card
+    /// doesn't respond, so the command times out, and the next CMD13 reports
+    /// ILLEGAL_COMMAND. The card state and transfer registers are left alone.
     fn cmd12_stop_transmission(&mut self) {
+        let state = self.get_state();
+        if !matches!(
+            state,
+            MmcState::Transfer | MmcState::Data | MmcState::Receive | MmcState::Program
+        ) {
+            debug!(
+                "SDMMC CMD12 with no transfer active ({:?}), ignoring",
+                state
+            );
+            self.card_errors |= R1_ILLEGAL_COMMAND;
+            self.status1 &= !TMIO_STAT1_CMD_BUSY;
+            self.status1 |= TMIO_STAT1_CMDTIMEOUT;
+            self.error_detail_status1 |= TMIO_ERR1_CMD_RESP_TIMEOUT;
+            return;
+        }
+
         self.set_response_32(self.get_r1_response());
         self.transfer_blocks_remaining = 0;
         self.transfer_buffer.clear();
         self.command_end();
 
-        // State transitions
+        // State transitions: stopping a write programs the data received so far
         match self.get_state() {
-            MmcState::Data | MmcState::Receive => self.set_state(MmcState::Transfer),
+            MmcState::Data => self.set_state(MmcState::Transfer),
+            MmcState::Receive => self.start_busy(true),
             MmcState::Transfer => self.set_state(MmcState::Standby),
             _ => {}
         }
     }
 
     /// CMD13: SEND_STATUS - Send card status
+    ///
+    /// Also reports errors from earlier transfers (e.g. a read past the end of the
+    /// card), which are cleared once reported. While the card is busy it reports the
+    /// Program state, and each CMD13 counts as a busy poll.
     fn cmd13_send_status(&mut self) {
-        self.set_response_32(self.get_r1_response());
+        if self.card_errors != 0 {
+            debug!("SDMMC reporting card errors {:#X}", self.card_errors);
+        }
+        let errors = std::mem::take(&mut self.card_errors);
+        self.set_response_32(self.get_r1_response() | errors);
         self.command_end();
+        self.poll_busy();
     }
 
     /// CMD16: SET_BLOCKLEN - Set block length
@@ -660,23 +1333,37 @@ impl SdmmcState {
     fn cmd18_read_multiple_block(&mut self, arg: u32) {
         let sector = arg;
 
-        // Use 32-bit mode parameters if available (data32_blk_count/len are set), otherwise use 16-bit
-        let (blocks, block_len) = if self.data32_blk_len > 0 {
-            (self.data32_blk_count, self.data32_blk_len as usize)
-        } else {
-            (self.blkcount, self.blklen as usize)
-        };
+        let (blocks, block_len) = self.transfer_params();
 
         debug!(
             "SDMMC read multiple blocks: sector={:#X}, blocks={}, len={} (32-bit mode: {}, port: {})",
             sector,
             blocks,
             block_len,
-            self.data32_blk_len > 0,
+            self.fifo32_mode,
             if self.portsel == 0 { "SD" } else { "NAND" }
         );
 
+        self.check_bus_width(18);
+        if self.portsel == 0 && self.sd_image.is_some() && sector as u64 >= self.sd_blocks {
+            debug!(
+                "SDMMC read starts past the end of the card (sector {:#X}, {} blocks)",
+                sector, self.sd_blocks
+            );
+            self.card_errors |= R1_OUT_OF_RANGE;
+            if self.strict_sd_reads {
+                // The card rejects the address and stays in Transfer; with no data
+                // arriving, the controller's read times out
+                self.set_response_32(self.get_r1_response() | R1_OUT_OF_RANGE);
+                self.command_end();
+                self.status1 |= TMIO_STAT1_DATATIMEOUT;
+                self.error_detail_status1 |= TMIO_ERR1_READ_DATA_TIMEOUT;
+                return;
+            }
+        }
         self.transfer_start_addr = sector;
+        self.transfer_port = self.portsel;
+        self.transfer_blocks = blocks;
         self.transfer_blocks_remaining = blocks;
         self.transfer_pos = 0;
         self.set_state(MmcState::Data);
@@ -685,20 +1372,8 @@ impl SdmmcState {
         self.transfer_buffer = vec![0u8; block_len];
 
         // Read from SD card file if available and SD port is selected
-        if self.portsel == 0
-            && let Some(ref mut file) = self.sd_file
-        {
-            let offset = sector as u64 * 512; // Standard 512-byte sectors
-            if let Err(e) = file.seek(SeekFrom::Start(offset)) {
-                warn!("Failed to seek SD card to sector {}: {}", sector, e);
-            } else if let Err(e) = file.read_exact(&mut self.transfer_buffer) {
-                warn!("Failed to read from SD card sector {}: {}", sector, e);
-                self.transfer_buffer.fill(0); // Fill with zeros on error
-            } else {
-                debug!("Read {} bytes from SD card sector {:#X}", block_len, sector);
-            }
-        }
-        // NAND reads remain stubbed (return zeros)
+        // (NAND reads remain stubbed and return zeros)
+        self.read_sd_block(sector);
 
         self.set_response_32(self.get_r1_response());
         self.command_end();
@@ -711,23 +1386,21 @@ impl SdmmcState {
     fn cmd25_write_multiple_block(&mut self, arg: u32) {
         let sector = arg;
 
-        // Use 32-bit mode parameters if available (data32_blk_count/len are set), otherwise use 16-bit
-        let (blocks, block_len) = if self.data32_blk_len > 0 {
-            (self.data32_blk_count, self.data32_blk_len as usize)
-        } else {
-            (self.blkcount, self.blklen as usize)
-        };
+        let (blocks, block_len) = self.transfer_params();
 
         debug!(
             "SDMMC write multiple blocks: sector={:#X}, blocks={}, len={} (32-bit mode: {}, port: {})",
             sector,
             blocks,
             block_len,
-            self.data32_blk_len > 0,
+            self.fifo32_mode,
             if self.portsel == 0 { "SD" } else { "NAND" }
         );
 
+        self.check_bus_width(25);
         self.transfer_start_addr = sector;
+        self.transfer_port = self.portsel;
+        self.transfer_blocks = blocks;
         self.transfer_blocks_remaining = blocks;
         self.transfer_pos = 0;
         self.set_state(MmcState::Receive);
@@ -750,7 +1423,15 @@ impl SdmmcState {
     }
 
     /// ACMD6: SET_BUS_WIDTH - Set bus width
-    fn acmd6_set_bus_width(&mut self, _arg: u32) {
+    ///
+    /// Argument bits 1-0 select the width: 0 = 1-bit, 2 = 4-bit.
+    fn acmd6_set_bus_width(&mut self, arg: u32) {
+        match arg & 0x3 {
+            0 => self.sd_bus_width = 1,
+            2 => self.sd_bus_width = 4,
+            width => warn!("SDMMC SET_BUS_WIDTH with reserved width {:#X}", width),
+        }
+        debug!("SDMMC SD card bus width: {}-bit", self.sd_bus_width);
         self.set_response_32(self.get_r1_response());
         self.command_end();
     }
@@ -759,27 +1440,42 @@ impl SdmmcState {
     fn acmd13_sd_status(&mut self) {
         self.set_response_32(self.get_r1_response());
 
-        // Prepare SD status buffer (64 bytes, mostly zeros)
-        self.transfer_buffer = vec![0u8; 64];
-        self.transfer_pos = 0;
-
         self.command_end();
-        self.status1 |= TMIO_STAT1_RXRDY;
+
+        // SD status block (64 bytes, mostly zeros)
+        self.start_register_read(vec![0u8; 64]);
     }
 
     /// ACMD41: SD_SEND_OP_COND - Send SD operating conditions
-    fn acmd41_sd_send_op_cond(&mut self, _arg: u32) {
-        let mut ocr = 0x80FF8080u32;
+    ///
+    /// The first `op_cond_busy_polls` requests report the card as still initializing
+    /// (OCR bit 31 clear) and leave it idle; later ones report power-up complete.
+    /// An inquiry (empty voltage window in `arg`) doesn't count as a poll.
+    fn acmd41_sd_send_op_cond(&mut self, arg: u32) {
+        let mut ocr = 0x00FF8080u32;
 
         // Set SDHC bit (bit 30) for SD cards
         if !self.nand_selected() {
             ocr |= 1 << 30;
         }
 
+        let inquiry = arg & 0x00FF_FFFF == 0;
+        let ready = self.op_cond_polls >= self.op_cond_busy_polls;
+        if !inquiry && !ready {
+            self.op_cond_polls += 1;
+            debug!(
+                "ACMD41: card busy ({}/{} polls)",
+                self.op_cond_polls, self.op_cond_busy_polls
+            );
+        }
+        if ready {
+            ocr |= OCR_POWER_UP_DONE;
+        }
+
         self.set_response_32(ocr);
         self.command_end();
 
-        if self.get_state() == MmcState::Idle {
+        if ready && !inquiry && self.get_state() == MmcState::Idle {
             self.set_state(MmcState::Ready);
         }
     }
@@ -794,33 +1490,44 @@ impl SdmmcState {
     fn acmd51_send_scr(&mut self) {
         self.set_response_32(self.get_r1_response());
 
-        // Prepare SCR buffer (8 bytes)
-        let scr = [0u8, 0x00, 0x00, 0x2a, 0x01, 0x00, 0x00, 0x00];
-        self.transfer_buffer = scr.to_vec();
-        self.transfer_pos = 0;
-
         self.command_end();
-        self.status1 |= TMIO_STAT1_RXRDY;
+
+        // SCR (8 bytes)
+        let scr = [0u8, 0x00, 0x00, 0x2a, 0x01, 0x00, 0x00, 0x00];
+        self.start_register_read(scr.to_vec());
     }
 
     // ========================================================================
     // FIFO data transfer methods
     // ========================================================================
 
-    /// Read 32 bits from the FIFO (for data transfer)
-    fn read_fifo32(&mut self) -> u32 {
-        if self.transfer_pos + 4 <= self.transfer_buffer.len() {
-            let value = u32::from_le_bytes([
-                self.transfer_buffer[self.transfer_pos],
-                self.transfer_buffer[self.transfer_pos + 1],
-                self.transfer_buffer[self.transfer_pos + 2],
-                self.transfer_buffer[self.transfer_pos + 3],
-            ]);
+    /// Start a single-block read of register data (SCR, SD status, switch status)
+    ///
+    /// Treated like a one-block transfer so DATAEND is raised once the driver has
+    /// drained the block.
+    fn start_register_read(&mut self, data: Vec<u8>) {
+        self.transfer_buffer = data;
+        self.transfer_pos = 0;
+        self.transfer_blocks = 1;
+        self.transfer_blocks_remaining = 1;
+        self.status1 |= TMIO_STAT1_RXRDY;
+    }
+
+    /// Read `width` bytes (2 or 4) from the active FIFO (for data transfer)
+    fn read_fifo(&mut self, width: usize) -> u32 {
+        if self.transfer_pos + width <= self.transfer_buffer.len() {
+            let mut bytes = [0u8; 4];
+            bytes[..width].copy_from_slice(
+                &self.transfer_buffer[self.transfer_pos..self.transfer_pos + width],
+            );
+            let value = u32::from_le_bytes(bytes);
             trace!(
-                "SDMMC FIFO32 read: {:#X} (pos={:#X})",
-                value, self.transfer_pos
+                "SDMMC FIFO{} read: {:#X} (pos={:#X})",
+                width * 8,
+                value,
+                self.transfer_pos
             );
-            self.transfer_pos += 4;
+            self.transfer_pos += width;
 
             // Check if block is complete
             if self.transfer_pos >= self.transfer_buffer.len() {
@@ -830,7 +1537,8 @@ impl SdmmcState {
             value
         } else {
             warn!(
-                "SDMMC FIFO32 read beyond buffer (pos={}, len={})",
+                "SDMMC FIFO{} read beyond buffer (pos={}, len={})",
+                width * 8,
                 self.transfer_pos,
                 self.transfer_buffer.len()
             );
@@ -838,17 +1546,20 @@ impl SdmmcState {
         }
     }
 
-    /// Write 32 bits to the FIFO (for data transfer)
-    fn write_fifo32(&mut self, value: u32) {
+    /// Write `width` bytes (2 or 4) to the active FIFO (for data transfer)
+    fn write_fifo(&mut self, value: u32, width: usize) {
         trace!(
-            "SDMMC FIFO32 write: {:#X} (pos={:#X})",
-            value, self.transfer_pos
+            "SDMMC FIFO{} write: {:#X} (pos={:#X})",
+            width * 8,
+            value,
+            self.transfer_pos
         );
 
-        if self.transfer_pos + 4 <= self.transfer_buffer.len() {
+        if self.transfer_pos + width <= self.transfer_buffer.len() {
             let bytes = value.to_le_bytes();
-            self.transfer_buffer[self.transfer_pos..self.transfer_pos + 4].copy_from_slice(&bytes);
-            self.transfer_pos += 4;
+            self.transfer_buffer[self.transfer_pos..self.transfer_pos + width]
+                .copy_from_slice(&bytes[..width]);
+            self.transfer_pos += width;
 
             // Check if block is complete
             if self.transfer_pos >= self.transfer_buffer.len() {
@@ -856,14 +1567,69 @@ impl SdmmcState {
             }
         } else {
             warn!(
-                "SDMMC FIFO32 write beyond buffer (pos={}, len={})",
+                "SDMMC FIFO{} write beyond buffer (pos={}, len={})",
+                width * 8,
                 self.transfer_pos,
                 self.transfer_buffer.len()
             );
         }
     }
 
+    /// Fill the transfer buffer from `sector` of the SD card image, if the transfer
+    /// targets the SD port and an image is attached
+    ///
+    /// On failure the buffer is zero-filled and the error is recorded for the next
+    /// CMD13 and in the error detail registers: reading past the end of the image is
+    /// out of range (no data arrives, so the read times out), anything else a general
+    /// error (reported as corrupted data).
+    fn read_sd_block(&mut self, sector: u32) {
+        if self.transfer_port != 0 {
+            return;
+        }
+        let Some(file) = self.sd_image.as_mut() else {
+            return;
+        };
+
+        let offset = sector as u64 * 512; // Standard 512-byte sectors
+        let result = file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut self.transfer_buffer));
+        match result {
+            Ok(()) => debug!(
+                "Read {} bytes from SD card sector {:#X}",
+                self.transfer_buffer.len(),
+                sector
+            ),
+            Err(e) => {
+                warn!("Failed to read from SD card sector {}: {}", sector, e);
+                self.transfer_buffer.fill(0); // Fill with zeros on error
+                if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    self.card_errors |= R1_OUT_OF_RANGE;
+                    self.error_detail_status1 |= TMIO_ERR1_READ_DATA_TIMEOUT;
+                } else {
+                    self.card_errors |= R1_ERROR;
+                    self.error_detail_status0 |= TMIO_ERR0_READ_DATA_CRC;
+                }
+            }
+        }
+    }
+
+    /// Sector of the block currently being transferred
+    ///
+    /// Counted against the block count latched when the transfer started, not the
+    /// BLKCOUNT register: a 32-bit transfer takes its count from DATA32_BLK_COUNT, and
+    /// BLKCOUNT may still hold whatever an earlier 16-bit transfer left there.
+    fn current_sector(&self) -> u32 {
+        self.transfer_start_addr
+            + self
+                .transfer_blocks
+                .saturating_sub(self.transfer_blocks_remaining) as u32
+    }
+
     /// Handle completion of reading a block
+    ///
+    /// Called once the driver has consumed the last FIFO word of the current block,
+    /// so DATAEND is only asserted after the final block has actually been drained.
     fn handle_block_complete_read(&mut self) {
         debug!(
             "SDMMC block read complete, {} blocks remaining",
@@ -881,28 +1647,12 @@ impl SdmmcState {
             if self.transfer_blocks_remaining == 0 {
                 // All blocks transferred
                 debug!("All blocks transferred, setting DATAEND flag");
-                self.status0 |= TMIO_STAT0_DATAEND;
-                self.transfer_buffer.clear();
-                self.set_state(MmcState::Transfer);
+                self.status1 &= !TMIO_STAT1_RXRDY;
+                self.finish_data_transfer();
             } else {
                 // Load next block
-                let next_sector = self.transfer_start_addr
-                    + (self.blkcount - self.transfer_blocks_remaining) as u32;
-
-                // Read from SD card if available and SD port is selected
-                if self.portsel == 0
-                    && let Some(ref mut file) = self.sd_file
-                {
-                    let offset = next_sector as u64 * 512;
-                    if let Err(e) = file.seek(SeekFrom::Start(offset)) {
-                        warn!("Failed to seek SD card to sector {}: {}", next_sector, e);
-                    } else if let Err(e) = file.read_exact(&mut self.transfer_buffer) {
-                        warn!("Failed to read from SD card sector {}: {}", next_sector, e);
-                        self.transfer_buffer.fill(0);
-                    } else {
-                        debug!("Read next block from SD card sector {:#X}", next_sector);
-                    }
-                }
+                // Read from SD card if the transfer targets the SD port
+                self.read_sd_block(self.current_sector());
 
                 debug!("More blocks remaining, setting RXRDY flag");
                 self.status1 |= TMIO_STAT1_RXRDY;
@@ -910,6 +1660,31 @@ impl SdmmcState {
         }
     }
 
+    /// Signal the end of a data transfer
+    ///
+    /// With auto-stop enabled in the STOP register the controller issues CMD12 itself,
+    /// returning the card from Data to Transfer, or from Receive to Program while the
+    /// written data is committed. Otherwise the card stays in its data state until the
+    /// driver sends CMD12.
+    fn finish_data_transfer(&mut self) {
+        self.status0 |= stat0::DATA_END;
+        self.transfer_buffer.clear();
+
+        if self.stop & TMIO_STOP_AUTO_CMD12 != 0 {
+            match self.get_state() {
+                MmcState::Data => {
+                    debug!("SDMMC auto CMD12");
+                    self.set_state(MmcState::Transfer);
+                }
+                MmcState::Receive => {
+                    debug!("SDMMC auto CMD12, programming written data");
+                    self.start_busy(true);
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Handle completion of writing a block
     fn handle_block_complete_write(&mut self) {
         trace!(
@@ -917,12 +1692,11 @@ impl SdmmcState {
             self.transfer_blocks_remaining
         );
 
-        // Write to SD card if available and SD port is selected
-        let current_sector =
-            self.transfer_start_addr + (self.blkcount - self.transfer_blocks_remaining) as u32;
+        // Write to SD card if the transfer targets the SD port
+        let current_sector = self.current_sector();
 
-        if self.portsel == 0
-            && let Some(ref mut file) = self.sd_file
+        if self.transfer_port == 0
+            && let Some(ref mut file) = self.sd_image
         {
             let offset = current_sector as u64 * 512;
             if let Err(e) = file.seek(SeekFrom::Start(offset)) {
@@ -932,6 +1706,9 @@ impl SdmmcState {
                     "Failed to write to SD card sector {}: {}",
                     current_sector, e
                 );
+                // The card reports a failed write through the CRC status token
+                self.card_errors |= R1_ERROR;
+                self.error_detail_status0 |= TMIO_ERR0_CRC_STATUS_TOKEN;
             } else {
                 debug!(
                     "Wrote {} bytes to SD card sector {:#X}",
@@ -950,9 +1727,7 @@ impl SdmmcState {
 
             if self.transfer_blocks_remaining == 0 {
                 // All blocks transferred
-                self.status0 |= TMIO_STAT0_DATAEND;
-                self.transfer_buffer.clear();
-                self.set_state(MmcState::Transfer);
+                self.finish_data_transfer();
             } else {
                 // Ready for next block
                 self.status1 |= TMIO_STAT1_TXRQ;
@@ -968,24 +1743,640 @@ impl SdmmcState {
 /// MMIO read handler function (for use with Unicorn)
 ///
 /// This is a thin adapter that converts Unicorn's u64 addresses to the u32
-/// offsets expected by the SDMMC handler.
-#[instrument(level = "trace", skip(uc))]
+/// offsets expected by the SDMMC handler. Doubleword reads (e.g. of the response
+/// block) are handled as two word reads in address order.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
 pub fn read_handler(uc: &mut Unicorn<'_, super::EmulatorState>, addr: u64, size: usize) -> u64 {
-    uc.get_data_mut().sdmmc.read(addr as u32, size) as u64
+    super::check_device_core(uc, "SDMMC", CORES);
+    let mut sdmmc = uc
+        .get_data()
+        .sdmmc
+        .lock()
+        .expect("SDMMC state lock poisoned");
+    let offset = addr as u32;
+    if uc.get_data().host_access.load(Ordering::Relaxed) {
+        return if size > 4 {
+            sdmmc.peek(offset, 4) as u64 | (sdmmc.peek(offset + 4, size - 4) as u64) << 32
+        } else {
+            sdmmc.peek(offset, size) as u64
+        };
+    }
+    if size > 4 {
+        let low = sdmmc.read(offset, 4) as u64;
+        low | (sdmmc.read(offset + 4, size - 4) as u64) << 32
+    } else {
+        sdmmc.read(offset, size) as u64
+    }
 }
 
 /// MMIO write handler function (for use with Unicorn)
 ///
 /// This is a thin adapter that converts Unicorn's u64 addresses and values to the u32
-/// types expected by the SDMMC handler.
-#[instrument(level = "trace", skip(uc))]
+/// types expected by the SDMMC handler. Doubleword writes are handled as two word
+/// writes in address order.
+#[instrument(
+    level = "trace",
+    skip(uc),
+    fields(core = ?uc.get_data().core, pc = %super::access_pc(uc))
+)]
 pub fn write_handler(
     uc: &mut Unicorn<'_, super::EmulatorState>,
     addr: u64,
     size: usize,
     value: u64,
 ) {
-    uc.get_data_mut()
+    super::check_device_core(uc, "SDMMC", CORES);
+    let mut sdmmc = uc
+        .get_data()
         .sdmmc
-        .write(addr as u32, size, value as u32);
+        .lock()
+        .expect("SDMMC state lock poisoned");
+    let offset = addr as u32;
+    if size > 4 {
+        sdmmc.write(offset, 4, value as u32);
+        sdmmc.write(offset + 4, size - 4, (value >> 32) as u32);
+    } else {
+        sdmmc.write(offset, size, value as u32);
+    }
+    drop(sdmmc);
+
+    if offset == reg::CMD {
+        uc.get_data()
+            .milestones
+            .reach(Milestone::FirstSdmmcCommand, || {
+                format!("CMD{} from {:?}", value & 0x3F, uc.get_data().core)
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blocks in the test SD card image
+    const CARD_BLOCKS: usize = 8;
+
+    /// Controller with an in-memory SD card whose sector `n` is filled with byte `n`,
+    /// clock on
+    fn controller() -> SdmmcState {
+        let image = (0..CARD_BLOCKS * 512).map(|i| (i / 512) as u8).collect();
+        let mut sd = SdmmcState::from_sd_card_data(image);
+        sd.write(reg::CLKCTL, 2, TMIO_CLKCTL_SDCLK_ON as u32);
+        sd
+    }
+
+    /// Issue `cmd` (CMD index plus REG_CMD flags) with `arg`
+    fn command(sd: &mut SdmmcState, cmd: u16, arg: u32) {
+        sd.write(reg::CMDARG0, 2, arg & 0xFFFF);
+        sd.write(reg::CMDARG1, 2, arg >> 16);
+        sd.write(reg::CMD, 2, cmd as u32);
+    }
+
+    /// Controller whose SD card has been identified and selected (Transfer state)
+    fn selected_card() -> SdmmcState {
+        select(controller())
+    }
+
+    /// Identify and select the SD card attached to `sd`
+    fn select(mut sd: SdmmcState) -> SdmmcState {
+        sd.op_cond_busy_polls = 0;
+        sd.busy_polls = 0;
+        command(&mut sd, 0, 0);
+        command(&mut sd, 55, 0);
+        command(&mut sd, 41, 0x00FF_8000);
+        command(&mut sd, 2, 0);
+        command(&mut sd, 3, 0);
+        command(&mut sd, 7, CARD_RCA << 16);
+        sd.busy_polls = DEFAULT_BUSY_POLLS;
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+        sd
+    }
+
+    fn status1(sd: &mut SdmmcState) -> u16 {
+        sd.read(reg::STATUS1, 2) as u16
+    }
+
+    /// Write `blocks` blocks of `fill` to sector `sector` with CMD25 through the
+    /// 16-bit FIFO, acknowledging TXRQ before each block as drivers do
+    fn write_blocks(sd: &mut SdmmcState, sector: u32, blocks: u16, fill: u8) {
+        sd.write(reg::BLKCOUNT, 2, blocks as u32);
+        sd.write(reg::BLKLEN, 2, 512);
+        command(sd, 25, sector);
+        for _ in 0..blocks {
+            assert_ne!(sd.status1 & TMIO_STAT1_TXRQ, 0);
+            sd.write(reg::STATUS1, 2, !TMIO_STAT1_TXRQ as u32);
+            for _ in 0..512 / 2 {
+                sd.write(reg::FIFO, 2, u16::from_le_bytes([fill, fill]) as u32);
+            }
+        }
+    }
+
+    /// Set the block registers of the FIFO width `fifo32` selects and start a CMD18
+    /// read of `blocks` blocks from `sector`
+    fn start_read(sd: &mut SdmmcState, fifo32: bool, sector: u32, blocks: u16) {
+        if fifo32 {
+            sd.write(reg::DATA_CTL, 2, TMIO_DATACTL_FIFO32 as u32);
+            sd.write(reg::DATA32_BLK_COUNT, 2, blocks as u32);
+            sd.write(reg::DATA32_BLK_LEN, 2, 512);
+        } else {
+            sd.write(reg::DATA_CTL, 2, 0);
+            sd.write(reg::BLKCOUNT, 2, blocks as u32);
+            sd.write(reg::BLKLEN, 2, 512);
+        }
+        command(sd, 18, sector);
+    }
+
+    /// Read `len` bytes from the FIFO `fifo32` selects
+    fn drain(sd: &mut SdmmcState, fifo32: bool, len: usize) -> Vec<u8> {
+        let (fifo, width) = if fifo32 {
+            (reg::DATA32_FIFO, 4)
+        } else {
+            (reg::FIFO, 2)
+        };
+        (0..len / width)
+            .flat_map(|_| sd.read(fifo, width).to_le_bytes()[..width].to_vec())
+            .collect()
+    }
+
+    /// The data a read of `blocks` blocks from `sector` should return
+    fn sectors(sector: u8, blocks: u8) -> Vec<u8> {
+        (sector..sector + blocks).flat_map(|n| [n; 512]).collect()
+    }
+
+    #[test]
+    fn fifo_width_follows_data_ctl() {
+        let mut sd = selected_card();
+        start_read(&mut sd, false, 2, 1);
+        // The 32-bit FIFO is idle and DATA32_IRQ reports no readiness
+        assert_eq!(sd.read(reg::DATA32_FIFO, 4), 0);
+        assert_eq!(sd.transfer_pos, 0);
+        assert_eq!(sd.read(reg::DATA32_IRQ, 2) as u16 & TMIO32_STAT_RXRDY, 0);
+        assert_eq!(drain(&mut sd, false, 512), sectors(2, 1));
+        command(&mut sd, 12, 0);
+
+        start_read(&mut sd, true, 3, 1);
+        assert_eq!(sd.read(reg::FIFO, 2), 0);
+        assert_eq!(sd.transfer_pos, 0);
+        assert_ne!(sd.read(reg::DATA32_IRQ, 2) as u16 & TMIO32_STAT_RXRDY, 0);
+        assert_eq!(drain(&mut sd, true, 512), sectors(3, 1));
+        assert_ne!(sd.status0 & stat0::DATA_END, 0);
+    }
+
+    #[test]
+    fn reset_drops_the_transfer_and_returns_to_idle() {
+        let mut sd = selected_card();
+        start_read(&mut sd, false, 1, 2);
+        drain(&mut sd, false, 4);
+        command(&mut sd, 55, CARD_RCA << 16);
+        assert!(sd.app_command_next);
+
+        sd.write(reg::RESET, 2, 0);
+        assert_eq!(sd.status0, 0);
+        assert_eq!(sd.status1, 0);
+        assert!(sd.transfer_buffer.is_empty());
+        assert_eq!(sd.transfer_pos, 0);
+        assert_eq!(sd.transfer_blocks_remaining, 0);
+        assert!(!sd.app_command_next);
+        assert_eq!(sd.get_state(), MmcState::Idle);
+
+        // Releasing reset leaves the controller idle and usable
+        sd.write(reg::RESET, 2, TMIO_RESET_RELEASE as u32);
+        assert_eq!(sd.get_state(), MmcState::Idle);
+        command(&mut sd, 8, 0x1AA);
+        assert_eq!(sd.resp[0], 0x1AA);
+    }
+
+    #[test]
+    fn dataend_waits_for_the_last_word_to_be_read() {
+        let mut sd = selected_card();
+        start_read(&mut sd, false, 1, 2);
+        let mut data = Vec::new();
+        for _ in 0..2 * 512 / 2 - 1 {
+            data.extend_from_slice(&drain(&mut sd, false, 2));
+            assert_eq!(sd.status0 & stat0::DATA_END, 0);
+            assert_ne!(sd.status1 & TMIO_STAT1_RXRDY, 0);
+        }
+        data.extend_from_slice(&drain(&mut sd, false, 2));
+        assert_ne!(sd.status0 & stat0::DATA_END, 0);
+        assert_eq!(sd.status1 & TMIO_STAT1_RXRDY, 0);
+        assert_eq!(data, sectors(1, 2));
+    }
+
+    #[test]
+    fn transfers_stay_on_the_port_they_started_on() {
+        let mut sd = selected_card();
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        start_read(&mut sd, false, 2, 2);
+        let mut data = drain(&mut sd, false, 512);
+        sd.write(reg::PORTSEL, 2, 1);
+        data.extend_from_slice(&drain(&mut sd, false, 512));
+        assert_eq!(data, sectors(2, 2));
+
+        // The second block of a write started on the SD port still reaches the card
+        sd.write(reg::PORTSEL, 2, 0);
+        sd.busy_polls = 0;
+        sd.write(reg::BLKCOUNT, 2, 2);
+        command(&mut sd, 25, 4);
+        for _ in 0..2 {
+            sd.write(reg::STATUS1, 2, !TMIO_STAT1_TXRQ as u32);
+            for _ in 0..512 / 2 {
+                sd.write(reg::FIFO, 2, 0xEEEE);
+            }
+            sd.write(reg::PORTSEL, 2, 1);
+        }
+        sd.write(reg::PORTSEL, 2, 0);
+        start_read(&mut sd, false, 4, 2);
+        assert_eq!(drain(&mut sd, false, 2 * 512), vec![0xEE; 2 * 512]);
+    }
+
+    #[test]
+    fn auto_stop_returns_the_card_to_transfer() {
+        let mut sd = selected_card();
+        start_read(&mut sd, false, 1, 2);
+        drain(&mut sd, false, 2 * 512);
+        assert_eq!(sd.get_state(), MmcState::Data);
+        command(&mut sd, 12, 0);
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        start_read(&mut sd, true, 1, 2);
+        drain(&mut sd, true, 2 * 512);
+        assert_ne!(sd.status0 & stat0::DATA_END, 0);
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+    }
+
+    #[test]
+    fn sub_word_accesses_touch_only_their_bytes() {
+        let mut sd = controller();
+        sd.write(reg::CMDARG0, 2, 0x1234);
+        sd.write(reg::CMDARG0 + 1, 1, 0xAB);
+        assert_eq!(sd.cmdarg0, 0xAB34);
+        assert_eq!(sd.read(reg::CMDARG0, 1), 0x34);
+        assert_eq!(sd.read(reg::CMDARG0 + 1, 1), 0xAB);
+
+        // A halfword at CMDARG0 + 2 is CMDARG1, not the top of CMDARG0
+        sd.write(reg::CMDARG0 + 2, 2, 0x5678);
+        assert_eq!((sd.cmdarg0, sd.cmdarg1), (0xAB34, 0x5678));
+
+        // A word spans both registers
+        sd.write(reg::CMDARG0, 4, 0xDEAD_BEEF);
+        assert_eq!((sd.cmdarg0, sd.cmdarg1), (0xBEEF, 0xDEAD));
+        assert_eq!(sd.read(reg::CMDARG0, 4), 0xDEAD_BEEF);
+        assert_eq!(sd.read(reg::CMDARG0 + 2, 2), 0xDEAD);
+    }
+
+    #[test]
+    fn fifo32_readiness_follows_the_burst_watermark() {
+        let mut sd = selected_card();
+        let rxrdy = |sd: &mut SdmmcState| sd.read(reg::DATA32_IRQ, 2) as u16 & TMIO32_STAT_RXRDY;
+
+        // With a whole-block watermark the FIFO isn't ready once a word is taken
+        start_read(&mut sd, true, 1, 2);
+        assert_ne!(rxrdy(&mut sd), 0);
+        drain(&mut sd, true, 4);
+        assert_eq!(rxrdy(&mut sd), 0);
+        command(&mut sd, 12, 0);
+
+        // With 64-byte bursts it's ready while a whole burst is left in the block
+        start_read(&mut sd, true, 1, 2);
+        sd.write(reg::DATA32_BLK_LEN, 2, 64);
+        let mut data = Vec::new();
+        for burst in 0..2 * 512 / 64 {
+            assert_ne!(rxrdy(&mut sd), 0, "burst {}", burst);
+            if burst % 8 == 7 {
+                data.extend_from_slice(&drain(&mut sd, true, 4));
+                assert_eq!(rxrdy(&mut sd), 0, "burst {}", burst);
+                data.extend_from_slice(&drain(&mut sd, true, 60));
+            } else {
+                data.extend_from_slice(&drain(&mut sd, true, 64));
+            }
+        }
+        assert_eq!(data, sectors(1, 2));
+    }
+
+    #[test]
+    fn cmd13_reports_a_read_past_the_image_once() {
+        let mut sd = selected_card();
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        // The second block lies past the end of the image
+        start_read(&mut sd, false, CARD_BLOCKS as u32 - 1, 2);
+        let data = drain(&mut sd, false, 2 * 512);
+        assert_eq!(data[..512], sectors(CARD_BLOCKS as u8 - 1, 1));
+        assert_eq!(data[512..], [0; 512]);
+
+        command(&mut sd, 13, CARD_RCA << 16);
+        let status = sd.response() as u32;
+        assert_ne!(status & R1_OUT_OF_RANGE, 0);
+        assert_eq!((status >> 9) & 0xF, MmcState::Transfer as u32);
+        command(&mut sd, 13, CARD_RCA << 16);
+        assert_eq!(sd.response() as u32 & R1_OUT_OF_RANGE, 0);
+    }
+
+    /// SD card image whose reads and writes fail, as with a bad medium
+    struct FailingImage;
+
+    impl Read for FailingImage {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("media error"))
+        }
+    }
+
+    impl Write for FailingImage {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("media error"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for FailingImage {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            Ok(match pos {
+                SeekFrom::End(_) => (CARD_BLOCKS * 512) as u64,
+                _ => 0,
+            })
+        }
+    }
+
+    #[test]
+    fn failures_set_error_detail_bits() {
+        let mut sd = selected_card();
+        command(&mut sd, 5, 0);
+        assert_eq!(sd.error_detail_status1, TMIO_ERR1_CMD_RESP_TIMEOUT);
+
+        sd.write(reg::ERROR_DETAIL_STATUS1, 2, 0);
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        start_read(&mut sd, false, CARD_BLOCKS as u32 - 1, 2);
+        drain(&mut sd, false, 2 * 512);
+        assert_eq!(sd.error_detail_status1, TMIO_ERR1_READ_DATA_TIMEOUT);
+        assert_eq!(sd.error_detail_status0, 0);
+
+        let mut sd = SdmmcState::with_sd_image(Some(Box::new(FailingImage)));
+        sd.write(reg::CLKCTL, 2, TMIO_CLKCTL_SDCLK_ON as u32);
+        let mut sd = select(sd);
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        start_read(&mut sd, false, 0, 1);
+        assert_eq!(sd.error_detail_status0, TMIO_ERR0_READ_DATA_CRC);
+        drain(&mut sd, false, 512);
+
+        sd.write(reg::ERROR_DETAIL_STATUS0, 2, 0);
+        sd.busy_polls = 0;
+        write_blocks(&mut sd, 0, 1, 0xA5);
+        assert_eq!(sd.error_detail_status0, TMIO_ERR0_CRC_STATUS_TOKEN);
+        assert_eq!(sd.error_detail_status1, 0);
+    }
+
+    #[test]
+    fn cmd7_selects_and_deselects_the_card() {
+        let mut sd = selected_card();
+        sd.busy_polls = 0;
+        command(&mut sd, 7, 0);
+        assert_eq!(sd.get_state(), MmcState::Standby);
+        // Another card's RCA doesn't select this one
+        command(&mut sd, 7, (CARD_RCA + 1) << 16);
+        assert_eq!(sd.get_state(), MmcState::Standby);
+        command(&mut sd, 7, CARD_RCA << 16);
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+        // The response reports the state the command was received in
+        assert_eq!((sd.response() as u32 >> 9) & 0xF, MmcState::Standby as u32);
+    }
+
+    #[test]
+    fn data32_irq_latches_only_the_irq_enables() {
+        let mut sd = selected_card();
+        start_read(&mut sd, true, 1, 1);
+
+        // Writing the readiness bits doesn't set or clear them
+        sd.write(
+            reg::DATA32_IRQ,
+            2,
+            (TMIO32_IRQ_RXRDY_ENABLE | TMIO32_STAT_RXRDY | TMIO32_STAT_BUSY) as u32,
+        );
+        assert_eq!(sd.data32_irq, TMIO32_IRQ_RXRDY_ENABLE);
+        let status = sd.read(reg::DATA32_IRQ, 2) as u16;
+        assert_ne!(status & TMIO32_STAT_RXRDY, 0);
+        assert_ne!(status & TMIO32_IRQ_RXRDY_ENABLE, 0);
+
+        // Switching to the TXRQ IRQ, as before a write, leaves the read ready
+        sd.write(reg::DATA32_IRQ, 2, TMIO32_IRQ_TXRQ_ENABLE as u32);
+        assert_eq!(sd.data32_irq, TMIO32_IRQ_TXRQ_ENABLE);
+        assert_ne!(sd.read(reg::DATA32_IRQ, 2) as u16 & TMIO32_STAT_RXRDY, 0);
+        assert_eq!(drain(&mut sd, true, 512), sectors(1, 1));
+        assert_eq!(sd.read(reg::DATA32_IRQ, 2) as u16 & TMIO32_STAT_RXRDY, 0);
+    }
+
+    #[test]
+    fn cmd12_outside_a_transfer_is_illegal() {
+        let mut sd = selected_card();
+        sd.busy_polls = 0;
+        command(&mut sd, 7, 0);
+        sd.write(reg::STATUS0, 2, 0);
+
+        command(&mut sd, 12, 0);
+        assert_eq!(sd.status0 & stat0::CMD_RESP_END, 0);
+        assert_ne!(sd.status1 & TMIO_STAT1_CMDTIMEOUT, 0);
+        assert_eq!(sd.status1 & TMIO_STAT1_CMD_BUSY, 0);
+        assert_eq!(sd.get_state(), MmcState::Standby);
+
+        command(&mut sd, 13, CARD_RCA << 16);
+        assert_ne!(sd.response() as u32 & R1_ILLEGAL_COMMAND, 0);
+        command(&mut sd, 13, CARD_RCA << 16);
+        assert_eq!(sd.response() as u32 & R1_ILLEGAL_COMMAND, 0);
+    }
+
+    #[test]
+    fn reads_past_the_end_of_the_card() {
+        // By default the read returns zeros and the next CMD13 reports it
+        let mut sd = selected_card();
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        start_read(&mut sd, false, CARD_BLOCKS as u32, 1);
+        assert_ne!(sd.status1 & TMIO_STAT1_RXRDY, 0);
+        assert_eq!(drain(&mut sd, false, 512), [0; 512]);
+        command(&mut sd, 13, CARD_RCA << 16);
+        assert_ne!(sd.response() as u32 & R1_OUT_OF_RANGE, 0);
+
+        // Strict reads are rejected up front and time out without data
+        let mut sd = selected_card();
+        sd.strict_sd_reads = true;
+        start_read(&mut sd, false, CARD_BLOCKS as u32, 1);
+        assert_ne!(sd.response() as u32 & R1_OUT_OF_RANGE, 0);
+        assert_ne!(sd.status0 & stat0::CMD_RESP_END, 0);
+        assert_ne!(sd.status1 & TMIO_STAT1_DATATIMEOUT, 0);
+        assert_eq!(sd.status1 & TMIO_STAT1_RXRDY, 0);
+        assert_eq!(sd.error_detail_status1, TMIO_ERR1_READ_DATA_TIMEOUT);
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+
+        // A read that ends on the last sector is fine either way
+        start_read(&mut sd, false, CARD_BLOCKS as u32 - 1, 1);
+        assert_eq!(
+            drain(&mut sd, false, 512),
+            sectors(CARD_BLOCKS as u8 - 1, 1)
+        );
+    }
+
+    #[test]
+    fn transfers_of_different_widths_back_to_back() {
+        let mut sd = selected_card();
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        // BLKCOUNT keeps a stale count the 32-bit transfer must not use
+        sd.write(reg::BLKCOUNT, 2, 5);
+        start_read(&mut sd, true, 1, 2);
+        assert_eq!(drain(&mut sd, true, 2 * 512), sectors(1, 2));
+        assert_ne!(sd.status0 & stat0::DATA_END, 0);
+
+        // DATA32_BLK_LEN is left set, but DATA_CTL makes this one 16-bit
+        sd.write(reg::STATUS0, 2, 0);
+        start_read(&mut sd, false, 4, 3);
+        assert_eq!(sd.read(reg::DATA32_FIFO, 4), 0);
+        assert_eq!(drain(&mut sd, false, 3 * 512), sectors(4, 3));
+        assert_ne!(sd.status0 & stat0::DATA_END, 0);
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+
+        // And back to 32-bit
+        start_read(&mut sd, true, 6, 2);
+        assert_eq!(drain(&mut sd, true, 2 * 512), sectors(6, 2));
+    }
+
+    #[test]
+    fn status0_reports_a_writable_card_present() {
+        let mut sd = controller();
+        let status = sd.read(reg::STATUS0, 2) as u16;
+        assert_eq!(status & stat0::CARD_INSERTED, 0x0020);
+        assert_eq!(status & stat0::WRITE_PROTECT, 0x0080);
+        assert_eq!(status & stat0::CARD_REMOVED, 0);
+    }
+
+    #[test]
+    fn cmd25_busy_releases_after_guest_polls_only() {
+        let mut sd = selected_card();
+        sd.write(reg::STOP, 2, TMIO_STOP_AUTO_CMD12 as u32);
+        write_blocks(&mut sd, 1, 2, 0xA5);
+        assert_ne!(sd.status0 & stat0::DATA_END, 0);
+        assert_eq!(sd.get_state(), MmcState::Program);
+
+        // Diagnostic reads see the card busy without ending the busy period
+        for _ in 0..DEFAULT_BUSY_POLLS * 2 {
+            assert_eq!(sd.peek(reg::STATUS1, 2) as u16 & TMIO_STAT1_DAT0, 0);
+        }
+        assert_eq!(sd.get_state(), MmcState::Program);
+
+        // Guest polls release it
+        for _ in 0..DEFAULT_BUSY_POLLS {
+            assert_eq!(status1(&mut sd) & TMIO_STAT1_DAT0, 0);
+        }
+        assert_ne!(status1(&mut sd) & TMIO_STAT1_DAT0, 0);
+        assert_eq!(sd.get_state(), MmcState::Transfer);
+    }
+
+    #[test]
+    fn peek_leaves_the_fifo_alone() {
+        let mut sd = selected_card();
+        command(&mut sd, 55, CARD_RCA << 16);
+        command(&mut sd, 51, 0);
+        assert_ne!(sd.peek(reg::STATUS1, 2) as u16 & TMIO_STAT1_RXRDY, 0);
+        assert_eq!(sd.peek(reg::FIFO, 2), 0);
+        assert_eq!(sd.transfer_pos, 0);
+    }
+
+    #[test]
+    fn commands_time_out_until_the_card_clock_is_on() {
+        let mut sd = SdmmcState::from_sd_card_data(vec![0; CARD_BLOCKS * 512]);
+        command(&mut sd, 8, 0x1AA);
+        assert_ne!(sd.status1 & TMIO_STAT1_CMDTIMEOUT, 0);
+        assert_ne!(sd.error_detail_status1 & TMIO_ERR1_CMD_RESP_TIMEOUT, 0);
+        assert_eq!(sd.status0 & stat0::CMD_RESP_END, 0);
+        assert_eq!(sd.resp, [0; 8]);
+
+        sd.write(reg::STATUS1, 2, !TMIO_STAT1_CMDTIMEOUT as u32);
+        sd.write(reg::ERROR_DETAIL_STATUS1, 2, 0);
+        sd.write(reg::CLKCTL, 2, TMIO_CLKCTL_SDCLK_ON as u32);
+        command(&mut sd, 8, 0x1AA);
+        assert_eq!(sd.status1 & TMIO_STAT1_CMDTIMEOUT, 0);
+        assert_eq!(sd.error_detail_status1, 0);
+        assert_ne!(sd.status0 & stat0::CMD_RESP_END, 0);
+        assert_eq!(sd.resp[0], 0x1AA);
+    }
+
+    #[test]
+    fn registers_latch_at_their_offsets() {
+        let mut sd = controller();
+        type Field = fn(&SdmmcState) -> u16;
+        let cases: [(u32, u16, Field); 17] = [
+            (reg::PORTSEL, 1, |sd| sd.portsel),
+            (reg::CMDARG0, 0x1234, |sd| sd.cmdarg0),
+            (reg::CMDARG1, 0x5678, |sd| sd.cmdarg1),
+            (reg::STOP, TMIO_STOP_AUTO_CMD12, |sd| sd.stop),
+            (reg::BLKCOUNT, 3, |sd| sd.blkcount),
+            (reg::IRQ_MASK0, 0x031D, |sd| sd.irq_mask0),
+            (reg::IRQ_MASK1, 0x837F, |sd| sd.irq_mask1),
+            (reg::CLKCTL, TMIO_CLKCTL_SDCLK_ON | 0x40, |sd| sd.clkctl),
+            (reg::BLKLEN, 0x200, |sd| sd.blklen),
+            (reg::OPT, 0x40EE, |sd| sd.opt),
+            (reg::ERROR_DETAIL_STATUS0, 0x0001, |sd| {
+                sd.error_detail_status0
+            }),
+            (reg::ERROR_DETAIL_STATUS1, 0x0002, |sd| {
+                sd.error_detail_status1
+            }),
+            (reg::SDIO_CARD_IRQ_CTL, 0x0007, |sd| sd.sdio_card_irq_ctl),
+            (reg::DATA_CTL, TMIO_DATACTL_FIFO32, |sd| sd.data_ctl),
+            (reg::RESET, TMIO_RESET_RELEASE, |sd| sd.reset),
+            (reg::DATA32_BLK_LEN, 0x200, |sd| sd.data32_blk_len),
+            (reg::DATA32_BLK_COUNT, 4, |sd| sd.data32_blk_count),
+        ];
+        for (offset, value, field) in cases {
+            sd.write(offset, 2, value as u32);
+            assert_eq!(field(&sd), value, "field for offset {:#X}", offset);
+            assert_eq!(sd.read(offset, 2), value as u32, "offset {:#X}", offset);
+        }
+        assert!(sd.fifo32_mode);
+
+        sd.write(reg::DATA32_IRQ, 2, TMIO32_IRQ_RXRDY_ENABLE as u32);
+        assert_eq!(sd.data32_irq, TMIO32_IRQ_RXRDY_ENABLE);
+        command(&mut sd, 13, CARD_RCA << 16);
+        assert_eq!(sd.cmd, 13);
+    }
+
+    #[test]
+    #[expect(deprecated)]
+    fn old_register_names_resolve_to_current_offsets() {
+        assert_eq!(reg::IRQ_STAT, reg::STATUS0);
+        assert_eq!(reg::IRQ_MASK, reg::IRQ_MASK0);
+        assert_eq!(reg::CLK_CTL, reg::CLKCTL);
+        assert_eq!(reg::OPTION, reg::OPT);
+        assert_eq!(reg::DATA_FIFO, reg::FIFO);
+        assert_eq!(reg::FIFO_CTL, reg::SDIO_CARD_IRQ_CTL);
+        assert_eq!(reg::SOFT_RST, reg::RESET);
+        assert_eq!(reg::SD_CLK_CTL, reg::CLKCTL);
+    }
+
+    #[test]
+    fn fifo_clear_drops_the_block_and_readiness() {
+        let mut sd = selected_card();
+        sd.write(reg::DATA_CTL, 2, TMIO_DATACTL_FIFO32 as u32);
+        sd.write(reg::DATA32_BLK_COUNT, 2, 1);
+        sd.write(reg::DATA32_BLK_LEN, 2, 512);
+        command(&mut sd, 18, 0);
+        sd.read(reg::DATA32_FIFO, 4);
+        assert_eq!(sd.transfer_pos, 4);
+        assert_ne!(sd.status1 & TMIO_STAT1_RXRDY, 0);
+
+        sd.write(
+            reg::DATA32_IRQ,
+            2,
+            (TMIO32_CLEAR_FIFO | TMIO32_IRQ_RXRDY_ENABLE) as u32,
+        );
+        assert_eq!(sd.transfer_pos, 0);
+        assert!(sd.transfer_buffer.is_empty());
+        assert_eq!(sd.status1 & (TMIO_STAT1_RXRDY | TMIO_STAT1_TXRQ), 0);
+        // The clear bit doesn't latch, the IRQ enable does
+        assert_eq!(sd.read(reg::DATA32_IRQ, 2) as u16 & TMIO32_CLEAR_FIFO, 0);
+        assert_ne!(sd.data32_irq & TMIO32_IRQ_RXRDY_ENABLE, 0);
+    }
 }
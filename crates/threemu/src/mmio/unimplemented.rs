@@ -0,0 +1,76 @@
+//! Tracking of accesses to MMIO registers the emulator doesn't implement.
+//!
+//! Device handlers record every access that falls through to their "unknown register"
+//! branch, and generic MMIO records every access it stubs out. The collected entries
+//! show which peripherals firmware actually relies on, most used first.
+
+use std::collections::BTreeMap;
+
+/// Accesses to a single unimplemented register
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnimplementedAccess {
+    /// Device the register belongs to (e.g. "GPU", "SDMMC", "generic")
+    pub region: &'static str,
+    /// Register offset within the device (absolute address for generic MMIO)
+    pub offset: u32,
+    /// Number of reads
+    pub reads: usize,
+    /// Number of writes
+    pub writes: usize,
+}
+
+impl UnimplementedAccess {
+    /// Total number of accesses
+    pub fn count(&self) -> usize {
+        self.reads + self.writes
+    }
+}
+
+/// Set of unimplemented registers hit during a run, with access counts
+#[derive(Debug, Clone, Default)]
+pub struct UnimplementedMmio {
+    accesses: BTreeMap<(&'static str, u32), UnimplementedAccess>,
+}
+
+impl UnimplementedMmio {
+    /// Record an access to an unimplemented register
+    pub fn record(&mut self, region: &'static str, offset: u32, is_write: bool) {
+        let access = self
+            .accesses
+            .entry((region, offset))
+            .or_insert(UnimplementedAccess {
+                region,
+                offset,
+                reads: 0,
+                writes: 0,
+            });
+        if is_write {
+            access.writes += 1;
+        } else {
+            access.reads += 1;
+        }
+    }
+
+    /// Add the counts recorded in `other` to this set
+    pub fn merge(&mut self, other: &UnimplementedMmio) {
+        for access in other.accesses.values() {
+            let entry = self
+                .accesses
+                .entry((access.region, access.offset))
+                .or_insert(UnimplementedAccess {
+                    reads: 0,
+                    writes: 0,
+                    ..access.clone()
+                });
+            entry.reads += access.reads;
+            entry.writes += access.writes;
+        }
+    }
+
+    /// Recorded registers, most accessed first
+    pub fn by_count(&self) -> Vec<UnimplementedAccess> {
+        let mut accesses: Vec<_> = self.accesses.values().cloned().collect();
+        accesses.sort_by_key(|access| std::cmp::Reverse(access.count()));
+        accesses
+    }
+}
@@ -0,0 +1,86 @@
+//! Per-instruction PC traces for differential debugging.
+//!
+//! When enabled, every instruction executed on the traced cores is logged as one
+//! `core,pc` line (e.g. `arm11,0x1FF80000`), in execution order, so the result can
+//! be compared against a trace from another emulator. Both cores write to the same
+//! buffered file; since the scheduler interleaves them in quanta, lines from one core
+//! come in runs of up to a quantum.
+
+use crate::cpu_types::{Core, CoreSelection};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// What to trace and where to write it
+#[derive(Debug, Clone)]
+pub struct PcTraceConfig {
+    /// Output file, overwritten if it exists
+    pub path: PathBuf,
+    /// Stop logging after this many entries
+    pub limit: Option<usize>,
+    /// Cores whose instructions are logged
+    pub cores: CoreSelection,
+}
+
+/// An open PC trace shared by the code hooks of both cores
+#[derive(Debug)]
+pub struct PcTrace {
+    writer: BufWriter<File>,
+    /// Entries that may still be written before the limit is reached
+    remaining: Option<usize>,
+    written: usize,
+    /// Set after a write fails, so the error is only reported once
+    failed: bool,
+}
+
+impl PcTrace {
+    /// Create the trace file for `config`
+    pub fn create(config: &PcTraceConfig) -> Result<Self, String> {
+        let file = File::create(&config.path)
+            .map_err(|e| format!("Failed to create PC trace {:?}: {}", config.path, e))?;
+        info!("Writing PC trace to {:?}", config.path);
+        Ok(Self {
+            writer: BufWriter::new(file),
+            remaining: config.limit,
+            written: 0,
+            failed: false,
+        })
+    }
+
+    /// Log an executed instruction, unless the entry limit has been reached
+    pub fn record(&mut self, core: Core, pc: u64) {
+        if self.failed || self.remaining == Some(0) {
+            return;
+        }
+        let name = match core {
+            Core::Arm9 => "arm9",
+            Core::Arm11 => "arm11",
+        };
+        if let Err(e) = writeln!(self.writer, "{},{:#X}", name, pc) {
+            warn!("Failed to write PC trace, stopping trace: {}", e);
+            self.failed = true;
+            return;
+        }
+        self.written += 1;
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= 1;
+            if *remaining == 0 {
+                info!("PC trace limit reached after {} entries", self.written);
+                self.flush();
+            }
+        }
+    }
+
+    /// Number of entries written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Write out buffered entries
+    pub fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            warn!("Failed to flush PC trace: {}", e);
+        }
+    }
+}
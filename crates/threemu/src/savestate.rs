@@ -0,0 +1,417 @@
+//! Save states: a stable, introspectable snapshot of emulator state.
+//!
+//! A save state holds the register file of both cores and the contents of the main
+//! memory regions. Two states can be compared with [`SaveState::diff`] to see what a
+//! span of execution changed.
+//!
+//! # Format
+//! All integers are little-endian.
+//! - Magic `b"3EMUSAVE"` followed by the format version (u32)
+//! - ARM9 registers, then ARM11 registers: one u32 each for r0-r15 and CPSR
+//! - Number of memory regions (u32), then for each region: name length (u32),
+//!   name (UTF-8), base address (u32), size (u32), and contents
+//!
+//! Readers reject more than 16 regions, names over 64 bytes, and regions larger than
+//! FCRAM.
+
+use crate::cpu_types::{ArmRegister, Core};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Magic bytes at the start of every save state file
+const MAGIC: &[u8; 8] = b"3EMUSAVE";
+
+/// Current save state format version
+const VERSION: u32 = 1;
+
+/// Most memory regions a save state may hold
+const MAX_REGIONS: u32 = 16;
+
+/// Longest region name a save state may hold
+const MAX_NAME_LEN: u32 = 64;
+
+/// Largest memory region a save state may hold (FCRAM, the biggest one snapshotted)
+const MAX_REGION_SIZE: u32 = crate::memory::FCRAM_SIZE as u32;
+
+/// Granularity of memory comparisons in [`SaveState::diff`]
+pub const DIFF_PAGE_SIZE: usize = 0x1000;
+
+/// Snapshot of one memory region
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    /// Region name (e.g. "FCRAM")
+    pub name: String,
+    /// Address of the first byte
+    pub base: u32,
+    pub data: Vec<u8>,
+}
+
+/// Snapshot of emulator state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveState {
+    /// ARM9 registers, in `ArmRegister::ALL` order
+    pub arm9_registers: [u32; ArmRegister::ALL.len()],
+    /// ARM11 registers, in `ArmRegister::ALL` order
+    pub arm11_registers: [u32; ArmRegister::ALL.len()],
+    pub memory: Vec<MemorySnapshot>,
+}
+
+impl SaveState {
+    /// Registers of `core`, in `ArmRegister::ALL` order
+    pub fn registers(&self, core: Core) -> &[u32; ArmRegister::ALL.len()] {
+        match core {
+            Core::Arm9 => &self.arm9_registers,
+            Core::Arm11 => &self.arm11_registers,
+        }
+    }
+
+    /// Write the save state to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read a save state from a file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::read_from(io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// Serialize the save state
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        for value in self.arm9_registers.iter().chain(&self.arm11_registers) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&(self.memory.len() as u32).to_le_bytes())?;
+        for region in &self.memory {
+            writer.write_all(&(region.name.len() as u32).to_le_bytes())?;
+            writer.write_all(region.name.as_bytes())?;
+            writer.write_all(&region.base.to_le_bytes())?;
+            writer.write_all(&(region.data.len() as u32).to_le_bytes())?;
+            writer.write_all(&region.data)?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize a save state
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a save state (bad magic)".to_string()));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported save state version {} (expected {})",
+                version, VERSION
+            )));
+        }
+
+        let mut arm9_registers = [0u32; ArmRegister::ALL.len()];
+        let mut arm11_registers = [0u32; ArmRegister::ALL.len()];
+        for value in arm9_registers.iter_mut().chain(arm11_registers.iter_mut()) {
+            *value = read_u32(&mut reader)?;
+        }
+
+        let region_count = read_length(&mut reader, "region count", MAX_REGIONS)?;
+        let mut memory = Vec::new();
+        for _ in 0..region_count {
+            let name_len = read_length(&mut reader, "region name length", MAX_NAME_LEN)?;
+            let name = read_bytes(&mut reader, name_len)?;
+            let name = String::from_utf8(name)
+                .map_err(|e| invalid_data(format!("invalid region name: {}", e)))?;
+            let base = read_u32(&mut reader)?;
+            let size = read_length(&mut reader, "region size", MAX_REGION_SIZE)?;
+            let data = read_bytes(&mut reader, size)?;
+            memory.push(MemorySnapshot { name, base, data });
+        }
+
+        Ok(Self {
+            arm9_registers,
+            arm11_registers,
+            memory,
+        })
+    }
+
+    /// Compare against a later state, reporting changed registers and memory pages
+    pub fn diff(&self, other: &SaveState) -> StateDiff {
+        let mut registers = Vec::new();
+        for core in [Core::Arm9, Core::Arm11] {
+            let (before, after) = (self.registers(core), other.registers(core));
+            for (i, reg) in ArmRegister::ALL.into_iter().enumerate() {
+                if before[i] != after[i] {
+                    registers.push(RegisterChange {
+                        core,
+                        reg,
+                        before: before[i],
+                        after: after[i],
+                    });
+                }
+            }
+        }
+
+        let mut memory = Vec::new();
+        for region in &self.memory {
+            let Some(other_region) = other.memory.iter().find(|r| r.name == region.name) else {
+                memory.push(MemoryDiff::Missing {
+                    name: region.name.clone(),
+                });
+                continue;
+            };
+            if region.base != other_region.base || region.data.len() != other_region.data.len() {
+                memory.push(MemoryDiff::Layout {
+                    name: region.name.clone(),
+                });
+                continue;
+            }
+            let ranges = diff_pages(region.base, &region.data, &other_region.data);
+            if !ranges.is_empty() {
+                memory.push(MemoryDiff::Changed {
+                    name: region.name.clone(),
+                    ranges,
+                });
+            }
+        }
+        for region in &other.memory {
+            if !self.memory.iter().any(|r| r.name == region.name) {
+                memory.push(MemoryDiff::Missing {
+                    name: region.name.clone(),
+                });
+            }
+        }
+
+        StateDiff { registers, memory }
+    }
+}
+
+/// A register whose value differs between two save states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange {
+    pub core: Core,
+    pub reg: ArmRegister,
+    pub before: u32,
+    pub after: u32,
+}
+
+/// A run of consecutive memory pages with differing contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangedRange {
+    /// First address of the range
+    pub start: u32,
+    /// End address of the range (exclusive)
+    pub end: u32,
+    /// Number of bytes that differ within the range
+    pub changed_bytes: usize,
+}
+
+/// Differences in one memory region between two save states
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryDiff {
+    /// Pages whose contents changed
+    Changed {
+        name: String,
+        ranges: Vec<ChangedRange>,
+    },
+    /// The region has a different base address or size in the two states
+    Layout { name: String },
+    /// The region is only present in one of the states
+    Missing { name: String },
+}
+
+/// Differences between two save states
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    pub registers: Vec<RegisterChange>,
+    pub memory: Vec<MemoryDiff>,
+}
+
+impl StateDiff {
+    /// Whether the two states are identical
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.memory.is_empty()
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "States are identical");
+        }
+
+        for core in [Core::Arm9, Core::Arm11] {
+            let changes: Vec<_> = self.registers.iter().filter(|c| c.core == core).collect();
+            if changes.is_empty() {
+                writeln!(f, "{:?} registers: unchanged", core)?;
+                continue;
+            }
+            writeln!(f, "{:?} registers:", core)?;
+            for change in changes {
+                writeln!(
+                    f,
+                    "  {:?}: {:#010X} -> {:#010X}",
+                    change.reg, change.before, change.after
+                )?;
+            }
+        }
+
+        for diff in &self.memory {
+            match diff {
+                MemoryDiff::Changed { name, ranges } => {
+                    let total: usize = ranges.iter().map(|r| r.changed_bytes).sum();
+                    writeln!(
+                        f,
+                        "{}: {} bytes differ in {} ranges",
+                        name,
+                        total,
+                        ranges.len()
+                    )?;
+                    for range in ranges {
+                        writeln!(
+                            f,
+                            "  {:#010X} - {:#010X}: {} bytes",
+                            range.start, range.end, range.changed_bytes
+                        )?;
+                    }
+                }
+                MemoryDiff::Layout { name } => {
+                    writeln!(f, "{}: base address or size differs", name)?;
+                }
+                MemoryDiff::Missing { name } => {
+                    writeln!(f, "{}: only present in one state", name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare two equally sized buffers page by page, merging adjacent changed pages
+fn diff_pages(base: u32, before: &[u8], after: &[u8]) -> Vec<ChangedRange> {
+    let mut ranges: Vec<ChangedRange> = Vec::new();
+    for (page, (a, b)) in before
+        .chunks(DIFF_PAGE_SIZE)
+        .zip(after.chunks(DIFF_PAGE_SIZE))
+        .enumerate()
+    {
+        let changed_bytes = a.iter().zip(b).filter(|(x, y)| x != y).count();
+        if changed_bytes == 0 {
+            continue;
+        }
+        let start = base + (page * DIFF_PAGE_SIZE) as u32;
+        let end = start + a.len() as u32;
+        match ranges.last_mut() {
+            Some(last) if last.end == start => {
+                last.end = end;
+                last.changed_bytes += changed_bytes;
+            }
+            _ => ranges.push(ChangedRange {
+                start,
+                end,
+                changed_bytes,
+            }),
+        }
+    }
+    ranges
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Read a length field, rejecting values above `max` before anything is allocated
+fn read_length(reader: &mut impl Read, what: &str, max: u32) -> io::Result<u32> {
+    let len = read_u32(reader)?;
+    if len > max {
+        return Err(invalid_data(format!(
+            "{} {} exceeds the maximum of {}",
+            what, len, max
+        )));
+    }
+    Ok(len)
+}
+
+/// Read exactly `len` bytes, growing the buffer only as input arrives so a truncated
+/// file fails without allocating the full length up front
+fn read_bytes(reader: &mut impl Read, len: u32) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "save state ended early",
+        ));
+    }
+    Ok(bytes)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> SaveState {
+        SaveState {
+            arm9_registers: [9; ArmRegister::ALL.len()],
+            arm11_registers: [11; ArmRegister::ALL.len()],
+            memory: vec![MemorySnapshot {
+                name: "VRAM".to_string(),
+                base: 0x1800_0000,
+                data: vec![0xAB; 0x20],
+            }],
+        }
+    }
+
+    fn serialize(state: &SaveState) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        state.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Offset of the first region's size field in a serialized [`state`]
+    fn size_offset() -> usize {
+        MAGIC.len() + 4 + 2 * ArmRegister::ALL.len() * 4 + 4 + 4 + "VRAM".len() + 4
+    }
+
+    #[test]
+    fn round_trips() {
+        let state = state();
+        let read = SaveState::read_from(serialize(&state).as_slice()).unwrap();
+        assert_eq!(read, state);
+    }
+
+    #[test]
+    fn oversized_region_is_rejected() {
+        let mut bytes = serialize(&state());
+        let offset = size_offset();
+        bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = SaveState::read_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn oversized_region_count_is_rejected() {
+        let mut bytes = serialize(&state());
+        let offset = MAGIC.len() + 4 + 2 * ArmRegister::ALL.len() * 4;
+        bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = SaveState::read_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_region_is_rejected() {
+        let mut bytes = serialize(&state());
+        let offset = size_offset();
+        bytes[offset..offset + 4].copy_from_slice(&MAX_REGION_SIZE.to_le_bytes());
+        let err = SaveState::read_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
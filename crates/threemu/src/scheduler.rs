@@ -3,8 +3,10 @@
 //! This module handles the interleaving of ARM9 and ARM11 execution,
 //! maintaining timing ratios based on real hardware clock speeds.
 
+use crate::cpu_types::{Core, CoreSelection};
 use crate::mmio;
-use tracing::error;
+use std::time::Duration;
+use tracing::{error, info, warn};
 use unicorn_engine::{RegisterARM, Unicorn};
 
 // ================================================================================================
@@ -33,10 +35,21 @@ pub const ARM11_INSTRUCTIONS_PER_FRAME: usize = ARM11_FREQ_HZ / TARGET_FPS; // ~
 pub const ARM9_INSTRUCTIONS_PER_FRAME: usize = ARM9_FREQ_HZ / TARGET_FPS; // ~2,233,333
 
 /// ARM11 instructions to execute per quantum
-pub const ARM11_INSTRUCTIONS_PER_QUANTUM: usize = ARM11_INSTRUCTIONS_PER_FRAME / QUANTUMS_PER_FRAME; // ~446,667
+pub const ARM11_INSTRUCTIONS_PER_QUANTUM: usize = instructions_per_quantum(ARM11_FREQ_HZ); // ~446,667
 
 /// ARM9 instructions to execute per quantum
-pub const ARM9_INSTRUCTIONS_PER_QUANTUM: usize = ARM9_INSTRUCTIONS_PER_FRAME / QUANTUMS_PER_FRAME; // ~223,333
+pub const ARM9_INSTRUCTIONS_PER_QUANTUM: usize = instructions_per_quantum(ARM9_FREQ_HZ); // ~223,333
+
+/// Lowest supported CPU frequency in Hz: one instruction per quantum
+///
+/// Below this a core's quantum would be 0 instructions, which Unicorn treats as no
+/// limit at all.
+pub const MIN_FREQ_HZ: usize = TARGET_FPS * QUANTUMS_PER_FRAME;
+
+/// Instructions a core running at `freq_hz` executes per quantum
+pub const fn instructions_per_quantum(freq_hz: usize) -> usize {
+    freq_hz / TARGET_FPS / QUANTUMS_PER_FRAME
+}
 
 /// Result of running a single quantum
 #[derive(Debug, Clone, PartialEq)]
@@ -47,9 +60,43 @@ pub enum QuantumResult {
     Error(String),
 }
 
+/// What each core did during one quantum
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuantumProgress {
+    /// Instructions ARM9 executed
+    pub arm9_ran: usize,
+    /// Cycles ARM9 spent in bootrom waits, which advance its clock without
+    /// executing instructions
+    pub arm9_waited: usize,
+    /// Instructions ARM11 executed
+    pub arm11_ran: usize,
+    /// ARM9 stopped during this quantum (stop PC, instruction limit, or hang)
+    pub arm9_stopped: bool,
+    /// ARM11 stopped during this quantum (stop PC, instruction limit, or hang)
+    pub arm11_stopped: bool,
+}
+
+/// Which scheduler stop condition ended a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopDetail {
+    /// The stop PCs were reached (all of them, or any with `stop_on_any_pc`)
+    StopPcs,
+    /// The total instruction limit was reached first
+    MaxInstructions,
+    /// The frame limit was reached first
+    MaxFrames,
+    /// Every core stopped on its own (per-core instruction limit, hang, or disabled)
+    /// before the stop PCs were reached
+    CoresStopped,
+}
+
 /// Configuration for the scheduler
 #[derive(Debug, Clone)]
 pub struct SchedulerConfig {
+    /// ARM9 clock frequency in Hz, used to convert instructions to emulated time
+    pub arm9_freq_hz: usize,
+    /// ARM11 clock frequency in Hz, used to convert instructions to emulated time
+    pub arm11_freq_hz: usize,
     /// ARM9 instructions per quantum
     pub arm9_quantum: usize,
     /// ARM11 instructions per quantum
@@ -58,18 +105,54 @@ pub struct SchedulerConfig {
     pub arm9_stop_pc: Option<u64>,
     /// Stop when ARM11 PC reaches this address
     pub arm11_stop_pc: Option<u64>,
+    /// Stop as soon as any core reaches its stop PC, instead of once every core
+    /// with a stop PC has reached it
+    pub stop_on_any_pc: bool,
     /// Stop after this many total instructions
     pub max_instructions: Option<usize>,
+    /// Stop once this many frames (of `QUANTUMS_PER_FRAME` quanta) have completed
+    pub max_frames: Option<usize>,
+    /// Stop ARM9 after it has run this many instructions, letting ARM11 continue
+    pub arm9_max_instructions: Option<usize>,
+    /// Stop ARM11 after it has run this many instructions, letting ARM9 continue
+    pub arm11_max_instructions: Option<usize>,
+    /// Stop a core whose PC is unchanged across this many consecutive quanta,
+    /// treating it as hung (e.g. spinning on `b .`)
+    pub hang_detect_quanta: Option<usize>,
+    /// Cores to run; a disabled core counts as stopped from the start
+    pub cores: CoreSelection,
+}
+
+impl SchedulerConfig {
+    /// Derive per-quantum instruction counts from the given CPU frequencies,
+    /// keeping the interleaving ratio equal to the frequency ratio
+    pub fn with_frequencies(arm9_freq_hz: usize, arm11_freq_hz: usize) -> Self {
+        Self {
+            arm9_freq_hz,
+            arm11_freq_hz,
+            arm9_quantum: instructions_per_quantum(arm9_freq_hz),
+            arm11_quantum: instructions_per_quantum(arm11_freq_hz),
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for SchedulerConfig {
     fn default() -> Self {
         Self {
+            arm9_freq_hz: ARM9_FREQ_HZ,
+            arm11_freq_hz: ARM11_FREQ_HZ,
             arm9_quantum: ARM9_INSTRUCTIONS_PER_QUANTUM,
             arm11_quantum: ARM11_INSTRUCTIONS_PER_QUANTUM,
             arm9_stop_pc: None,
             arm11_stop_pc: None,
+            stop_on_any_pc: false,
             max_instructions: None,
+            max_frames: None,
+            arm9_max_instructions: None,
+            arm11_max_instructions: None,
+            hang_detect_quanta: None,
+            cores: CoreSelection::Both,
         }
     }
 }
@@ -80,21 +163,55 @@ pub struct Scheduler {
     arm9_pc: u64,
     arm11_pc: u64,
     total_executed: usize,
+    arm9_executed: usize,
+    arm11_executed: usize,
+    /// Cycles ARM9 spent in bootrom waits, kept apart from the instruction counts
+    arm9_waited_cycles: usize,
     arm9_stopped: bool,
     arm11_stopped: bool,
+    /// Consecutive quanta that ended at the same PC
+    arm9_same_pc_quanta: usize,
+    arm11_same_pc_quanta: usize,
+    /// PC at which a core was detected as hung
+    arm9_hang_pc: Option<u64>,
+    arm11_hang_pc: Option<u64>,
+    /// Quanta run since the last frame boundary
+    quanta_in_frame: usize,
+    /// Frames completed so far
+    frames_completed: usize,
+    /// Whether the most recent quantum completed a frame
+    frame_ended: bool,
+    /// Core that stopped most recently
+    last_stopped: Option<Core>,
+    /// Stop condition that was met first, while it still holds
+    fired: Option<StopDetail>,
 }
 
 impl Scheduler {
     /// Create a new scheduler
     pub fn new(config: SchedulerConfig, arm9_entry: u64, arm11_entry: u64) -> Self {
-        Self {
-            config,
+        let mut scheduler = Self {
             arm9_pc: arm9_entry,
             arm11_pc: arm11_entry,
             total_executed: 0,
-            arm9_stopped: false,
-            arm11_stopped: false,
-        }
+            arm9_executed: 0,
+            arm11_executed: 0,
+            arm9_waited_cycles: 0,
+            arm9_stopped: !config.cores.runs(Core::Arm9),
+            arm11_stopped: !config.cores.runs(Core::Arm11),
+            config,
+            arm9_same_pc_quanta: 0,
+            arm11_same_pc_quanta: 0,
+            arm9_hang_pc: None,
+            arm11_hang_pc: None,
+            quanta_in_frame: 0,
+            frames_completed: 0,
+            frame_ended: false,
+            last_stopped: None,
+            fired: None,
+        };
+        scheduler.update_fired();
+        scheduler
     }
 
     /// Check if ARM9 is stopped
@@ -127,45 +244,246 @@ impl Scheduler {
         self.total_executed
     }
 
+    /// Get instructions executed by ARM9
+    pub fn arm9_executed(&self) -> usize {
+        self.arm9_executed
+    }
+
+    /// Get instructions executed by ARM11
+    pub fn arm11_executed(&self) -> usize {
+        self.arm11_executed
+    }
+
+    /// Get cycles ARM9 spent in bootrom waits
+    pub fn arm9_waited_cycles(&self) -> usize {
+        self.arm9_waited_cycles
+    }
+
+    /// Emulated time elapsed, counting one cycle per executed instruction plus the
+    /// cycles spent in bootrom waits, at each core's configured frequency
+    ///
+    /// The cores advance in lockstep quanta, so this is the time of whichever core
+    /// has run furthest; a core that stopped early doesn't hold it back.
+    pub fn emulated_time(&self) -> Duration {
+        let core_time = |cycles: usize, freq_hz: usize| {
+            let nanos = cycles as u128 * 1_000_000_000 / freq_hz.max(1) as u128;
+            Duration::from_nanos(nanos as u64)
+        };
+        core_time(
+            self.arm9_executed + self.arm9_waited_cycles,
+            self.config.arm9_freq_hz,
+        )
+        .max(core_time(self.arm11_executed, self.config.arm11_freq_hz))
+    }
+
+    /// PC at which ARM9 was detected as hung, if it was
+    pub fn arm9_hang_pc(&self) -> Option<u64> {
+        self.arm9_hang_pc
+    }
+
+    /// PC at which ARM11 was detected as hung, if it was
+    pub fn arm11_hang_pc(&self) -> Option<u64> {
+        self.arm11_hang_pc
+    }
+
+    /// Number of frames completed (every `QUANTUMS_PER_FRAME` quanta)
+    pub fn frames_completed(&self) -> usize {
+        self.frames_completed
+    }
+
+    /// Whether the most recent quantum completed a frame
+    pub fn frame_ended(&self) -> bool {
+        self.frame_ended
+    }
+
+    /// Count a completed quantum towards the current frame
+    fn advance_frame(&mut self) {
+        self.quanta_in_frame += 1;
+        self.frame_ended = self.quanta_in_frame >= QUANTUMS_PER_FRAME;
+        if self.frame_ended {
+            self.quanta_in_frame = 0;
+            self.frames_completed += 1;
+        }
+    }
+
+    /// Update a core's same-PC counter after a quantum and report whether the core
+    /// now counts as hung
+    fn detect_hang(&self, same_pc_quanta: &mut usize, prev_pc: u64, pc: u64) -> bool {
+        let Some(limit) = self.config.hang_detect_quanta else {
+            return false;
+        };
+        if pc == prev_pc {
+            *same_pc_quanta += 1;
+        } else {
+            *same_pc_quanta = 0;
+        }
+        *same_pc_quanta >= limit
+    }
+
     /// Check if any stop condition is met
     pub fn check_stop_conditions(&self) -> bool {
-        // If both cores are stopped, we're done
-        if self.arm9_stopped && self.arm11_stopped {
-            return true;
+        self.stop_detail().is_some()
+    }
+
+    /// The stop condition that is met, if any
+    ///
+    /// This is the condition that fired first, as long as it still holds; a limit
+    /// reached after the stop PCs (or the other way around) doesn't replace it. When
+    /// several are first met in the same quantum, reaching the stop PCs takes
+    /// precedence, so a run that reached them on its last quantum isn't reported as
+    /// hitting a limit.
+    pub fn stop_detail(&self) -> Option<StopDetail> {
+        self.fired
+    }
+
+    /// Hung core whose stop stopped every core, if the run ended that way
+    ///
+    /// A core that hung while the other kept running until some other condition
+    /// fired isn't reported.
+    pub fn stopping_hang(&self) -> Option<(Core, u64)> {
+        if self.fired != Some(StopDetail::CoresStopped) {
+            return None;
         }
+        match self.last_stopped? {
+            Core::Arm9 => self.arm9_hang_pc.map(|pc| (Core::Arm9, pc)),
+            Core::Arm11 => self.arm11_hang_pc.map(|pc| (Core::Arm11, pc)),
+        }
+    }
 
-        // Check ARM9 PC stop condition
-        if let Some(stop_pc) = self.config.arm9_stop_pc
-            && self.arm9_pc == stop_pc
-        {
-            return true;
+    /// Keep the recorded stop condition while it holds, otherwise record the first
+    /// one that is met now
+    fn update_fired(&mut self) {
+        self.fired = self
+            .fired
+            .filter(|&detail| self.condition_met(detail))
+            .or_else(|| {
+                [
+                    StopDetail::StopPcs,
+                    StopDetail::MaxInstructions,
+                    StopDetail::MaxFrames,
+                    StopDetail::CoresStopped,
+                ]
+                .into_iter()
+                .find(|&detail| self.condition_met(detail))
+            });
+    }
+
+    /// Whether the stop condition `detail` is met
+    fn condition_met(&self, detail: StopDetail) -> bool {
+        match detail {
+            StopDetail::StopPcs => self.stop_pcs_reached(),
+            StopDetail::MaxInstructions => self
+                .config
+                .max_instructions
+                .is_some_and(|max| self.total_executed >= max),
+            StopDetail::MaxFrames => self
+                .config
+                .max_frames
+                .is_some_and(|max| self.frames_completed >= max),
+            StopDetail::CoresStopped => self.arm9_stopped && self.arm11_stopped,
         }
+    }
 
-        // Check ARM11 PC stop condition
-        if let Some(stop_pc) = self.config.arm11_stop_pc
-            && self.arm11_pc == stop_pc
-        {
-            return true;
+    /// Whether the stop PCs have been reached
+    fn stop_pcs_reached(&self) -> bool {
+        // Check PC stop conditions (ignored for a disabled core): by default every
+        // core given a stop PC must reach it, since a core that does simply waits there
+        let arm9_reached = self
+            .config
+            .arm9_stop_pc
+            .filter(|_| self.config.cores.runs(Core::Arm9))
+            .map(|_| self.is_arm9_stop_pc(self.arm9_pc));
+        let arm11_reached = self
+            .config
+            .arm11_stop_pc
+            .filter(|_| self.config.cores.runs(Core::Arm11))
+            .map(|_| self.is_arm11_stop_pc(self.arm11_pc));
+        let mut targets = [arm9_reached, arm11_reached]
+            .into_iter()
+            .flatten()
+            .peekable();
+        if targets.peek().is_none() {
+            return false;
+        }
+        if self.config.stop_on_any_pc {
+            targets.any(|reached| reached)
+        } else {
+            targets.all(|reached| reached)
         }
+    }
 
-        // Check max instructions
-        if let Some(max) = self.config.max_instructions
-            && self.total_executed >= max
-        {
-            return true;
+    /// Stop PC of `core`, if it has one
+    pub fn stop_pc(&self, core: Core) -> Option<u64> {
+        match core {
+            Core::Arm9 => self.config.arm9_stop_pc,
+            Core::Arm11 => self.config.arm11_stop_pc,
         }
+    }
 
-        false
+    /// Replace the stop PC of `core`, from the next quantum on
+    ///
+    /// A core halted at its old stop PC (and not also hung or out of instructions)
+    /// resumes from there, so a stop PC can be moved or removed between runs.
+    pub fn set_stop_pc(&mut self, core: Core, pc: Option<u64>) {
+        let (stop_pc, stopped, current_pc, hang_pc, executed, limit) = match core {
+            Core::Arm9 => (
+                &mut self.config.arm9_stop_pc,
+                &mut self.arm9_stopped,
+                self.arm9_pc,
+                self.arm9_hang_pc,
+                self.arm9_executed,
+                self.config.arm9_max_instructions,
+            ),
+            Core::Arm11 => (
+                &mut self.config.arm11_stop_pc,
+                &mut self.arm11_stopped,
+                self.arm11_pc,
+                self.arm11_hang_pc,
+                self.arm11_executed,
+                self.config.arm11_max_instructions,
+            ),
+        };
+        let halted_at_stop_pc = *stopped
+            && *stop_pc == Some(current_pc)
+            && hang_pc.is_none()
+            && limit.is_none_or(|limit| executed < limit);
+        *stop_pc = pc;
+        if halted_at_stop_pc && pc != Some(current_pc) {
+            info!("{:?} resuming from its old stop PC {:#X}", core, current_pc);
+            *stopped = false;
+        }
+        self.update_fired();
     }
 
     /// Check if a specific PC matches any stop condition for ARM9
     fn is_arm9_stop_pc(&self, pc: u64) -> bool {
-        self.config.arm9_stop_pc == Some(pc)
+        self.config.cores.runs(Core::Arm9) && self.config.arm9_stop_pc == Some(pc)
     }
 
     /// Check if a specific PC matches any stop condition for ARM11
     fn is_arm11_stop_pc(&self, pc: u64) -> bool {
-        self.config.arm11_stop_pc == Some(pc)
+        self.config.cores.runs(Core::Arm11) && self.config.arm11_stop_pc == Some(pc)
+    }
+
+    /// Length of a core's next quantum, shortened so that a core with `executed`
+    /// instructions so far doesn't run past `limit`
+    fn quantum_len(quantum: usize, executed: usize, limit: Option<usize>) -> usize {
+        limit.map_or(quantum, |limit| quantum.min(limit.saturating_sub(executed)))
+    }
+
+    /// Invalidate `dst`'s translated code for any shared memory written by `src`
+    fn sync_shared_writes(
+        src: &mut Unicorn<'static, mmio::EmulatorState>,
+        dst: &mut Unicorn<'static, mmio::EmulatorState>,
+    ) {
+        if let Some((start, end)) = src.get_data_mut().shared_writes.take()
+            && let Err(e) = dst.ctl_remove_cache(start, end)
+        {
+            warn!(
+                "Failed to flush translation cache for {:#X} - {:#X}: {:?}",
+                start, end, e
+            );
+        }
     }
 
     /// Run a single quantum of execution for both cores
@@ -174,13 +492,53 @@ impl Scheduler {
         arm9_emu: &mut Unicorn<'static, mmio::EmulatorState>,
         arm11_emu: &mut Unicorn<'static, mmio::EmulatorState>,
     ) -> QuantumResult {
+        match self.run_quantum_detailed(arm9_emu, arm11_emu) {
+            Ok(_) => QuantumResult::Continue,
+            Err(e) => QuantumResult::Error(e),
+        }
+    }
+
+    /// Run a single quantum of execution for both cores, reporting what each core
+    /// did during it
+    pub fn run_quantum_detailed(
+        &mut self,
+        arm9_emu: &mut Unicorn<'static, mmio::EmulatorState>,
+        arm11_emu: &mut Unicorn<'static, mmio::EmulatorState>,
+    ) -> Result<QuantumProgress, String> {
+        let (arm9_before, arm11_before) = (self.arm9_executed, self.arm11_executed);
+        let arm9_waited_before = self.arm9_waited_cycles;
+        let (arm9_was_stopped, arm11_was_stopped) = (self.arm9_stopped, self.arm11_stopped);
+
+        // Stop a core that has used up its own instruction limit
+        if let Some(limit) = self.config.arm9_max_instructions
+            && !self.arm9_stopped
+            && self.arm9_executed >= limit
+        {
+            info!("ARM9 reached its instruction limit ({})", limit);
+            self.arm9_stopped = true;
+        }
+        if let Some(limit) = self.config.arm11_max_instructions
+            && !self.arm11_stopped
+            && self.arm11_executed >= limit
+        {
+            info!("ARM11 reached its instruction limit ({})", limit);
+            self.arm11_stopped = true;
+        }
+
         // Run ARM9 quantum (only if not already stopped)
         if !self.arm9_stopped {
-            let _span = tracing::error_span!("ARM9").entered();
+            let _span = Core::Arm9.span().entered();
             let arm9_stop = self.config.arm9_stop_pc.unwrap_or(u64::MAX);
-            match arm9_emu.emu_start(self.arm9_pc, arm9_stop, 0, self.config.arm9_quantum) {
+            let prev_pc = self.arm9_pc;
+            let quantum = Self::quantum_len(
+                self.config.arm9_quantum,
+                self.arm9_executed,
+                self.config.arm9_max_instructions,
+            );
+            match arm9_emu.emu_start(self.arm9_pc, arm9_stop, 0, quantum) {
                 Ok(_) => {
-                    self.total_executed += self.config.arm9_quantum;
+                    self.total_executed += quantum;
+                    self.arm9_executed += quantum;
                     self.arm9_pc = arm9_emu.reg_read(RegisterARM::PC).unwrap();
                 }
                 Err(e) => {
@@ -190,7 +548,7 @@ impl Scheduler {
                         self.arm9_stopped = true;
                     } else {
                         error!("{:?}", e);
-                        return QuantumResult::Error(format!("ARM9: {:?}", e));
+                        return Err(format!("ARM9: {:?}", e));
                     }
                 }
             }
@@ -198,16 +556,38 @@ impl Scheduler {
             // Check if ARM9 hit a stop condition after successful execution
             if self.is_arm9_stop_pc(self.arm9_pc) {
                 self.arm9_stopped = true;
+            } else {
+                let mut same_pc_quanta = self.arm9_same_pc_quanta;
+                if self.detect_hang(&mut same_pc_quanta, prev_pc, self.arm9_pc) {
+                    warn!("ARM9 suspected hang at PC {:#X}", self.arm9_pc);
+                    self.arm9_hang_pc = Some(self.arm9_pc);
+                    self.arm9_stopped = true;
+                }
+                self.arm9_same_pc_quanta = same_pc_quanta;
             }
+
+            // Bootrom waits return immediately, so account for the time they would
+            // have taken on the cycle clock (they execute no instructions)
+            let waited = std::mem::take(&mut arm9_emu.get_data_mut().waited_cycles) as usize;
+            self.arm9_waited_cycles += waited;
+
+            Self::sync_shared_writes(arm9_emu, arm11_emu);
         }
 
         // Run ARM11 quantum (only if not already stopped)
         if !self.arm11_stopped {
-            let _span = tracing::error_span!("ARM11").entered();
+            let _span = Core::Arm11.span().entered();
             let arm11_stop = self.config.arm11_stop_pc.unwrap_or(u64::MAX);
-            match arm11_emu.emu_start(self.arm11_pc, arm11_stop, 0, self.config.arm11_quantum) {
+            let prev_pc = self.arm11_pc;
+            let quantum = Self::quantum_len(
+                self.config.arm11_quantum,
+                self.arm11_executed,
+                self.config.arm11_max_instructions,
+            );
+            match arm11_emu.emu_start(self.arm11_pc, arm11_stop, 0, quantum) {
                 Ok(_) => {
-                    self.total_executed += self.config.arm11_quantum;
+                    self.total_executed += quantum;
+                    self.arm11_executed += quantum;
                     self.arm11_pc = arm11_emu.reg_read(RegisterARM::PC).unwrap();
                 }
                 Err(e) => {
@@ -217,7 +597,7 @@ impl Scheduler {
                         self.arm11_stopped = true;
                     } else {
                         error!("{:?}", e);
-                        return QuantumResult::Error(format!("ARM11: {:?}", e));
+                        return Err(format!("ARM11: {:?}", e));
                     }
                 }
             }
@@ -225,9 +605,187 @@ impl Scheduler {
             // Check if ARM11 hit a stop condition after successful execution
             if self.is_arm11_stop_pc(self.arm11_pc) {
                 self.arm11_stopped = true;
+            } else {
+                let mut same_pc_quanta = self.arm11_same_pc_quanta;
+                if self.detect_hang(&mut same_pc_quanta, prev_pc, self.arm11_pc) {
+                    warn!("ARM11 suspected hang at PC {:#X}", self.arm11_pc);
+                    self.arm11_hang_pc = Some(self.arm11_pc);
+                    self.arm11_stopped = true;
+                }
+                self.arm11_same_pc_quanta = same_pc_quanta;
             }
+
+            arm11_emu.get_data_mut().gpu.advance_scanout();
+            Self::sync_shared_writes(arm11_emu, arm9_emu);
+        }
+
+        if self.arm11_stopped && !arm11_was_stopped {
+            self.last_stopped = Some(Core::Arm11);
+        } else if self.arm9_stopped && !arm9_was_stopped {
+            self.last_stopped = Some(Core::Arm9);
         }
 
-        QuantumResult::Continue
+        self.advance_frame();
+        self.update_fired();
+        Ok(QuantumProgress {
+            arm9_ran: self.arm9_executed - arm9_before,
+            arm9_waited: self.arm9_waited_cycles - arm9_waited_before,
+            arm11_ran: self.arm11_executed - arm11_before,
+            arm9_stopped: self.arm9_stopped && !arm9_was_stopped,
+            arm11_stopped: self.arm11_stopped && !arm11_was_stopped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waited_cycles_advance_time_but_not_instruction_counts() {
+        let config = SchedulerConfig {
+            max_instructions: Some(1_000),
+            ..SchedulerConfig::with_frequencies(1_000_000, 1_000_000)
+        };
+        let mut scheduler = Scheduler::new(config, 0, 0);
+        scheduler.arm9_executed = 500;
+        scheduler.total_executed = 500;
+        scheduler.arm9_waited_cycles = 1_500;
+
+        assert_eq!(scheduler.arm9_executed(), 500);
+        assert_eq!(scheduler.total_executed(), 500);
+        assert_eq!(scheduler.arm9_waited_cycles(), 1_500);
+        assert_eq!(scheduler.stop_detail(), None);
+        assert_eq!(scheduler.emulated_time(), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn first_condition_to_fire_is_reported() {
+        let config = SchedulerConfig {
+            arm9_stop_pc: Some(0x100),
+            max_instructions: Some(1_000),
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config, 0, 0);
+        assert_eq!(scheduler.stop_detail(), None);
+
+        scheduler.arm9_pc = 0x100;
+        scheduler.update_fired();
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::StopPcs));
+
+        // A limit reached afterwards doesn't replace it
+        scheduler.total_executed = 1_000;
+        scheduler.update_fired();
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::StopPcs));
+
+        // Until the stop PC moves away, leaving the limit as the condition that holds
+        scheduler.set_stop_pc(Core::Arm9, Some(0x200));
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::MaxInstructions));
+    }
+
+    #[test]
+    fn disabled_core_starts_stopped_and_its_stop_pc_is_ignored() {
+        let config = SchedulerConfig {
+            arm9_stop_pc: Some(0x100),
+            arm11_stop_pc: Some(0x200),
+            cores: CoreSelection::Arm9Only,
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config, 0, 0);
+        assert!(!scheduler.arm9_stopped());
+        assert!(scheduler.arm11_stopped());
+        assert_eq!(scheduler.stop_detail(), None);
+
+        // ARM9 reaching its stop PC is enough, although ARM11 never reaches its own
+        scheduler.arm9_pc = 0x100;
+        scheduler.arm9_stopped = true;
+        scheduler.update_fired();
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::StopPcs));
+
+        // With only the disabled core's stop PC set, no PC ends the run
+        let config = SchedulerConfig {
+            arm11_stop_pc: Some(0x200),
+            cores: CoreSelection::Arm9Only,
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config, 0, 0x200);
+        assert_eq!(scheduler.stop_detail(), None);
+        scheduler.arm9_stopped = true;
+        scheduler.update_fired();
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::CoresStopped));
+    }
+
+    #[test]
+    fn per_core_limit_shortens_the_last_quantum() {
+        assert_eq!(Scheduler::quantum_len(1_000, 0, None), 1_000);
+        assert_eq!(Scheduler::quantum_len(1_000, 0, Some(2_500)), 1_000);
+        assert_eq!(Scheduler::quantum_len(1_000, 2_000, Some(2_500)), 500);
+        assert_eq!(Scheduler::quantum_len(1_000, 2_500, Some(2_500)), 0);
+    }
+
+    #[test]
+    fn core_at_its_limit_stays_stopped_when_its_stop_pc_moves() {
+        let config = SchedulerConfig {
+            arm9_stop_pc: Some(0x100),
+            arm9_max_instructions: Some(1_000),
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config, 0x100, 0);
+        scheduler.arm9_executed = 1_000;
+        scheduler.arm9_stopped = true;
+        scheduler.set_stop_pc(Core::Arm9, None);
+        assert!(scheduler.arm9_stopped());
+        // ARM11 isn't limited by ARM9's cap
+        assert!(!scheduler.arm11_stopped());
+        assert_eq!(scheduler.stop_detail(), None);
+    }
+
+    #[test]
+    fn frame_limit_fires_once_enough_quanta_have_run() {
+        let config = SchedulerConfig {
+            max_frames: Some(2),
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config, 0, 0);
+        for quantum in 1..=2 * QUANTUMS_PER_FRAME {
+            assert_eq!(scheduler.stop_detail(), None, "quantum {}", quantum);
+            scheduler.advance_frame();
+            scheduler.update_fired();
+            assert_eq!(scheduler.frame_ended(), quantum % QUANTUMS_PER_FRAME == 0);
+        }
+        assert_eq!(scheduler.frames_completed(), 2);
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::MaxFrames));
+    }
+
+    #[test]
+    fn hang_is_only_reported_when_it_stopped_the_last_core() {
+        let config = SchedulerConfig {
+            max_instructions: Some(1_000),
+            hang_detect_quanta: Some(2),
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config, 0, 0);
+
+        // ARM9 hangs while ARM11 keeps running into the instruction limit
+        scheduler.arm9_hang_pc = Some(0x100);
+        scheduler.arm9_stopped = true;
+        scheduler.last_stopped = Some(Core::Arm9);
+        scheduler.total_executed = 1_000;
+        scheduler.arm11_stopped = true;
+        scheduler.last_stopped = Some(Core::Arm11);
+        scheduler.update_fired();
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::MaxInstructions));
+        assert_eq!(scheduler.stopping_hang(), None);
+
+        // ARM11 hanging last stops the run
+        let mut scheduler = Scheduler::new(SchedulerConfig::default(), 0, 0);
+        scheduler.arm9_stopped = true;
+        scheduler.last_stopped = Some(Core::Arm9);
+        scheduler.arm11_hang_pc = Some(0x200);
+        scheduler.arm11_stopped = true;
+        scheduler.last_stopped = Some(Core::Arm11);
+        scheduler.update_fired();
+        assert_eq!(scheduler.stop_detail(), Some(StopDetail::CoresStopped));
+        assert_eq!(scheduler.stopping_hang(), Some((Core::Arm11, 0x200)));
     }
 }
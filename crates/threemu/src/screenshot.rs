@@ -0,0 +1,112 @@
+//! Screen dumps for headless runs.
+//!
+//! Screens can be written either converted to RGB8 (as binary PPM images) or as the
+//! raw framebuffer bytes the GPU scans out, together with a small JSON sidecar
+//! describing the layout, for inspecting format and stride problems that the
+//! conversion would hide.
+
+use crate::core::EmulatorCore;
+use crate::display::{Screen, render_screen_to_rgb};
+use oxidiz3ds_hw::specs::display;
+use std::path::Path;
+use tracing::info;
+
+/// How screens are written by [`dump_screens`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DumpFormat {
+    /// Converted to RGB8 in display orientation, as a binary PPM image
+    #[default]
+    Rgb,
+    /// Native framebuffer bytes plus a JSON sidecar with address, size, format and stride
+    Raw,
+}
+
+/// Write both screens to `dir` as `top`/`bottom` files in the given format
+///
+/// Screens without a configured framebuffer are skipped.
+pub fn dump_screens(emulator: &EmulatorCore, dir: &Path, format: DumpFormat) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+
+    for (screen, name) in [(Screen::Top, "top"), (Screen::Bottom, "bottom")] {
+        if screen.framebuffer_addr(emulator) == 0 {
+            info!("{:?} screen has no framebuffer, not dumping it", screen);
+            continue;
+        }
+        match format {
+            DumpFormat::Rgb => {
+                let path = dir.join(format!("{}.ppm", name));
+                let mut image =
+                    format!("P6\n{} {}\n255\n", screen.width(), screen.height()).into_bytes();
+                image.extend(render_screen_to_rgb(emulator, screen));
+                write(&path, &image)?;
+            }
+            DumpFormat::Raw => dump_raw(emulator, screen, dir, name)?,
+        }
+    }
+    Ok(())
+}
+
+/// Write a screen's framebuffer bytes and layout sidecar
+///
+/// The framebuffer is stored rotated: each of its `height` lines is one display
+/// column, `width` pixels long and `stride` bytes apart.
+fn dump_raw(emulator: &EmulatorCore, screen: Screen, dir: &Path, name: &str) -> Result<(), String> {
+    let addr = screen.framebuffer_addr(emulator);
+    let (format, stride) = screen.framebuffer_layout(emulator);
+    let (width, height) = (screen.height(), screen.width());
+    let stride = if stride != 0 {
+        stride
+    } else {
+        // Unknown formats are rendered as RGB8
+        let bytes_per_pixel = format
+            .bytes_per_pixel()
+            .unwrap_or(display::BYTES_PER_PIXEL_RGB8);
+        width * bytes_per_pixel as u32
+    };
+
+    let data = framebuffer_bytes(emulator, addr, stride, height)?;
+    write(&dir.join(format!("{}.bin", name)), data)?;
+
+    let sidecar = format!(
+        "{{\"screen\":\"{}\",\"address\":{},\"width\":{},\"height\":{},\"format\":\"{:?}\",\"stride\":{}}}\n",
+        name, addr, width, height, format, stride
+    );
+    write(&dir.join(format!("{}.json", name)), sidecar.as_bytes())
+}
+
+/// The `stride * height` bytes of the framebuffer at `addr`, which must lie within
+/// one shared RAM region
+///
+/// The address and stride come from guest-programmed registers, so a bogus value is
+/// reported rather than read.
+fn framebuffer_bytes(
+    emulator: &EmulatorCore,
+    addr: u32,
+    stride: u32,
+    height: u32,
+) -> Result<&[u8], String> {
+    let len = stride.checked_mul(height).ok_or_else(|| {
+        format!(
+            "Framebuffer stride {:#X} is too large for {} lines",
+            stride, height
+        )
+    })? as usize;
+    let data = emulator.read_phys(addr, len).ok_or_else(|| {
+        format!(
+            "Framebuffer address {:#X} is outside FCRAM, VRAM and AXI WRAM",
+            addr
+        )
+    })?;
+    if data.len() < len {
+        return Err(format!(
+            "Framebuffer at {:#X} ({:#X} bytes) runs past the end of its memory region",
+            addr, len
+        ));
+    }
+    Ok(data)
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), String> {
+    info!("Writing {:?}", path);
+    std::fs::write(path, data).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
@@ -0,0 +1,72 @@
+//! Helpers for golden-image tests (enabled with the `test-utils` feature).
+
+use crate::core::EmulatorCore;
+use crate::display::{Screen, render_screen_to_rgb};
+
+/// Maximum number of mismatched pixel locations listed in a comparison failure
+const MAX_REPORTED_MISMATCHES: usize = 10;
+
+impl EmulatorCore {
+    /// Compare a screen's current contents against a reference PNG, pixel for pixel
+    pub fn assert_screen_matches(&self, screen: Screen, expected_png: &[u8]) -> Result<(), String> {
+        self.assert_screen_matches_with_tolerance(screen, expected_png, 0)
+    }
+
+    /// Compare a screen's current contents against a reference PNG, allowing each color
+    /// channel to differ by up to `tolerance` (e.g. for pixel format conversion rounding)
+    ///
+    /// On failure, the error reports how many pixels differ and where the first few are.
+    pub fn assert_screen_matches_with_tolerance(
+        &self,
+        screen: Screen,
+        expected_png: &[u8],
+        tolerance: u8,
+    ) -> Result<(), String> {
+        let expected = image::load_from_memory_with_format(expected_png, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to decode reference PNG: {:?}", e))?
+            .into_rgb8();
+
+        let (width, height) = (screen.width(), screen.height());
+        if expected.dimensions() != (width, height) {
+            return Err(format!(
+                "Reference image is {}x{}, but the {:?} screen is {}x{}",
+                expected.width(),
+                expected.height(),
+                screen,
+                width,
+                height
+            ));
+        }
+
+        let actual = render_screen_to_rgb(self, screen);
+        let mismatches: Vec<(u32, u32)> = actual
+            .chunks_exact(3)
+            .zip(expected.as_raw().chunks_exact(3))
+            .enumerate()
+            .filter(|(_, (a, e))| {
+                a.iter()
+                    .zip(e.iter())
+                    .any(|(a, e)| a.abs_diff(*e) > tolerance)
+            })
+            .map(|(i, _)| (i as u32 % width, i as u32 / width))
+            .collect();
+
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+
+        let locations: Vec<String> = mismatches
+            .iter()
+            .take(MAX_REPORTED_MISMATCHES)
+            .map(|(x, y)| format!("({}, {})", x, y))
+            .collect();
+        Err(format!(
+            "{:?} screen differs from reference in {} of {} pixels (tolerance {}), first at: {}",
+            screen,
+            mismatches.len(),
+            width * height,
+            tolerance,
+            locations.join(", ")
+        ))
+    }
+}
@@ -0,0 +1,78 @@
+//! End-to-end tests of the headless `threemu-cli` binary
+
+use std::process::{Command, Output};
+use threemu::args::{EXIT_HANG, EXIT_PASS};
+
+/// `b .`
+const SPIN: [u8; 4] = [0xFE, 0xFF, 0xFF, 0xEA];
+
+/// Run `threemu-cli` on `code`, loaded as a raw binary at the start of ARM9 private
+/// WRAM, with `args` appended and info logging enabled
+fn run_cli(name: &str, code: &[u8], args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("threemu-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let binary = dir.join("payload.bin");
+    std::fs::write(&binary, code).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_threemu-cli"))
+        .arg("--raw-binary")
+        .arg(&binary)
+        .args(["--load-addr", "0x08000000", "--entry", "0x08000000"])
+        .args(["--core", "arm9", "--timeout-ms", "10000"])
+        .args(args)
+        .env("RUST_LOG", "info")
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn mmio_log_is_printed_at_exit() {
+    let output = run_cli(
+        "mmio-log",
+        &SPIN,
+        &[
+            "--max-instructions",
+            "10000",
+            "--mmio-log-range",
+            "0x10100000-0x10102000",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(EXIT_PASS));
+    assert!(
+        stdout(&output).contains("ARM9 MMIO log (0x10100000 - 0x10102000): 0 accesses"),
+        "{}",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn device_registers_are_dumped_at_exit() {
+    let args = ["--max-instructions", "10000"];
+    assert!(!stdout(&run_cli("no-dump", &SPIN, &args)).contains("SDMMC registers:"));
+
+    let output = run_cli("dump", &SPIN, &[&args[..], &["--dump-devices"]].concat());
+    assert_eq!(output.status.code(), Some(EXIT_PASS));
+    let stdout = stdout(&output);
+    assert!(stdout.contains("GPU registers:"), "{}", stdout);
+    assert!(stdout.contains("SDMMC registers:"), "{}", stdout);
+}
+
+#[test]
+fn hang_fails_the_run() {
+    // No stop PCs, so nothing else could fail the run
+    let output = run_cli("hang", &SPIN, &["--hang-detect-quanta", "3"]);
+    assert_eq!(output.status.code(), Some(EXIT_HANG));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Arm9 suspected hang at PC 0x8000000"),
+        "{}",
+        stderr
+    );
+}
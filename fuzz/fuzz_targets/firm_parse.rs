@@ -0,0 +1,17 @@
+//! FIRM header parsing must reject malformed input with an error, never a panic.
+//!
+//! Run with `cargo +nightly fuzz run firm_parse` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use threemu::FirmHeader;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(firm) = FirmHeader::parse_and_validate(data) {
+        // A validated header's sections must be loadable without further checks
+        for (_, section) in firm.loadable_sections() {
+            assert!(data.get(section.file_range()).is_some());
+        }
+    }
+});